@@ -12,6 +12,15 @@ pub fn filedtask_derive(input: TokenStream) -> TokenStream {
     }
 }
 
+#[proc_macro_derive(FilteredTask)]
+pub fn filteredtask_derive(input: TokenStream) -> TokenStream {
+    let input = &parse_macro_input!(input as DeriveInput);
+    match generate_filteredtask(input) {
+        Ok(ts) => ts,
+        Err(err) => panic!("{}", err),
+    }
+}
+
 #[proc_macro_derive(IdAssignedTask)]
 pub fn idassignedtask_derive(input: TokenStream) -> TokenStream {
     let input = &parse_macro_input!(input as DeriveInput);
@@ -37,36 +46,34 @@ fn generate_filedtask(derive_input: &DeriveInput) -> Result<TokenStream, syn::Er
     let expanded = quote! {
         impl #struct_name {
             pub fn read_tasks(cfg: &MyConfig) -> Result<Vec<Self>, SigoError> {
-                let mut path = PathBuf::from(&cfg.data);
-                path.push(Self::FILE_NAME);
-                utils::create_file_if_not_exist(&path)?;
-                let tasks = std::fs::read_to_string(path.clone())
-                    .map_err(|e| SigoError::FileReadErr(path.clone(), e))?;
-                let tasks = serde_json::from_str::<Vec<Self>>(&tasks)
-                    .map_err(|e| SigoError::ParseStrToTasksErr(path.clone(), e))?;
-                Ok(tasks)
+                Self::read_tasks_from(cfg, Self::FILE_NAME)
             }
 
             pub fn write_tasks(cfg: &MyConfig, tasks: Vec<Self>) -> Result<(), SigoError> {
-                let mut path = PathBuf::from(&cfg.data);
-                path.push(Self::FILE_NAME);
-                utils::create_file_if_not_exist(&path)?;
-                let tmp_path = path.with_extension(format!("sigo-tmp-{}", std::process::id()));
-                let mut file = std::fs::File::create(&tmp_path)
-                    .map_err(|e| SigoError::FileCreateErr(tmp_path.clone(), e))?;
-                let tasks = serde_json::to_string(&tasks)?;
-                std::io::BufWriter::with_capacity(tasks.len(), &file)
-                    .write_all(tasks.as_bytes())
-                    .map_err(|e| SigoError::FileWriteErr(tmp_path.clone(), e))?;
-                file.flush()
-                    .map_err(|e| SigoError::FileWriteErr(tmp_path.clone(), e))?;
-                std::fs::rename(&tmp_path, &path)
-                    .map_err(|e| SigoError::FileRenameErr(tmp_path.clone(), path.clone(), e))?;
-                Ok(())
+                Self::write_tasks_to(cfg, Self::FILE_NAME, tasks)
+            }
+
+            /// Read the tasks stored in another collection, like an archive
+            pub fn read_tasks_from(cfg: &MyConfig, file_name: &str) -> Result<Vec<Self>, SigoError> {
+                let storage = storage::open(cfg);
+                serde_json::from_str::<Vec<Self>>(&storage.read(file_name)?)
+                    .map_err(|e| storage.parse_error(file_name, e))
+            }
+
+            pub fn write_tasks_to(
+                cfg: &MyConfig,
+                file_name: &str,
+                tasks: Vec<Self>,
+            ) -> Result<(), SigoError> {
+                storage::open(cfg).write(file_name, &serde_json::to_string(&tasks)?)
             }
 
             pub fn add_task(cfg: &MyConfig, task: Self) -> Result<Self, SigoError> {
                 let mut tasks = Self::read_tasks(cfg)?;
+                let task = Self {
+                    modified_at: chrono::Local::now(),
+                    ..task
+                };
                 tasks.push(task.clone());
                 Self::write_tasks(cfg, tasks)?;
                 Ok(task)
@@ -77,6 +84,30 @@ fn generate_filedtask(derive_input: &DeriveInput) -> Result<TokenStream, syn::Er
     Ok(expanded.into())
 }
 
+fn generate_filteredtask(derive_input: &DeriveInput) -> Result<TokenStream, syn::Error> {
+    // return Err if input is not struct
+    match &derive_input.data {
+        syn::Data::Struct(v) => v,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &derive_input.ident,
+                "Must be struct type",
+            ));
+        }
+    };
+
+    let struct_name = &derive_input.ident;
+    let expanded = quote! {
+        impl #struct_name {
+            pub fn matches(&self, filter: &Filter) -> bool {
+                filter.matches(&Task::from(self.clone()))
+            }
+        }
+    };
+
+    Ok(expanded.into())
+}
+
 fn generate_idassignedtask(derive_input: &DeriveInput) -> Result<TokenStream, syn::Error> {
     // return Err if input is not struct
     match &derive_input.data {
@@ -93,23 +124,31 @@ fn generate_idassignedtask(derive_input: &DeriveInput) -> Result<TokenStream, sy
     let expanded = quote! {
         impl #struct_name {
             fn get_by_id(cfg: &MyConfig, id: u32) -> Result<Self, SigoError> {
-                let tasks = Self::read_tasks(cfg)?;
-                tasks
-                    .into_iter()
-                    .find(|t| t.id == id)
-                    .ok_or(SigoError::TaskNotFound(id))
+                let storage = storage::open(cfg);
+                let task = storage
+                    .find_by_id(Self::FILE_NAME, id)?
+                    .ok_or(SigoError::TaskNotFound(id))?;
+                serde_json::from_value(task)
+                    .map_err(|e| storage.parse_error(Self::FILE_NAME, e))
             }
 
-            fn delete_by_id(cfg: &MyConfig, id: u32) -> Result<(), SigoError> {
-                let tasks = Self::read_tasks(cfg)?;
-                let updated_tasks = tasks
-                    .into_iter()
-                    .filter(|t| t.id != id)
-                    .collect::<Vec<Self>>();
-                Self::write_tasks(cfg, updated_tasks)?;
+            /// Replace the stored sigo which has the same id
+            fn update(&self, cfg: &MyConfig) -> Result<(), SigoError> {
+                let mut tasks = Self::read_tasks(cfg)?;
+                for task in tasks.iter_mut().filter(|t| t.id == self.id) {
+                    *task = Self {
+                        modified_at: chrono::Local::now(),
+                        ..self.clone()
+                    };
+                }
+                Self::write_tasks(cfg, tasks)?;
                 Ok(())
             }
 
+            pub fn is_active(&self) -> bool {
+                self.intervals.iter().any(|i| i.end.is_none())
+            }
+
             pub fn get_main_description(&self) -> String {
                 match &self.description {
                     Some(v) => v
@@ -120,23 +159,136 @@ fn generate_idassignedtask(derive_input: &DeriveInput) -> Result<TokenStream, sy
                 }
             }
 
-            pub fn complete(&self, cfg: &MyConfig) -> Result<CompletedTask, SigoError> {
-                let before_tasks = Self::read_tasks(cfg)?;
-                let after_tasks = before_tasks
-                    .into_iter()
-                    .filter(|t| t.id != self.id)
-                    .collect::<Vec<Self>>();
-                Self::write_tasks(cfg, after_tasks)?;
-                let completed_task = CompletedTask {
-                    description: <std::option::Option<Vec<std::string::String>> as Clone>::clone(
-                        &self.description,
-                    )
-                    .unwrap_or_default()
-                    .concat(),
+            pub fn display_description(&self) -> String {
+                let mut lines = vec![utils::display_option_vec_string(&self.description)];
+                lines.extend(self.annotations.iter().map(|a| a.to_string()));
+                lines.join("\n* ")
+            }
+
+            /// Indent the main description to show the depth in a tree
+            pub fn indented(mut self, depth: usize) -> Self {
+                if let Some(first_description) =
+                    self.description.as_mut().and_then(|v| v.get_mut(0))
+                {
+                    *first_description = format!("{}{}", "  ".repeat(depth), first_description);
+                }
+                self
+            }
+
+            /// Taskwarrior-style urgency from priority, due date, age, tags and blocking
+            pub fn urgency(&self, cfg: &MyConfig) -> f64 {
+                let coefficients = &cfg.urgency;
+                let now = chrono::Local::now();
+                let priority = match self.priority {
+                    Priority::H => coefficients.priority_h,
+                    Priority::M => coefficients.priority_m,
+                    Priority::L => coefficients.priority_l,
                 };
-                let mut completed_tasks = CompletedTask::read_tasks(cfg)?;
-                CompletedTask::add_task(cfg, completed_task.clone())?;
-                Ok(completed_task)
+                let due = match self.due {
+                    Some(due) => {
+                        let days_overdue = (now.date_naive() - due).num_days() as f64;
+                        if days_overdue >= 7.0 {
+                            1.0
+                        } else if days_overdue >= -14.0 {
+                            (days_overdue + 14.0) * 0.8 / 21.0 + 0.2
+                        } else {
+                            0.2
+                        }
+                    }
+                    None => 0.0,
+                };
+                let age = ((now - self.created_at).num_days() as f64 / 365.0).min(1.0);
+                let tags = match self.tags.len() {
+                    0 => 0.0,
+                    1 => 0.8,
+                    2 => 0.9,
+                    _ => 1.0,
+                };
+                let blocked = if self.is_blocked() { 1.0 } else { 0.0 };
+                priority
+                    + due * coefficients.due
+                    + age * coefficients.age
+                    + tags * coefficients.tags
+                    + blocked * coefficients.blocked
+            }
+
+            /// Values of the columns named by `MyConfig::extra_columns`
+            pub fn extra_fields(&self, cfg: &MyConfig) -> Vec<String> {
+                let mut fields = vec![format!("{:.1}", self.urgency(cfg))];
+                fields.extend(self.uda_fields(&cfg.udas));
+                fields
+            }
+
+            /// Names and values shown by `sigo info`
+            pub fn info_rows(&self) -> Vec<(String, String)> {
+                let mut rows = vec![
+                    ("ID".to_owned(), self.id.to_string()),
+                    ("UUID".to_owned(), self.uuid.to_string()),
+                    ("Description".to_owned(), self.get_main_description()),
+                    ("Priority".to_owned(), self.priority.to_string()),
+                    ("Tags".to_owned(), utils::display_tags(&self.tags)),
+                    ("Project".to_owned(), utils::display_option_string(&self.project)),
+                    ("Assignee".to_owned(), utils::display_option_string(&self.assignee)),
+                    ("Due".to_owned(), utils::display_option_date(&self.due)),
+                    ("Scheduled".to_owned(), utils::display_option_date(&self.scheduled)),
+                    ("Until".to_owned(), utils::display_option_date(&self.until)),
+                    ("Depends".to_owned(), utils::display_ids(&self.depends)),
+                    ("Parent".to_owned(), utils::display_option_u32(&self.parent_id)),
+                    ("Estimate".to_owned(), utils::display_option_minutes(&self.estimate)),
+                    ("Points".to_owned(), utils::display_option_u32(&self.points)),
+                    ("Tracked".to_owned(), utils::display_intervals(&self.intervals)),
+                    ("Attachments".to_owned(), self.attachments.join("\n")),
+                    ("Annotations".to_owned(), utils::display_annotations(&self.annotations)),
+                    ("Created".to_owned(), utils::display_datetime(&self.created_at)),
+                    ("Modified".to_owned(), utils::display_datetime(&self.modified_at)),
+                    ("Reviewed".to_owned(), utils::display_option_datetime(&self.reviewed_at)),
+                ];
+                rows.extend(utils::uda_rows(&self.udas));
+                rows
+            }
+
+            pub fn uda_fields(&self, names: &[String]) -> Vec<String> {
+                names
+                    .iter()
+                    .map(|name| match self.udas.get(name) {
+                        Some(serde_json::Value::String(value)) => value.to_owned(),
+                        Some(value) => value.to_string(),
+                        None => "".to_owned(),
+                    })
+                    .collect()
+            }
+
+            pub fn is_blocked(&self) -> bool {
+                !self.depends.is_empty()
+            }
+
+            pub fn is_expired(&self) -> bool {
+                match self.until {
+                    Some(until) => until < chrono::Local::now().date_naive(),
+                    None => false,
+                }
+            }
+
+            pub fn is_overdue(&self) -> bool {
+                match self.due {
+                    Some(due) => due < chrono::Local::now().date_naive(),
+                    None => false,
+                }
+            }
+
+            /// Whether the scheduled date, if any, has come
+            pub fn is_scheduled(&self) -> bool {
+                match self.scheduled {
+                    Some(scheduled) => scheduled <= chrono::Local::now().date_naive(),
+                    None => true,
+                }
+            }
+
+            pub fn attach(&self, cfg: &MyConfig, attachment: &str) -> Result<Self, SigoError> {
+                let mut task = self.clone();
+                task.attachments.push(utils::normalize_attachment(attachment)?);
+                task.update(cfg)?;
+                Ok(task)
             }
 
             pub fn annotate(&self, cfg: &MyConfig, annotate: &str) -> Result<(), SigoError> {
@@ -146,54 +298,54 @@ fn generate_idassignedtask(derive_input: &DeriveInput) -> Result<TokenStream, sy
                     .into_iter()
                     .filter(|t| t.id != id)
                     .collect::<Vec<Self>>();
-                let mut description =
-                    <std::option::Option<Vec<std::string::String>> as Clone>::clone(
-                        &self.description,
-                    )
-                    .unwrap_or_default();
-                description.push(annotate.to_owned());
+                let mut annotations = self.annotations.clone();
+                annotations.push(Annotation::new(annotate));
                 let annotated_task = Self {
-                    id: self.id,
-                    description: Some(description),
-                    priority: self.priority,
+                    annotations,
+                    modified_at: chrono::Local::now(),
+                    ..self.clone()
                 };
                 after_tasks.push(annotated_task);
                 Self::write_tasks(cfg, after_tasks)?;
                 Ok(())
             }
 
-        pub fn modify(
-            &self,
-            cfg: &MyConfig,
-            text: &Option<String>,
-            priority: Option<Priority>,
-        ) -> Result<Self, SigoError> {
-                let id = self.id;
-                let before_tasks = Self::read_tasks(cfg)?;
-                let mut after_tasks = before_tasks
-                    .into_iter()
-                    .filter(|t| t.id != id)
-                    .collect::<Vec<Self>>();
-                let mut description =
-                    <std::option::Option<Vec<std::string::String>> as Clone>::clone(
-                        &self.description,
-                    )
-                    .unwrap_or_default();
-                if let Some(text) = text {
-                    if let Some(first_description) = description.get_mut(0) {
-                        *first_description = text.to_string()
+            pub fn modify(
+                &self,
+                cfg: &MyConfig,
+                modification: &Modification,
+            ) -> Result<Self, SigoError> {
+                let mut task = self.clone();
+                if let Some(text) = &modification.description {
+                    match task.description.as_mut().and_then(|v| v.get_mut(0)) {
+                        Some(first_description) => *first_description = text.to_owned(),
+                        None => task.description = Some(vec![text.to_owned()]),
                     }
                 }
-                let new_task = Self {
-                    id: self.id,
-                    description: Some(description),
-                    priority: priority.unwrap_or(self.priority),
-                };
-                after_tasks.push(new_task.clone());
-                Self::write_tasks(cfg, after_tasks)?;
-            Ok(new_task)
+                task.priority = modification.priority.unwrap_or(task.priority);
+                modification.apply_tags(&mut task.tags);
+                if let Some(project) = &modification.project {
+                    task.project = project.clone();
+                }
+                if let Some(assignee) = &modification.assignee {
+                    task.assignee = assignee.clone();
+                }
+                modification.apply_udas(&mut task.udas);
+                task.due = modification.due.unwrap_or(task.due);
+                task.scheduled = modification.scheduled.unwrap_or(task.scheduled);
+                task.until = modification.until.unwrap_or(task.until);
+                task.estimate = modification.estimate.unwrap_or(task.estimate);
+                task.points = modification.points.unwrap_or(task.points);
+                if let Some(annotations) = &modification.annotations {
+                    task.annotations = annotations.clone();
+                }
+                if modification.reviewed {
+                    task.reviewed_at = Some(chrono::Local::now());
+                }
+                task.update(cfg)?;
+                Ok(task)
+            }
         }
-    }
 
     };
 
@@ -0,0 +1,155 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+
+use crate::{
+    config::MyConfig,
+    error::SigoError,
+    storage::{self, ensure_on_disk, Backend},
+    trash,
+};
+
+pub const DIR_NAME: &str = "backups";
+/// Backups kept without `keep_backups` in the config
+const DEFAULT_KEEP: usize = 10;
+
+/// Directory of the backups, each a directory named by when it was taken
+pub fn dir(cfg: &MyConfig) -> PathBuf {
+    PathBuf::from(&cfg.data).join(DIR_NAME)
+}
+
+/// Directory of the backup taken at the timestamp
+pub fn path(cfg: &MyConfig, timestamp: &str) -> Result<PathBuf, SigoError> {
+    ensure_on_disk(cfg, "restore-backup")?;
+    let backup = dir(cfg).join(timestamp);
+    if timestamp.contains(['/', '.']) || !backup.is_dir() {
+        return Err(SigoError::UnknownBackup(timestamp.to_owned()));
+    }
+    Ok(backup)
+}
+
+/// Timestamps of the backups, oldest first
+pub fn list(cfg: &MyConfig) -> Result<Vec<String>, SigoError> {
+    ensure_on_disk(cfg, "restore-backup")?;
+    let dir = dir(cfg);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(&dir).map_err(|e| SigoError::FileReadErr(dir.clone(), e))?;
+    let mut timestamps = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<String>>();
+    timestamps.sort();
+    Ok(timestamps)
+}
+
+/// Copy the files of the data directory to a new backup, dropping the oldest ones beyond those
+/// kept, and return the backup with the number of files copied
+pub fn create(cfg: &MyConfig) -> Result<(PathBuf, usize), SigoError> {
+    ensure_on_disk(cfg, "backup")?;
+    let data = PathBuf::from(&cfg.data);
+    let backup = dir(cfg).join(Local::now().format("%Y%m%d-%H%M%S").to_string());
+    if !storage::may_write()? {
+        return Ok((backup, files_in(&data)?.len()));
+    }
+    if backup.is_dir() {
+        fs::remove_dir_all(&backup).map_err(|e| SigoError::FileWriteErr(backup.clone(), e))?;
+    }
+    fs::create_dir_all(&backup).map_err(|e| SigoError::FileCreateErr(backup.clone(), e))?;
+    let copied = copy_files(&data, &backup)?;
+    rotate(cfg)?;
+    Ok((backup, copied))
+}
+
+/// Back up before a command removing sigos, unless they are kept in memory with nothing to back up
+pub fn safeguard(cfg: &MyConfig) -> Result<(), SigoError> {
+    if cfg.storage != Backend::Memory {
+        create(cfg)?;
+    }
+    Ok(())
+}
+
+/// Remove the oldest backups beyond the number kept, 0 keeping all of them
+fn rotate(cfg: &MyConfig) -> Result<(), SigoError> {
+    let keep = cfg.keep_backups.unwrap_or(DEFAULT_KEEP);
+    if keep == 0 {
+        return Ok(());
+    }
+    let timestamps = list(cfg)?;
+    let overflow = timestamps.len().saturating_sub(keep);
+    for timestamp in &timestamps[..overflow] {
+        let path = dir(cfg).join(timestamp);
+        fs::remove_dir_all(&path).map_err(|e| SigoError::FileWriteErr(path.clone(), e))?;
+    }
+    Ok(())
+}
+
+/// Files directly in the directory, leaving out those in directories like the backups
+fn files_in(dir: &Path) -> Result<Vec<PathBuf>, SigoError> {
+    let entries = fs::read_dir(dir).map_err(|e| SigoError::FileReadErr(dir.to_path_buf(), e))?;
+    Ok(entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file())
+        .collect())
+}
+
+fn copy_files(from: &Path, to: &Path) -> Result<usize, SigoError> {
+    let mut copied = 0;
+    for path in files_in(from)? {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        let target = to.join(name);
+        fs::copy(&path, &target).map_err(|e| SigoError::FileWriteErr(target.clone(), e))?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+fn rename(from: &Path, to: &Path) -> Result<(), SigoError> {
+    fs::rename(from, to)
+        .map_err(|e| SigoError::FileRenameErr(from.to_path_buf(), to.to_path_buf(), e))
+}
+
+/// Replace the data directory with the backup, copied to a directory next to it first so a
+/// failed copy leaves the data as it was, and return the number of files restored
+pub fn restore(cfg: &MyConfig, timestamp: &str) -> Result<usize, SigoError> {
+    let backup = path(cfg, timestamp)?;
+    if !storage::may_write()? {
+        return Ok(files_in(&backup)?.len());
+    }
+    let data = PathBuf::from(&cfg.data);
+    let sibling = |suffix: &str| {
+        let mut name = data.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}-{}", suffix, std::process::id()));
+        data.with_file_name(name)
+    };
+    let restored = sibling("restore");
+    let old = sibling("old");
+    fs::create_dir(&restored).map_err(|e| SigoError::FileCreateErr(restored.clone(), e))?;
+    let copied = match copy_files(&backup, &restored) {
+        Ok(copied) => copied,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&restored);
+            return Err(e);
+        }
+    };
+
+    rename(&data, &old)?;
+    if let Err(e) = rename(&restored, &data) {
+        rename(&old, &data)?;
+        return Err(e);
+    }
+    // The backups stay, including the one restored, and so does the trash
+    rename(&old.join(DIR_NAME), &dir(cfg))?;
+    if old.join(trash::DIR_NAME).is_dir() {
+        rename(&old.join(trash::DIR_NAME), &data.join(trash::DIR_NAME))?;
+    }
+    fs::remove_dir_all(&old).map_err(|e| SigoError::FileWriteErr(old.clone(), e))?;
+    Ok(copied)
+}
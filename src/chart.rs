@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+/// Width of the longest bar
+const WIDTH: usize = 50;
+
+/// One bar of a stacked bar chart
+pub struct Bar {
+    pub label: String,
+    /// Lengths of the stacked segments, drawn with the chart's marks in order
+    pub values: Vec<usize>,
+}
+
+/// Horizontal stacked bars scaled to the longest one, with the values after each bar
+pub fn stacked_bars(bars: &[Bar], marks: &[char]) -> String {
+    let max = bars
+        .iter()
+        .map(|b| b.values.iter().sum::<usize>())
+        .max()
+        .unwrap_or_default()
+        .max(1);
+    let label_width = bars.iter().map(|b| b.label.len()).max().unwrap_or_default();
+    bars.iter()
+        .map(|bar| {
+            let mut line = format!("{:<width$} |", bar.label, width = label_width);
+            let mut drawn = 0;
+            for (value, mark) in bar.values.iter().zip(marks.iter().cycle()) {
+                let length = value * WIDTH / max;
+                line.extend(std::iter::repeat_n(*mark, length));
+                drawn += length;
+            }
+            line.extend(std::iter::repeat_n(' ', WIDTH - drawn));
+            let values = bar
+                .values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>();
+            format!("{} {}", line, values.join(" / "))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Month as a grid of weeks starting on Monday, with a note after each day like a count of sigos
+pub fn month(first: NaiveDate, notes: &BTreeMap<NaiveDate, String>) -> String {
+    let mut lines = vec![
+        format!("{:^42}", first.format("%B %Y").to_string()),
+        ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]
+            .iter()
+            .map(|d| format!("{:<6}", d))
+            .collect::<String>(),
+    ];
+    let mut line = "      ".repeat(first.weekday().num_days_from_monday() as usize);
+    let mut day = first;
+    while day.month() == first.month() {
+        let note = notes.get(&day).map(String::as_str).unwrap_or_default();
+        line.push_str(&format!("{:>2}{:<4}", day.day(), note));
+        if day.weekday() == Weekday::Sun {
+            lines.push(std::mem::take(&mut line));
+        }
+        day = day + Days::new(1);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+        .iter()
+        .map(|l| l.trim_end())
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+/// Line of a tree with the lines under it
+pub struct Node {
+    pub label: String,
+    pub children: Vec<Node>,
+}
+
+/// Trees with the children of each node indented under it
+pub fn tree(roots: &[Node]) -> String {
+    let mut lines = Vec::new();
+    for root in roots {
+        lines.push(root.label.clone());
+        draw_children(&root.children, "", &mut lines);
+    }
+    lines.join("\n")
+}
+
+fn draw_children(nodes: &[Node], prefix: &str, lines: &mut Vec<String>) {
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        lines.push(format!("{}{}{}", prefix, branch, node.label));
+        draw_children(&node.children, &format!("{}{}", prefix, indent), lines);
+    }
+}
+
+/// Column of a board with its title and cards
+pub struct Lane {
+    pub title: String,
+    pub cards: Vec<String>,
+}
+
+/// Lanes side by side sharing the width, with cards cut to fit
+pub fn board(lanes: &[Lane], width: usize) -> String {
+    const GAP: &str = " | ";
+    let lane_width = (width.saturating_sub(GAP.len() * lanes.len().saturating_sub(1))
+        / lanes.len().max(1))
+    .max(8);
+    let fit = |text: &str| {
+        let mut text = text.chars().collect::<Vec<char>>();
+        if text.len() > lane_width {
+            text.truncate(lane_width - 1);
+            text.push('…');
+        }
+        let text = text.into_iter().collect::<String>();
+        format!("{:<width$}", text, width = lane_width)
+    };
+    let rows = lanes
+        .iter()
+        .map(|l| l.cards.len())
+        .max()
+        .unwrap_or_default();
+    let mut lines = vec![
+        lanes
+            .iter()
+            .map(|lane| fit(&format!("{} ({})", lane.title, lane.cards.len())))
+            .collect::<Vec<String>>()
+            .join(GAP)
+            .trim_end()
+            .to_owned(),
+        lanes
+            .iter()
+            .map(|_| "-".repeat(lane_width))
+            .collect::<Vec<String>>()
+            .join("-+-"),
+    ];
+    for row in 0..rows {
+        let line = lanes
+            .iter()
+            .map(|lane| fit(lane.cards.get(row).map(String::as_str).unwrap_or_default()))
+            .collect::<Vec<String>>()
+            .join(GAP);
+        lines.push(line.trim_end().to_owned());
+    }
+    lines.join("\n")
+}
@@ -1,108 +1,1491 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use chrono::{Datelike, Days, Duration, Local, NaiveDate};
+use clap::ValueEnum;
+use regex::RegexBuilder;
+use tabled::settings::Color;
+
 use crate::{
-    config::MyConfig,
+    backup,
+    chart::{board, month as month_grid, stacked_bars, tree, Bar, Lane},
+    config::{self, MyConfig},
+    context,
+    crypt::{self, Crypt},
+    doctor, edit,
     error::*,
-    task::{ReadyTask, Task, WaitingTask},
-    utils::tasks_to_string,
-    AppArg, Command,
+    filter::Filter,
+    journal, operations,
+    report::{
+        accomplishments, completion_streak, dependency_tree, standup, stats_rows, AgeBuckets,
+        BlockedTask, BlockingTask, BurndownPoint, InfoRow, ProjectProgress, ProjectSummary,
+        ProjectTime, SearchHit, TagCount, Tally, TimesheetRow, WaitingStay, WeekVelocity,
+    },
+    storage::{self, Mode},
+    task::{
+        CompletedTask, DeletedTask, Modification, OpenState, ReadyTask, RecurringTask, Task,
+        TaskRef, TaskRefs, WaitingTask,
+    },
+    trash,
+    utils::{
+        build_tree, confirm, display_age, display_intervals, display_minutes, group_by_project,
+        group_by_tags, grouped_tasks_to_string, open_with_platform, paginate, parse_date,
+        parse_words, prompt, tasks_to_colored_string, tasks_to_string, tasks_to_string_with,
+        Columns,
+    },
+    AppArg, Command, GroupBy, Priority, QueryAction,
 };
 
-// TODO: DRY get id and match pattern
+/// Reports available without defining them in the config, which may replace them
+const BUILTIN_REPORTS: [(&str, &str); 1] = [(
+    "weekly",
+    "(built-in) sigos completed in the last 7 days by project",
+)];
+
+/// Times a command is run when other sigos keep changing the sigos before it writes them
+const ATTEMPTS: usize = 3;
+
 pub fn run(cfg: &MyConfig, args: AppArg) -> Result<String> {
+    let mut attempt = 1;
+    loop {
+        storage::begin();
+        match run_once(cfg, args.clone()) {
+            // Nothing was written yet, so running it again from the start is safe
+            Err(SigoError::Conflict(_)) if !storage::wrote() && attempt < ATTEMPTS => attempt += 1,
+            output => return output.and_then(|output| with_dry_run_changes(cfg, output)),
+        }
+    }
+}
+
+/// The output followed by what a dry run would have changed
+fn with_dry_run_changes(cfg: &MyConfig, output: String) -> Result<String> {
+    if storage::mode() != Mode::DryRun {
+        return Ok(output);
+    }
+    let changes = storage::dry_run_changes(cfg)?;
+    let summary = match changes.is_empty() {
+        true => "Dry run, nothing was written: no sigos would change".to_owned(),
+        false => format!(
+            "Dry run, nothing was written; it would\n  {}",
+            changes.join("\n  ")
+        ),
+    };
+    Ok(format!("{}\n\n{}", output, summary))
+}
+
+/// Return the waiting sigos whose date has come and delete those past their until date, left as
+/// they are in read-only mode
+fn wake_and_expire(cfg: &MyConfig) -> Result<()> {
+    WaitingTask::wake_expired(cfg)?;
+    Task::expire_all(cfg)?;
+    Ok(())
+}
+
+fn run_once(cfg: &MyConfig, args: AppArg) -> Result<String> {
+    // Before anything else reads the sigos, which may be what it finds broken
+    if let Command::Doctor { fix } = args.command {
+        return doctor::run(cfg, fix);
+    }
+    // Written before the journal snapshot, so undoing a command does not put them back
+    if storage::mode() == Mode::Write {
+        wake_and_expire(cfg)?;
+    }
+    // Not journaled, as the journal is what they change
     match args.command {
+        Command::Undo {
+            last_destructive: false,
+        } => {
+            let entry = journal::undo(cfg)?;
+            return Ok(format!(
+                "Undid `sigo {}` ({})",
+                entry.command,
+                entry.entry.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        Command::Encrypt { key_file } => return encrypt(cfg, key_file),
+        Command::Decrypt => return decrypt(cfg),
+        _ => {}
+    }
+    let snapshot = journal::Snapshot::take(cfg)?;
+    let output = match args.command {
+        // Writes each decision as it is made, so an interrupted review keeps those made so far
+        command @ Command::Review { .. } => run_command(cfg, command),
+        command => storage::transaction(cfg, || {
+            // Staged with the changes of the command, which then sees them
+            if storage::mode() == Mode::DryRun {
+                wake_and_expire(cfg)?;
+            }
+            run_command(cfg, command)
+        }),
+    };
+    let command_line = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    snapshot.record(cfg, &command_line)?;
+    output
+}
+
+// TODO: DRY get id and match pattern
+fn run_command(cfg: &MyConfig, command: Command) -> Result<String> {
+    match command {
         Command::Add {
             description,
             priority,
             waiting,
+            due,
+            scheduled,
+            estimate,
+            points,
+            until,
+            recur,
+            depends,
+            parent,
         } => {
-            let new_task = ReadyTask::add_task(cfg, ReadyTask::new(cfg, &description, priority)?)?;
+            let words = parse_words(&description, &cfg.udas);
+            let depends = depends
+                .iter()
+                .flat_map(|refs| refs.0.iter())
+                .map(|r| Task::resolve_id(cfg, r))
+                .collect::<Result<Vec<u32>>>()?;
+            let parent = parent.map(|r| Task::resolve_id(cfg, &r)).transpose()?;
+            let mut new_task = ReadyTask {
+                due,
+                scheduled,
+                until,
+                tags: words.tags,
+                project: words.project.filter(|p| !p.is_empty()),
+                assignee: cfg
+                    .resolve_assignee(words.assignee.filter(|a| !a.is_empty()))
+                    .or_else(|| cfg.assignee.clone()),
+                udas: words
+                    .udas
+                    .into_iter()
+                    .map(|(k, v)| (k, serde_json::Value::String(v)))
+                    .collect(),
+                depends,
+                parent_id: parent,
+                estimate: estimate.map(|e| e.num_minutes()),
+                points,
+                ..ReadyTask::new(cfg, &words.description, priority)?
+            };
+            if let Some(recur) = recur {
+                let template = RecurringTask::new(cfg, &new_task, recur)?;
+                new_task.template = Some(template.id);
+                RecurringTask::add_task(cfg, template)?;
+            }
+            let new_task = ReadyTask::add_task(cfg, new_task)?;
             if waiting {
-                let new_task = new_task.wait(cfg, &None)?;
+                let new_task = new_task.wait(cfg, &None, None, None)?;
                 Ok(format!("Created waiting sigo {}", new_task.id))
             } else {
                 Ok(format!("Created sigo {}", new_task.id))
             }
         }
-        Command::Modify { id, text, priority } => {
-            let task = Task::get_by_id(cfg, id)?;
-            match task {
-                Task::Ready(task) => {
-                    task.modify(cfg, &text, priority)?;
-                    Ok(format!("Modify sigo {}", task.id))
-                }
-                Task::Waiting(task) => {
-                    task.modify(cfg, &text, priority)?;
-                    Ok(format!("Modify sigo {}", task.id))
+        Command::Modify {
+            id,
+            words,
+            text,
+            priority,
+            due,
+            scheduled,
+            until,
+            estimate,
+            points,
+        } => {
+            let words = modification_from_words(cfg, &words);
+            let modification = Modification {
+                description: text.or(words.description),
+                priority,
+                due: due.map(Some),
+                scheduled: scheduled.map(Some),
+                until: until.map(Some),
+                estimate: estimate.map(|e| Some(e.num_minutes())),
+                points: points.map(Some),
+                ..words
+            };
+            for_each_task(cfg, [id], |task| {
+                let before_priority = task.priority();
+                let after_priority = task.modify(cfg, &modification)?.priority();
+                let name = task.name();
+                if before_priority != after_priority {
+                    Ok(format!(
+                        "Modify sigo {} (priority {} -> {})",
+                        name, before_priority, after_priority
+                    ))
+                } else {
+                    Ok(format!("Modify sigo {}", name))
                 }
-                Task::Completed(_) => panic!(),
-            }
+            })
         }
-        Command::Done { id } => {
-            let task = Task::get_by_id(cfg, id)?;
-            match task {
-                Task::Ready(task) => {
-                    task.complete(cfg)?;
-                    Ok(format!("Completed sigo {}", task.id))
+        Command::Duplicate { id, words } => for_each_task(cfg, [id], |task| {
+            let new_task = ReadyTask::add_task(cfg, task.duplicate(cfg)?)?;
+            new_task.modify(cfg, &modification_from_words(cfg, &words))?;
+            Ok(format!("Created sigo {} from {}", new_task.id, task.name()))
+        }),
+        Command::Info { ids } => for_each_task(cfg, ids, |task| {
+            Ok(tasks_to_string(InfoRow::from_task(
+                &task,
+                &ReadyTask::read_tasks(cfg)?,
+                &WaitingTask::read_tasks(cfg)?,
+            )))
+        }),
+        Command::Edit { ids } => for_each_task(cfg, ids, |task| {
+            let Some(modification) = edit::edit(cfg, &task)? else {
+                return Ok("No changes".to_owned());
+            };
+            task.modify(cfg, &modification)?;
+            Ok(format!("Edited sigo {}", task.name()))
+        }),
+        Command::Log {
+            description,
+            priority,
+            estimate,
+            points,
+        } => {
+            let words = parse_words(&description, &cfg.udas);
+            let task = CompletedTask {
+                tags: words.tags,
+                project: words.project.filter(|p| !p.is_empty()),
+                assignee: cfg
+                    .resolve_assignee(words.assignee.filter(|a| !a.is_empty()))
+                    .or_else(|| cfg.assignee.clone()),
+                udas: words
+                    .udas
+                    .into_iter()
+                    .map(|(k, v)| (k, serde_json::Value::String(v)))
+                    .collect(),
+                estimate: estimate.map(|e| e.num_minutes()),
+                points,
+                ..CompletedTask::new(&words.description, priority)
+            };
+            let task = CompletedTask::add_task(cfg, task)?;
+            Ok(format!("Logged sigo {}", task.uuid))
+        }
+        Command::Done { targets, yes } => {
+            let ids = targets
+                .iter()
+                .map(|t| t.parse::<TaskRefs>())
+                .collect::<std::result::Result<Vec<_>, _>>();
+            let ids = match ids {
+                Ok(ids) => ids,
+                Err(_) => {
+                    let filter = filter_with_context(cfg, &targets)?;
+                    let mut tasks = ReadyTask::read_tasks(cfg)?;
+                    tasks.retain(|t| t.matches(&filter));
+                    if tasks.is_empty() {
+                        return Ok("No matching sigos".to_owned());
+                    }
+                    if !yes {
+                        println!("{}", tasks_to_string(&tasks));
+                        if !confirm(&format!("Complete {} sigos?", tasks.len()))? {
+                            return Ok("Done cancelled".to_owned());
+                        }
+                    }
+                    tasks
+                        .iter()
+                        .map(|t| TaskRefs(vec![TaskRef::Id(t.id)]))
+                        .collect()
                 }
-                Task::Waiting(task) => {
-                    task.complete(cfg)?;
-                    Ok(format!("Completed sigo {}", task.id))
+            };
+            for_each_task(cfg, ids, |task| complete_task(cfg, task))
+        }
+        Command::Restore { ids } => for_each_task(cfg, ids, |task| match task {
+            Task::Completed(completed) => {
+                let task = completed.restore(cfg)?;
+                match completed.id.filter(|id| *id != task.id) {
+                    Some(former) => Ok(format!(
+                        "Restored sigo {} as {}, since {} is taken",
+                        former, task.id, former
+                    )),
+                    None => Ok(format!("Restored sigo {}", task.id)),
                 }
-                Task::Completed(_) => panic!(),
             }
+            task => Err(SigoError::NotCompleted(task.uuid())),
+        }),
+        Command::Start { ids } => for_each_task(cfg, ids, |task| match task {
+            Task::Ready(task) => {
+                let task = task.start(cfg)?;
+                Ok(format!(
+                    "Started sigo {} '{}'",
+                    task.id,
+                    task.get_main_description()
+                ))
+            }
+            Task::Waiting(_) => Err(SigoError::NotReady(task.name())),
+            task => Err(SigoError::NotOpen(task.uuid())),
+        }),
+        Command::Stop => {
+            let task = Task::active(cfg)?.ok_or(SigoError::NoActiveTask)?;
+            let task = task.stop(cfg)?;
+            Ok(format!(
+                "Stopped sigo {} (tracked {})",
+                task.id,
+                display_intervals(&task.intervals)
+            ))
         }
-        Command::Wait { id, text } => {
-            let task = Task::get_by_id(cfg, id)?;
-            match task {
-                Task::Ready(task) => {
-                    let task = task.wait(cfg, &text)?;
-                    Ok(format!(
-                        "Waiting sigo {} '{}'",
-                        task.id,
-                        task.get_main_description()
+        Command::Delete { ids, keep_series } => {
+            if ids.iter().map(|refs| refs.0.len()).sum::<usize>() > 1 {
+                trash::snapshot(
+                    cfg,
+                    &[
+                        ReadyTask::FILE_NAME,
+                        WaitingTask::FILE_NAME,
+                        DeletedTask::FILE_NAME,
+                        RecurringTask::FILE_NAME,
+                    ]
+                    .map(str::to_owned),
+                )?;
+            }
+            for_each_task(cfg, ids, |task| delete_task(cfg, task, keep_series))
+        }
+        Command::Review { days } => {
+            let stale_before = Local::now() - Duration::days(days);
+            let uuids = WaitingTask::read_tasks(cfg)?
+                .iter()
+                .filter(|t| t.reviewed_at.is_none_or(|r| r < stale_before))
+                .map(|t| t.uuid)
+                .chain(
+                    ReadyTask::read_tasks(cfg)?
+                        .iter()
+                        .filter(|t| t.modified_at < stale_before)
+                        .map(|t| t.uuid),
+                )
+                .collect::<Vec<_>>();
+            if uuids.is_empty() {
+                return Ok("Nothing to review".to_owned());
+            }
+            let mut reviewed = 0;
+            for (n, uuid) in uuids.iter().enumerate() {
+                // Earlier decisions may have changed the sigo
+                let Ok(task) = Task::get_by_ref(cfg, &TaskRef::Uuid(uuid.to_string())) else {
+                    continue;
+                };
+                println!(
+                    "\n[{}/{}]{}",
+                    n + 1,
+                    uuids.len(),
+                    tasks_to_string(InfoRow::from_task(
+                        &task,
+                        &ReadyTask::read_tasks(cfg)?,
+                        &WaitingTask::read_tasks(cfg)?,
                     ))
+                );
+                match storage::transaction(cfg, || review_task(cfg, task))? {
+                    Some(output) => {
+                        println!("{}", output);
+                        reviewed += 1;
+                    }
+                    None => break,
                 }
-                Task::Waiting(task) => Ok(format!("Already waiting sigo {}", task.id)),
-                Task::Completed(_) => panic!(),
             }
+            Ok(format!("Reviewed {} of {} sigos", reviewed, uuids.len()))
+        }
+        Command::Purge {
+            yes,
+            before,
+            keep_last,
+            archive,
+        } if before.is_some() || keep_last.is_some() => {
+            let (old, kept) =
+                CompletedTask::partition_old(CompletedTask::read_tasks(cfg)?, before, keep_last);
+            if old.is_empty() {
+                return Ok("No completed sigos to purge".to_owned());
+            }
+            let verb = if archive {
+                "Archive"
+            } else {
+                "Permanently remove"
+            };
+            // Nothing to confirm in a dry run, whose summary lists the sigos it would purge
+            if !yes
+                && storage::mode() != Mode::DryRun
+                && !confirm(&format!("{} {} completed sigos?", verb, old.len()))?
+            {
+                return Ok("Purge cancelled".to_owned());
+            }
+            let count = old.len();
+            backup::safeguard(cfg)?;
+            let mut names = vec![CompletedTask::FILE_NAME.to_owned()];
+            if archive {
+                names.extend(
+                    old.iter()
+                        .map(|t| CompletedTask::archive_name(t.done_at().year())),
+                );
+                names.sort();
+                names.dedup();
+            }
+            trash::snapshot(cfg, &names)?;
+            if archive {
+                CompletedTask::archive(cfg, old)?;
+            }
+            CompletedTask::write_tasks(cfg, kept)?;
+            Ok(format!("Purged {} completed sigos", count))
         }
-        Command::Back { id, text } => {
-            let task = Task::get_by_id(cfg, id)?;
-            match task {
-                Task::Ready(task) => Ok(format!(
-                    "Already ready sigo {} '{}'",
+        Command::Purge { yes, .. } => {
+            let tasks = DeletedTask::read_tasks(cfg)?;
+            if tasks.is_empty() {
+                return Ok("No deleted sigos".to_owned());
+            }
+            if !yes
+                && storage::mode() != Mode::DryRun
+                && !confirm(&format!(
+                    "Permanently remove {} deleted sigos?",
+                    tasks.len()
+                ))?
+            {
+                return Ok("Purge cancelled".to_owned());
+            }
+            backup::safeguard(cfg)?;
+            trash::snapshot(cfg, &[DeletedTask::FILE_NAME.to_owned()])?;
+            DeletedTask::write_tasks(cfg, Vec::new())?;
+            Ok(format!("Purged {} sigos", tasks.len()))
+        }
+        Command::Archive { days } => {
+            let before = Local::now().date_naive() - Days::new(days);
+            let (old, kept) =
+                CompletedTask::partition_old(CompletedTask::read_tasks(cfg)?, Some(before), None);
+            let count = old.len();
+            CompletedTask::archive(cfg, old)?;
+            CompletedTask::write_tasks(cfg, kept)?;
+            Ok(format!("Archived {} completed sigos", count))
+        }
+        Command::Wait {
+            ids,
+            text,
+            until,
+            reason,
+        } => for_each_task(cfg, ids, |task| match task {
+            Task::Ready(task) => {
+                let task = task.wait(cfg, &text, until, reason.clone())?;
+                Ok(format!(
+                    "Waiting sigo {} '{}'",
                     task.id,
                     task.get_main_description()
-                )),
-                Task::Waiting(task) => {
-                    let task = task.back(cfg, &text)?;
-                    Ok(format!("Returning sigo {}", task.id))
-                }
-                Task::Completed(_) => panic!(),
+                ))
+            }
+            Task::Waiting(_) => Err(SigoError::AlreadyWaiting(task.name())),
+            Task::Completed(_) => Err(SigoError::AlreadyCompleted(task.name())),
+            task => Err(SigoError::NotOpen(task.uuid())),
+        }),
+        Command::Snooze { id, until } => for_each_task(cfg, [id], |task| match task {
+            Task::Ready(task) => {
+                let task = task.wait(cfg, &None, Some(until), None)?;
+                Ok(format!("Snoozed sigo {} until {}", task.id, until))
+            }
+            Task::Waiting(task) => {
+                let task = task.snooze(cfg, until)?;
+                Ok(format!("Snoozed sigo {} until {}", task.id, until))
+            }
+            task => Err(SigoError::NotOpen(task.uuid())),
+        }),
+        Command::Back { ids, text } => for_each_task(cfg, ids, |task| match task {
+            Task::Waiting(task) => {
+                let task = task.back(cfg, &text)?;
+                Ok(format!("Returning sigo {}", task.id))
+            }
+            task => Err(SigoError::NotWaiting(task.name())),
+        }),
+        Command::Annotate { id, text } => for_each_task(cfg, [id], |task| match task {
+            Task::Ready(task) => {
+                task.annotate(cfg, &text)?;
+                Ok(format!("Annotated sigo {}", task.id))
+            }
+            Task::Waiting(task) => {
+                task.annotate(cfg, &text)?;
+                Ok(format!("Annotated sigo {}", task.id))
+            }
+            task => Err(SigoError::NotOpen(task.uuid())),
+        }),
+        Command::Append { id, text } => for_each_task(cfg, [id], |task| {
+            let description = format!("{} {}", task.description(), text.trim());
+            task.set_description(cfg, &description)?;
+            Ok(format!("Appended to sigo {}", task.name()))
+        }),
+        Command::Prepend { id, text } => for_each_task(cfg, [id], |task| {
+            let description = format!("{} {}", text.trim(), task.description());
+            task.set_description(cfg, &description)?;
+            Ok(format!("Prepended to sigo {}", task.name()))
+        }),
+        Command::Denotate { id, pattern } => for_each_task(cfg, [id], |task| {
+            let annotations = task
+                .annotations()
+                .iter()
+                .filter(|a| !a.description.contains(&pattern))
+                .cloned()
+                .collect::<Vec<_>>();
+            let removed = task.annotations().len() - annotations.len();
+            if removed == 0 {
+                return Err(SigoError::NoMatchingAnnotation(pattern.clone()));
+            }
+            let modification = Modification {
+                annotations: Some(annotations),
+                ..Default::default()
+            };
+            task.modify(cfg, &modification)?;
+            Ok(format!(
+                "Removed {} annotation(s) from sigo {}",
+                removed,
+                task.name()
+            ))
+        }),
+        Command::Attach { id, attachment } => for_each_task(cfg, [id], |task| match task {
+            Task::Ready(task) => {
+                task.attach(cfg, &attachment)?;
+                Ok(format!("Attached to sigo {}", task.id))
             }
+            Task::Waiting(task) => {
+                task.attach(cfg, &attachment)?;
+                Ok(format!("Attached to sigo {}", task.id))
+            }
+            task => Err(SigoError::NotOpen(task.uuid())),
+        }),
+        Command::Open { ids } => for_each_task(cfg, ids, |task| {
+            let (id, attachments) = match task {
+                Task::Ready(task) => (task.id, task.attachments),
+                Task::Waiting(task) => (task.id, task.attachments),
+                task => return Err(SigoError::NotOpen(task.uuid())),
+            };
+            let attachment = attachments.first().ok_or(SigoError::NoAttachment(id))?;
+            open_with_platform(attachment)?;
+            Ok(format!("Opened {}", attachment))
+        }),
+        Command::List {
+            filter,
+            group,
+            group_by,
+            tree,
+            all,
+            urgency,
+            sort,
+            columns,
+            limit,
+            page,
+        } => {
+            let columns = Columns::select::<ReadyTask>(
+                cfg.extra_columns(),
+                &cfg.report_columns("list", columns),
+            )?;
+            let filter = filter_with_context(cfg, &filter)?;
+            let mut tasks = ReadyTask::read_tasks(cfg)?;
+            tasks.retain(|t| t.matches(&filter) && (all || t.is_scheduled()));
+            if urgency {
+                tasks.sort_by(|a, b| b.urgency(cfg).total_cmp(&a.urgency(cfg)));
+            } else if let Some(sort) = sort.map_or_else(|| cfg.sort(), |sort| Ok(Some(sort)))? {
+                tasks = sort.sort(cfg, tasks);
+            } else {
+                tasks.sort_by_key(|t| (t.is_blocked(), !t.is_overdue(), t.priority));
+            }
+            let (tasks, footer) = match limit.or(cfg.limit).filter(|limit| *limit > 0) {
+                Some(limit) => paginate(tasks, limit, page),
+                None => (tasks, None),
+            };
+            let list = if tree {
+                let tasks = build_tree(tasks, |t| t.id, |t| t.parent_id)
+                    .into_iter()
+                    .map(|(depth, t)| t.indented(depth));
+                tasks_to_string_with(tasks, &columns, |t| t.extra_fields(cfg))
+            } else if let Some(by) = group_by.or(group.then_some(GroupBy::Project)) {
+                let groups = match by {
+                    GroupBy::Project => group_by_project(tasks, |t| &t.project),
+                    GroupBy::Tag => group_by_tags(tasks, |t| &t.tags),
+                };
+                grouped_tasks_to_string(groups, &columns, |t| t.extra_fields(cfg))
+            } else {
+                tasks_to_string_with(tasks, &columns, |t| t.extra_fields(cfg))
+            };
+            Ok(match footer {
+                Some(footer) => format!("{}\n{}", list, footer),
+                None => list,
+            })
         }
-        Command::Annotate { id, text } => {
-            let task = Task::get_by_id(cfg, id)?;
-            match task {
-                Task::Ready(task) => {
-                    task.annotate(cfg, &text)?;
-                    Ok(format!("Annotated sigo {}", task.id))
-                }
-                Task::Waiting(task) => {
-                    task.annotate(cfg, &text)?;
-                    Ok(format!("Annotated sigo {}", task.id))
+        Command::Count {
+            filter,
+            ready,
+            waiting,
+            completed,
+            deleted,
+        } => {
+            let filter = filter_with_context(cfg, &filter)?;
+            let ready = ready || !(waiting || completed || deleted);
+            if let Filter::All = filter {
+                // Counted by the storage without reading every sigo
+                let storage = storage::open(cfg);
+                let mut count = 0;
+                for (counted, name) in [
+                    (ready, ReadyTask::FILE_NAME),
+                    (waiting, WaitingTask::FILE_NAME),
+                    (completed, CompletedTask::FILE_NAME),
+                    (deleted, DeletedTask::FILE_NAME),
+                ] {
+                    if counted {
+                        count += storage.count(name)?;
+                    }
                 }
-                Task::Completed(_) => panic!(),
+                return Ok(count.to_string());
+            }
+            let mut count = 0;
+            if ready {
+                let tasks = ReadyTask::read_tasks(cfg)?;
+                count += tasks.iter().filter(|t| t.matches(&filter)).count();
+            }
+            if waiting {
+                let tasks = WaitingTask::read_tasks(cfg)?;
+                count += tasks.iter().filter(|t| t.matches(&filter)).count();
             }
+            if completed {
+                let tasks = CompletedTask::read_tasks(cfg)?;
+                count += tasks.iter().filter(|t| t.matches(&filter)).count();
+            }
+            if deleted {
+                let tasks = DeletedTask::read_tasks(cfg)?;
+                count += tasks.iter().filter(|t| t.matches(&filter)).count();
+            }
+            Ok(count.to_string())
+        }
+        Command::Ids {
+            filter,
+            waiting,
+            newline,
+        } => {
+            let filter = filter_with_context(cfg, &filter)?;
+            let mut ids = ReadyTask::read_tasks(cfg)?
+                .iter()
+                .filter(|t| t.matches(&filter))
+                .map(|t| t.id)
+                .collect::<Vec<u32>>();
+            if waiting {
+                ids.extend(
+                    WaitingTask::read_tasks(cfg)?
+                        .iter()
+                        .filter(|t| t.matches(&filter))
+                        .map(|t| t.id),
+                );
+            }
+            ids.sort();
+            let separator = if newline { "\n" } else { " " };
+            Ok(ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<String>>()
+                .join(separator))
         }
-        Command::List => {
+        Command::Next {
+            filter,
+            n,
+            brief,
+            columns,
+        } => {
+            let columns = Columns::select::<ReadyTask>(
+                cfg.extra_columns(),
+                &cfg.report_columns("next", columns),
+            )?;
+            let filter = filter_with_context(cfg, &filter)?;
             let mut tasks = ReadyTask::read_tasks(cfg)?;
-            tasks.sort_by(|a, b| a.priority.cmp(&b.priority));
+            tasks.retain(|t| t.matches(&filter) && t.is_scheduled() && !t.is_blocked());
+            tasks.sort_by(|a, b| b.urgency(cfg).total_cmp(&a.urgency(cfg)));
+            tasks.truncate(n);
+            if brief {
+                return Ok(tasks
+                    .iter()
+                    .map(|t| format!("{} {}", t.id, t.get_main_description()))
+                    .collect::<Vec<String>>()
+                    .join("\n"));
+            }
+            Ok(tasks_to_string_with(tasks, &columns, |t| {
+                t.extra_fields(cfg)
+            }))
+        }
+        Command::All {
+            filter,
+            sort,
+            columns,
+            archived,
+        } => {
+            let columns =
+                Columns::select::<Task>(cfg.extra_columns(), &cfg.report_columns("all", columns))?;
+            let filter = filter_with_context(cfg, &filter)?;
+            let mut tasks = ReadyTask::read_tasks(cfg)?
+                .into_iter()
+                .map(Task::Ready)
+                .chain(WaitingTask::read_tasks(cfg)?.into_iter().map(Task::Waiting))
+                .filter(|t| filter.matches(t))
+                .chain(
+                    CompletedTask::read_matching(cfg, archived, |t| t.matches(&filter))?
+                        .into_iter()
+                        .map(Task::Completed),
+                )
+                .collect::<Vec<Task>>();
+            if let Some(sort) = sort {
+                tasks = sort.sort(cfg, tasks);
+            }
+            Ok(tasks_to_string_with(tasks, &columns, |t| {
+                t.extra_fields(cfg)
+            }))
+        }
+        Command::Report { name: None } => Ok(cfg
+            .reports
+            .iter()
+            .map(|(name, report)| format!("{}: {}", name, report.filter))
+            .chain(
+                BUILTIN_REPORTS
+                    .iter()
+                    .filter(|(name, _)| !cfg.reports.contains_key(*name))
+                    .map(|(name, about)| format!("{}: {}", name, about)),
+            )
+            .collect::<Vec<String>>()
+            .join("\n")),
+        Command::Report { name: Some(name) }
+            if name == "weekly" && !cfg.reports.contains_key(&name) =>
+        {
+            Ok(accomplishments(
+                &CompletedTask::read_tasks(cfg)?,
+                Local::now() - Duration::days(7),
+            ))
+        }
+        Command::Report { name: Some(name) } => {
+            let report = cfg
+                .reports
+                .get(&name)
+                .ok_or(SigoError::UnknownReport(name))?;
+            let columns = Columns::select::<Task>(cfg.extra_columns(), &report.columns())?;
+            let filter = filter_with_context(cfg, std::slice::from_ref(&report.filter))?;
+            let mut tasks = Task::read_all(cfg)?;
+            tasks.retain(|t| filter.matches(t));
+            match report.sort()? {
+                Some(sort) => tasks = sort.sort(cfg, tasks),
+                None => tasks.sort_by(|a, b| b.urgency(cfg).total_cmp(&a.urgency(cfg))),
+            }
+            Ok(tasks_to_string_with(tasks, &columns, |t| {
+                t.extra_fields(cfg)
+            }))
+        }
+        Command::Query { action: None } => Ok(cfg
+            .queries
+            .iter()
+            .map(|(name, filter)| format!("@{}: {}", name, filter))
+            .collect::<Vec<String>>()
+            .join("\n")),
+        Command::Query {
+            action: Some(QueryAction::Save { name, filter }),
+        } => {
+            if !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+                || name.is_empty()
+            {
+                return Err(SigoError::InvalidQueryName(name));
+            }
+            Filter::parse(cfg, &filter)?;
+            let filter = filter.join(" ");
+            config::update(|cfg| {
+                cfg.queries.insert(name.clone(), filter);
+            })?;
+            Ok(format!("Saved query @{}", name))
+        }
+        Command::Query {
+            action: Some(QueryAction::Remove { name }),
+        } => {
+            if !cfg.queries.contains_key(&name) {
+                return Err(SigoError::UnknownQuery(name));
+            }
+            config::update(|cfg| {
+                cfg.queries.remove(&name);
+            })?;
+            Ok(format!("Removed query @{}", name))
+        }
+        Command::Context { name } => match name.as_deref() {
+            Some("none") => {
+                context::write_active(cfg, None)?;
+                Ok("Cleared context".to_owned())
+            }
+            Some(name) => {
+                context::write_active(cfg, Some(name))?;
+                Ok(format!("Set context {}", name))
+            }
+            None => match context::read_active(cfg)? {
+                Some(name) => match cfg.contexts.get(&name) {
+                    Some(filter) => Ok(format!("Context {}: {}", name, filter)),
+                    None => Err(SigoError::UnknownContext(name)),
+                },
+                None => Ok("No context".to_owned()),
+            },
+        },
+        Command::Recur => {
+            let created = RecurringTask::expand(cfg)?;
+            Ok(format!("Created {} recurring sigos", created.len()))
+        }
+        Command::Completed {
+            filter,
+            since,
+            from,
+            to,
+            archived,
+            columns,
+        } => {
+            let columns = Columns::select::<CompletedTask>(
+                Vec::new(),
+                &cfg.report_columns("completed", columns),
+            )?;
+            let filter = Filter::parse(cfg, &filter)?.and(Filter::completed_between(from, to));
+            let since = since.map(|since| Local::now() - since);
+            let mut tasks = CompletedTask::read_matching(cfg, archived, |t| {
+                t.matches(&filter)
+                    && since.is_none_or(|from| t.completed_at.is_some_and(|c| c >= from))
+            })?;
+            tasks.sort_by_key(|t| t.completed_at);
+            Ok(tasks_to_string_with(tasks, &columns, |_| Vec::new()))
+        }
+        Command::Stale { days } => {
+            let stale_before = Local::now() - Duration::days(days);
+            let mut tasks = ReadyTask::read_tasks(cfg)?;
+            tasks.retain(|t| t.modified_at < stale_before);
+            tasks.sort_by_key(|t| t.modified_at);
+            let mut extra = cfg.extra_columns();
+            extra.push("untouched".to_owned());
+            Ok(tasks_to_string_with(tasks, &Columns::all(extra), |t| {
+                let mut fields = t.extra_fields(cfg);
+                fields.push(display_age(&t.modified_at));
+                fields
+            }))
+        }
+        Command::Tags => Ok(tasks_to_string(TagCount::from_tasks(&Task::read_all(cfg)?))),
+        Command::Projects { archived } => Ok(tasks_to_string(ProjectProgress::from_tasks(
+            &ReadyTask::read_tasks(cfg)?,
+            &WaitingTask::read_tasks(cfg)?,
+            &CompletedTask::read_tasks_with_archive(cfg, archived)?,
+        ))),
+        Command::Board { width } => {
+            let today = Local::now().date_naive();
+            let card = |id: u32, description: String| format!("{} {}", id, description);
+            let (active, ready): (Vec<ReadyTask>, Vec<ReadyTask>) = ReadyTask::read_tasks(cfg)?
+                .into_iter()
+                .partition(|t| t.is_active());
+            let lanes = [
+                Lane {
+                    title: "Ready".to_owned(),
+                    cards: ready
+                        .into_iter()
+                        .map(|t| card(t.id, t.get_main_description()))
+                        .collect(),
+                },
+                Lane {
+                    title: "Active".to_owned(),
+                    cards: active
+                        .into_iter()
+                        .map(|t| card(t.id, t.get_main_description()))
+                        .collect(),
+                },
+                Lane {
+                    title: "Waiting".to_owned(),
+                    cards: WaitingTask::read_tasks(cfg)?
+                        .into_iter()
+                        .map(|t| card(t.id, t.get_main_description()))
+                        .collect(),
+                },
+                Lane {
+                    title: "Done today".to_owned(),
+                    cards: CompletedTask::read_tasks(cfg)?
+                        .into_iter()
+                        .filter(|t| t.completed_at.is_some_and(|c| c.date_naive() == today))
+                        .map(|t| t.description)
+                        .collect(),
+                },
+            ];
+            let width = width
+                .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+                .unwrap_or(80);
+            Ok(board(&lanes, width))
+        }
+        Command::Dashboard => {
+            let mut ready = ReadyTask::read_tasks(cfg)?;
+            let waiting = WaitingTask::read_tasks(cfg)?;
+            let completed = CompletedTask::read_tasks(cfg)?;
+            let deleted = DeletedTask::read_tasks(cfg)?;
+            let overdue = ready.iter().filter(|t| t.is_overdue()).count()
+                + waiting.iter().filter(|t| t.is_overdue()).count();
+            let active = ready
+                .iter()
+                .find_map(|t| {
+                    let start = t.intervals.iter().find(|i| i.end.is_none())?.start;
+                    Some(format!(
+                        "{} {} ({})",
+                        t.id,
+                        t.get_main_description(),
+                        display_age(&start)
+                    ))
+                })
+                .unwrap_or_else(|| "none".to_owned());
+            let counts = format!(
+                "Ready {}  Waiting {}  Completed {}  Deleted {}",
+                ready.len(),
+                waiting.len(),
+                completed.len(),
+                deleted.len()
+            );
+            let streak = completion_streak(&completed);
+            ready.sort_by(|a, b| b.urgency(cfg).total_cmp(&a.urgency(cfg)));
+            ready.truncate(5);
+            let columns = Columns::select::<ReadyTask>(
+                cfg.extra_columns(),
+                &["id", "P", "description", "due", "urg"].map(str::to_owned),
+            )?;
+            Ok(format!(
+                "{}\nOverdue {}\nActive: {}\nStreak: {} {}\n\nMost urgent\n{}",
+                counts,
+                overdue,
+                active,
+                streak,
+                if streak == 1 { "day" } else { "days" },
+                tasks_to_string_with(ready, &columns, |t| t.extra_fields(cfg))
+            ))
+        }
+        Command::Age => Ok(tasks_to_string(AgeBuckets::from_tasks(
+            ReadyTask::read_tasks(cfg)?
+                .into_iter()
+                .map(Task::Ready)
+                .chain(WaitingTask::read_tasks(cfg)?.into_iter().map(Task::Waiting))
+                .collect(),
+        ))),
+        Command::Standup => Ok(standup(
+            &ReadyTask::read_tasks(cfg)?,
+            &WaitingTask::read_tasks(cfg)?,
+            &CompletedTask::read_tasks(cfg)?,
+        )),
+        Command::Timesheet { week, csv } => {
+            let today = Local::now().date_naive();
+            let from = if week {
+                today - Days::new(today.weekday().num_days_from_monday().into())
+            } else {
+                today
+            };
+            let rows = TimesheetRow::from_tasks(&Task::read_all(cfg)?, from, today);
+            if csv {
+                return Ok(TimesheetRow::to_csv(&rows));
+            }
+            let total = rows.iter().map(|row| row.time).sum();
+            let projects = ProjectTime::from_rows(&rows);
+            Ok(format!(
+                "{}\nPer project\n{}\nTotal {}",
+                tasks_to_string(rows),
+                tasks_to_string(projects),
+                display_minutes(&total)
+            ))
+        }
+        Command::Due { within } => {
+            let today = Local::now().date_naive();
+            let until = today + within;
+            let mut tasks = ReadyTask::read_tasks(cfg)?
+                .into_iter()
+                .map(Task::from)
+                .chain(WaitingTask::read_tasks(cfg)?.into_iter().map(Task::from))
+                .filter(|t| t.due().is_some_and(|due| due <= until))
+                .collect::<Vec<Task>>();
+            tasks.sort_by_key(|t| t.due());
+            Ok(tasks_to_colored_string(tasks, |t| match t.due() {
+                Some(due) if due < today => Some(Color::FG_RED),
+                Some(due) if due == today => Some(Color::FG_YELLOW),
+                _ => None,
+            }))
+        }
+        Command::Blocked => Ok(tasks_to_string(BlockedTask::from_tasks(
+            &ReadyTask::read_tasks(cfg)?,
+            &WaitingTask::read_tasks(cfg)?,
+        ))),
+        Command::Unblocked => Ok(tasks_to_string(BlockingTask::from_tasks(
+            &ReadyTask::read_tasks(cfg)?,
+            &WaitingTask::read_tasks(cfg)?,
+        ))),
+        Command::Expired { since } => {
+            let mut tasks = DeletedTask::read_tasks(cfg)?;
+            tasks.retain(|t| t.expired);
+            if let Some(since) = since {
+                let from = Local::now() - since;
+                tasks.retain(|t| t.modified_at >= from);
+            }
+            tasks.sort_by_key(|t| t.modified_at);
             Ok(tasks_to_string(tasks))
         }
-        Command::Waiting => {
+        Command::Stats { archived } => {
+            let ready = ReadyTask::read_tasks(cfg)?;
+            let waiting = WaitingTask::read_tasks(cfg)?;
+            let completed = CompletedTask::read_tasks_with_archive(cfg, archived)?;
+            let deleted = DeletedTask::read_tasks(cfg)?;
+            let tags = ready
+                .iter()
+                .flat_map(|t| t.tags.iter())
+                .chain(waiting.iter().flat_map(|t| t.tags.iter()))
+                .chain(completed.iter().flat_map(|t| t.tags.iter()));
+            let projects = ready
+                .iter()
+                .filter_map(|t| t.project.as_ref())
+                .chain(waiting.iter().filter_map(|t| t.project.as_ref()))
+                .chain(completed.iter().filter_map(|t| t.project.as_ref()));
+            Ok([
+                tasks_to_string(stats_rows(&ready, &waiting, &completed, &deleted)),
+                format!(
+                    "Completed per week\n{}",
+                    tasks_to_string(WeekVelocity::from_completed(&completed, 8))
+                ),
+                format!("Most used tags\n{}", tasks_to_string(Tally::top(tags, 5))),
+                format!(
+                    "Most used projects\n{}",
+                    tasks_to_string(Tally::top(projects, 5))
+                ),
+                format!(
+                    "Longest waiting\n{}",
+                    tasks_to_string(WaitingStay::longest(&waiting, 5))
+                ),
+            ]
+            .join("\n"))
+        }
+        Command::Calendar { month, due } => {
+            let first = month.unwrap_or_else(|| {
+                let today = Local::now().date_naive();
+                today - Days::new((today.day() - 1).into())
+            });
+            let in_month = |date: &Option<NaiveDate>| {
+                date.filter(|d| d.year() == first.year() && d.month() == first.month())
+            };
+            // Day, kind of date, id and description of each open sigo in the month
+            let mut entries = Vec::new();
+            let ready = ReadyTask::read_tasks(cfg)?;
+            let waiting = WaitingTask::read_tasks(cfg)?;
+            for (id, description, due, scheduled) in ready
+                .iter()
+                .map(|t| (t.id, t.get_main_description(), t.due, t.scheduled))
+                .chain(
+                    waiting
+                        .iter()
+                        .map(|t| (t.id, t.get_main_description(), t.due, t.scheduled)),
+                )
+            {
+                if let Some(day) = in_month(&due) {
+                    entries.push((day, "due", id, description.clone()));
+                }
+                if let Some(day) = in_month(&scheduled) {
+                    entries.push((day, "sched", id, description));
+                }
+            }
+            entries.sort();
+            let mut notes = BTreeMap::new();
+            for (day, ..) in entries.iter() {
+                *notes.entry(*day).or_insert(0) += 1;
+            }
+            let notes = notes
+                .into_iter()
+                .map(|(day, count)| (day, format!("({})", count)))
+                .collect();
+            let mut output = month_grid(first, &notes);
+            if due {
+                let mut last_day = None;
+                for (day, kind, id, description) in entries {
+                    if last_day != Some(day) {
+                        output.push_str(&format!("\n\n{}", day.format("%a %Y-%m-%d")));
+                        last_day = Some(day);
+                    }
+                    output.push_str(&format!("\n  {:<5} {:>3} {}", kind, id, description));
+                }
+            }
+            Ok(output)
+        }
+        Command::Tree { filter } => {
+            let filter = filter_with_context(cfg, &filter)?;
+            Ok(tree(&dependency_tree(
+                ReadyTask::read_tasks(cfg)?,
+                WaitingTask::read_tasks(cfg)?,
+                &filter,
+            )))
+        }
+        Command::Burndown { daily, periods } => {
+            let points = BurndownPoint::from_tasks(
+                &ReadyTask::read_tasks(cfg)?,
+                &WaitingTask::read_tasks(cfg)?,
+                &CompletedTask::read_tasks(cfg)?,
+                &DeletedTask::read_tasks(cfg)?,
+                daily,
+                periods,
+            );
+            let bars = points
+                .into_iter()
+                .map(|p| Bar {
+                    label: p.period.to_string(),
+                    values: vec![p.open, p.completed],
+                })
+                .collect::<Vec<Bar>>();
+            Ok(format!(
+                "# open / . completed\n{}",
+                stacked_bars(&bars, &['#', '.'])
+            ))
+        }
+        Command::Summary => Ok(tasks_to_string(ProjectSummary::from_tasks(
+            ReadyTask::read_tasks(cfg)?,
+            WaitingTask::read_tasks(cfg)?,
+            CompletedTask::read_tasks(cfg)?,
+        ))),
+        Command::Search {
+            pattern,
+            ignore_case,
+            regex,
+            archived,
+        } => {
+            let pattern = if regex {
+                pattern
+            } else {
+                regex::escape(&pattern)
+            };
+            let pattern = RegexBuilder::new(&pattern)
+                .case_insensitive(ignore_case)
+                .build()
+                .map_err(SigoError::InvalidPattern)?;
+            let hits = SearchHit::search(
+                &ReadyTask::read_tasks(cfg)?,
+                &WaitingTask::read_tasks(cfg)?,
+                &CompletedTask::read_tasks_with_archive(cfg, archived)?,
+                &pattern,
+            );
+            Ok(tasks_to_string(hits))
+        }
+        Command::Velocity { weeks, archived } => {
+            let tasks = CompletedTask::read_tasks_with_archive(cfg, archived)?;
+            Ok(tasks_to_string(WeekVelocity::from_completed(&tasks, weeks)))
+        }
+        Command::Undo {
+            last_destructive: true,
+        } => {
+            let snapshot = trash::restore_last(cfg)?;
+            Ok(format!(
+                "Rolled back `sigo {}` ({})",
+                snapshot.command,
+                snapshot.taken.format("%Y-%m-%d %H:%M")
+            ))
+        }
+        Command::Undo { .. }
+        | Command::Encrypt { .. }
+        | Command::Decrypt
+        | Command::Doctor { .. } => {
+            unreachable!("handled by run_once")
+        }
+        Command::Replay => {
+            storage::ensure_on_disk(cfg, "replay")?;
+            let operations = operations::read_operations(cfg)?;
+            if operations.is_empty() {
+                return Ok("No operations logged yet".to_owned());
+            }
+            let mut collections = operations::replay(&operations);
+            let storage = storage::open(cfg);
+            for name in storage.names()? {
+                collections.entry(name).or_default();
+            }
+            let writes = collections
+                .iter()
+                .map(|(name, tasks)| Ok((name.as_str(), serde_json::to_string(tasks)?)))
+                .collect::<Result<Vec<(&str, String)>>>()?;
+            backup::safeguard(cfg)?;
+            trash::snapshot(cfg, &collections.keys().cloned().collect::<Vec<String>>())?;
+            storage.write_all(&writes)?;
+            Ok(format!(
+                "Rebuilt {} sigos from {} operations",
+                collections.values().map(Vec::len).sum::<usize>(),
+                operations.len()
+            ))
+        }
+        Command::Backup => {
+            let (backup, copied) = backup::create(cfg)?;
+            Ok(format!(
+                "Backed up {} files to {}",
+                copied,
+                backup.display()
+            ))
+        }
+        Command::RestoreBackup {
+            timestamp,
+            list,
+            force,
+        } => {
+            let Some(timestamp) = timestamp.filter(|_| !list) else {
+                let timestamps = backup::list(cfg)?;
+                if timestamps.is_empty() {
+                    return Ok("No backups".to_owned());
+                }
+                return Ok(timestamps.join("\n"));
+            };
+            let lost = journal::entries_since(cfg, &backup::path(cfg, &timestamp)?)?;
+            if lost > 0 && !force {
+                return Err(SigoError::JournalWouldBeLost(lost));
+            }
+            let restored = backup::restore(cfg, &timestamp)?;
+            Ok(format!(
+                "Restored {} files of backup {}",
+                restored, timestamp
+            ))
+        }
+        Command::Profiles => {
+            let current = config::profile();
+            let mut lines = vec![format!(
+                "{} (default)",
+                if current.is_none() { "*" } else { " " }
+            )];
+            for name in config::profiles()? {
+                let marker = if current == Some(name.as_str()) {
+                    "*"
+                } else {
+                    " "
+                };
+                lines.push(format!("{} {}", marker, name));
+            }
+            Ok(lines.join("\n"))
+        }
+        Command::Migrate { to } => {
+            storage::ensure_on_disk(cfg, "migrate")?;
+            if to == cfg.storage {
+                return Ok(format!("Already stored in {}", to));
+            }
+            let moved = storage::migrate(cfg, to)?;
+            config::update(|cfg| cfg.storage = to)?;
+            Ok(format!(
+                "Copied {} collections to {} and switched to it",
+                moved, to
+            ))
+        }
+        Command::History { id } => {
+            let task = Task::get_by_ref(cfg, &id)?;
+            let mut events = journal::history(cfg, task.uuid())?;
+            // The journal keeps only the latest entries
+            if !events
+                .first()
+                .is_some_and(|e| e.event == "created" || e.event == "logged")
+            {
+                events.insert(
+                    0,
+                    journal::Event {
+                        entry: task.created_at(),
+                        event: "created".to_owned(),
+                        command: String::new(),
+                    },
+                );
+            }
+            Ok(tasks_to_string(events))
+        }
+        Command::Waiting {
+            filter,
+            group,
+            group_by,
+            columns,
+        } => {
+            let columns = Columns::select::<WaitingTask>(
+                cfg.extra_columns(),
+                &cfg.report_columns("waiting", columns),
+            )?;
+            let filter = filter_with_context(cfg, &filter)?;
             let mut tasks = WaitingTask::read_tasks(cfg)?;
-            tasks.sort_by(|a, b| a.priority.cmp(&b.priority));
-            Ok(tasks_to_string(tasks))
+            tasks.retain(|t| t.matches(&filter));
+            // The soonest to be ready first and those waiting indefinitely the longest waiting
+            tasks.sort_by_key(|t| (t.wait_until.is_none(), t.wait_until, t.waiting_since()));
+            if let Some(by) = group_by.or(group.then_some(GroupBy::Project)) {
+                let groups = match by {
+                    GroupBy::Project => group_by_project(tasks, |t| &t.project),
+                    GroupBy::Tag => group_by_tags(tasks, |t| &t.tags),
+                };
+                Ok(grouped_tasks_to_string(groups, &columns, |t| {
+                    t.extra_fields(cfg)
+                }))
+            } else {
+                Ok(tasks_to_string_with(tasks, &columns, |t| {
+                    t.extra_fields(cfg)
+                }))
+            }
+        }
+    }
+}
+
+fn complete_task(cfg: &MyConfig, task: Task) -> Result<String> {
+    match task {
+        Task::Ready(task) => {
+            task.complete(cfg)?;
+            if task.template.is_some() {
+                RecurringTask::expand(cfg)?;
+            }
+            Ok(format!("Completed sigo {}", task.id))
+        }
+        Task::Waiting(task) => {
+            task.complete(cfg)?;
+            if task.template.is_some() {
+                RecurringTask::expand(cfg)?;
+            }
+            Ok(format!("Completed sigo {}", task.id))
+        }
+        Task::Completed(_) => Err(SigoError::AlreadyCompleted(task.name())),
+        task => Err(SigoError::NotOpen(task.uuid())),
+    }
+}
+
+fn delete_task(cfg: &MyConfig, task: Task, keep_series: bool) -> Result<String> {
+    let (id, template) = match task {
+        Task::Ready(task) => {
+            task.delete(cfg)?;
+            (task.id, task.template)
+        }
+        Task::Waiting(task) => {
+            task.delete(cfg)?;
+            (task.id, task.template)
+        }
+        task => return Err(SigoError::NotOpen(task.uuid())),
+    };
+    match template {
+        Some(template) if keep_series => {
+            RecurringTask::keep_period(cfg, template)?;
+            Ok(format!("Deleted sigo {}, keeping its recurrence", id))
         }
+        Some(template) if RecurringTask::end(cfg, template)? => {
+            Ok(format!("Deleted sigo {} and ended its recurrence", id))
+        }
+        _ => Ok(format!("Deleted sigo {}", id)),
+    }
+}
+
+/// Ask what to do with the sigo until a decision is made, `None` when quitting the review
+fn review_task(cfg: &MyConfig, task: Task) -> Result<Option<String>> {
+    let reviewed = Modification {
+        reviewed: true,
+        ..Default::default()
+    };
+    loop {
+        let Some(key) = prompt("[c]omplete [d]elete [p]riority d[u]e [k]eep [q]uit:")? else {
+            return Ok(None);
+        };
+        match key.as_str() {
+            "c" => return complete_task(cfg, task).map(Some),
+            "d" => return delete_task(cfg, task, false).map(Some),
+            "p" => {
+                let answer = prompt("Priority (H/M/L):")?.unwrap_or_default();
+                match Priority::from_str(&answer, true) {
+                    Ok(priority) => {
+                        let modification = Modification {
+                            priority: Some(priority),
+                            ..reviewed
+                        };
+                        task.modify(cfg, &modification)?;
+                        return Ok(Some(format!("Set priority of sigo {}", task.name())));
+                    }
+                    Err(err) => println!("{}", err),
+                }
+            }
+            "u" => {
+                let answer = prompt("Due date:")?.unwrap_or_default();
+                match parse_date(&answer) {
+                    Ok(due) => {
+                        let modification = Modification {
+                            due: Some(Some(due)),
+                            ..reviewed
+                        };
+                        task.modify(cfg, &modification)?;
+                        return Ok(Some(format!("Set due date of sigo {}", task.name())));
+                    }
+                    Err(err) => println!("{}", err),
+                }
+            }
+            "k" | "" => {
+                task.modify(cfg, &reviewed)?;
+                return Ok(Some(format!("Kept sigo {}", task.name())));
+            }
+            "q" => return Ok(None),
+            _ => println!("Unknown key {}", key),
+        }
+    }
+}
+
+/// Run `f` on each of the sigos, reporting which ones failed after trying all of them
+fn for_each_task<I, F>(cfg: &MyConfig, refs: I, mut f: F) -> Result<String>
+where
+    I: IntoIterator<Item = TaskRefs>,
+    F: FnMut(Task) -> Result<String>,
+{
+    let mut outputs = Vec::new();
+    let mut failures = Vec::new();
+    for task_ref in refs.into_iter().flat_map(|refs| refs.0) {
+        match Task::get_by_ref(cfg, &task_ref).and_then(&mut f) {
+            Ok(output) => outputs.push(output),
+            Err(err) => failures.push((task_ref.to_string(), err)),
+        }
+    }
+    match failures.len() {
+        0 => Ok(outputs.join("\n")),
+        1 if outputs.is_empty() => Err(failures.remove(0).1),
+        _ => Err(SigoError::Failures(outputs, failures)),
+    }
+}
+
+/// Modification given by a description, `+tag`s, `-tag`s, `project:`, `assignee:` and user defined attributes
+fn modification_from_words(cfg: &MyConfig, words: &[String]) -> Modification {
+    let words = parse_words(words, &cfg.udas);
+    Modification {
+        description: Some(words.description).filter(|d| !d.is_empty()),
+        add_tags: words.tags,
+        remove_tags: words.remove_tags,
+        project: words.project.map(|p| Some(p).filter(|p| !p.is_empty())),
+        assignee: words
+            .assignee
+            .map(|a| cfg.resolve_assignee(Some(a).filter(|a| !a.is_empty()))),
+        udas: words.udas,
+        ..Default::default()
+    }
+}
+
+/// Parse the filter narrowed by the one of the active context
+fn filter_with_context(cfg: &MyConfig, words: &[String]) -> Result<Filter> {
+    let context = Filter::parse(cfg, &context::active_filter(cfg)?)?;
+    Ok(Filter::parse(cfg, words)?.and(context))
+}
+
+/// Write every collection, the journal and the operations log again with the key file, or in
+/// plain text without one, and return the number of collections
+fn rekey(cfg: &MyConfig, key_file: Option<PathBuf>) -> Result<usize> {
+    let crypt = Crypt::rekeying(cfg, key_file);
+    let storage = storage::open_with(cfg, cfg.storage, crypt.clone());
+    let names = storage.names()?;
+    let mut collections = Vec::new();
+    for name in &names {
+        collections.push((name.as_str(), storage.read(name)?));
+    }
+    storage.write_all(&collections)?;
+    journal::rekey(cfg, &crypt)?;
+    operations::rekey(cfg, &crypt)?;
+    trash::rekey(cfg, &crypt)?;
+    Ok(names.len())
+}
+
+fn encrypt(cfg: &MyConfig, key_file: PathBuf) -> Result<String> {
+    storage::ensure_on_disk(cfg, "encrypt")?;
+    if !storage::may_write()? {
+        return Ok(format!(
+            "Would encrypt the sigos with {}",
+            key_file.display()
+        ));
+    }
+    if !key_file.exists() {
+        crypt::generate_key(&key_file)?;
+    }
+    let key_file = key_file
+        .canonicalize()
+        .map_err(|e| SigoError::FileReadErr(key_file.clone(), e))?;
+    if cfg.key_file.as_ref() == Some(&key_file) {
+        return Ok(format!("Already encrypted with {}", key_file.display()));
+    }
+    let count = rekey(cfg, Some(key_file.clone()))?;
+    config::update(|cfg| cfg.key_file = Some(key_file.clone()))?;
+    Ok(format!(
+        "Encrypted {} collections with {}; keep a copy of it, as the sigos cannot be read without it",
+        count,
+        key_file.display()
+    ))
+}
+
+fn decrypt(cfg: &MyConfig) -> Result<String> {
+    storage::ensure_on_disk(cfg, "decrypt")?;
+    if cfg.key_file.is_none() {
+        return Ok("Not encrypted".to_owned());
+    }
+    if !storage::may_write()? {
+        return Ok("Would decrypt the sigos".to_owned());
     }
+    let count = rekey(cfg, None)?;
+    config::update(|cfg| cfg.key_file = None)?;
+    Ok(format!("Decrypted {} collections", count))
 }
@@ -1,19 +1,354 @@
+use std::{collections::BTreeMap, fs, path::PathBuf, sync::OnceLock};
+
+use directories::{BaseDirs, ProjectDirs};
 use serde::{Deserialize, Serialize};
+use toml::Table;
+
+use crate::{
+    error::SigoError,
+    sort::Sort,
+    storage::{self, Backend, Durability},
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct MyConfig {
     pub data: String,
+    /// Backend keeping the sigos in the data directory, changed by `sigo migrate`
+    #[serde(default)]
+    pub storage: Backend,
+    /// File of the key encrypting the sigos in the data directory, set by `sigo encrypt`
+    #[serde(default)]
+    pub key_file: Option<PathBuf>,
+    /// Assignee of sigos added without `assignee:`, also referred to as `assignee:me`
+    #[serde(default)]
+    pub assignee: Option<String>,
+    /// Names of user defined attributes, set by `name:value`
+    #[serde(default)]
+    pub udas: Vec<String>,
+    #[serde(default)]
+    pub urgency: UrgencyCoefficients,
+    /// Named filters applied to lists while active, e.g. `work = "project:job +office"`
+    #[serde(default)]
+    pub contexts: BTreeMap<String, String>,
+    /// Editor for `sigo edit`, falling back to `$VISUAL`, `$EDITOR` and then `vi`
+    #[serde(default)]
+    pub editor: Option<String>,
+    /// Order of `sigo list` without `--sort`, e.g. `due+,priority-`
+    #[serde(default)]
+    pub sort: Option<String>,
+    /// Whether regular expressions in filters ignore case without the `i` flag
+    #[serde(default)]
+    pub regex_ignore_case: bool,
+    /// Number of sigos per page of `sigo list` without `--limit`
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// Whether the yearly archives of completed sigos are written compressed with gzip
+    #[serde(default)]
+    pub compress_archives: bool,
+    /// Whether ids count up and are never reused, rather than the smallest free one being issued
+    #[serde(default)]
+    pub monotonic_ids: bool,
+    /// Whether writes are synced to the disk, `safe`, or left to the system for speed, `fast`
+    #[serde(default)]
+    pub durability: Durability,
+    /// Number of backups kept by `sigo backup`, 10 if not set and all of them if 0
+    #[serde(default)]
+    pub keep_backups: Option<usize>,
+    /// Columns of reports without `--columns`, e.g. `list = "id,description,due"`
+    #[serde(default)]
+    pub columns: BTreeMap<String, String>,
+    /// Reports run by `sigo report <name>`
+    #[serde(default)]
+    pub reports: BTreeMap<String, ReportDefinition>,
+    /// Filters saved by `sigo query save`, used as `@name`
+    #[serde(default)]
+    pub queries: BTreeMap<String, String>,
+}
+
+/// Directories of sigo, missing without a home directory
+fn project_dirs() -> Result<ProjectDirs, SigoError> {
+    ProjectDirs::from("", "", "sigo").ok_or(SigoError::NoHomeDir)
+}
+
+/// Path of the config file, `config.toml` in the config directory of the platform
+/// (`$XDG_CONFIG_HOME/sigo` on Linux), or the `config.ini` of older versions while only it exists
+pub fn path() -> Result<PathBuf, SigoError> {
+    let path = project_dirs()?.config_dir().join("config.toml");
+    Ok(match legacy_path() {
+        Some(legacy) if !path.is_file() && legacy.is_file() => legacy,
+        _ => path,
+    })
+}
+
+/// Config of older versions, whose `data` still points to where their sigos are
+fn legacy_path() -> Option<PathBuf> {
+    Some(
+        BaseDirs::new()?
+            .config_dir()
+            .join("sigotowarrior")
+            .join("config.ini"),
+    )
+}
+
+/// Profile chosen by `--profile` or `$SIGO_PROFILE`, set once when the config is loaded
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Directory of the profiles, each a file of the config keys it overrides
+fn profiles_dir() -> Result<PathBuf, SigoError> {
+    Ok(project_dirs()?.config_dir().join("profiles"))
+}
+
+fn profile_path(name: &str) -> Result<PathBuf, SigoError> {
+    Ok(profiles_dir()?.join(format!("{}.toml", name)))
+}
+
+/// Names of the profiles, sorted
+pub fn profiles() -> Result<Vec<String>, SigoError> {
+    let dir = profiles_dir()?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(&dir).map_err(|e| SigoError::FileReadErr(dir.clone(), e))?;
+    let mut names = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            (path.extension()? == "toml").then_some(path.file_stem()?.to_str()?.to_owned())
+        })
+        .collect::<Vec<String>>();
+    names.sort();
+    Ok(names)
+}
+
+/// Name of the profile in use, if any
+pub fn profile() -> Option<&'static str> {
+    PROFILE.get().map(String::as_str)
+}
+
+fn load_base() -> Result<MyConfig, SigoError> {
+    let path = path()?;
+    confy::load_path::<MyConfig>(&path).map_err(|e| SigoError::ConfigLoadErr(path, e))
+}
+
+fn to_table(cfg: &MyConfig) -> Result<Table, SigoError> {
+    let path = path()?;
+    Table::try_from(cfg).map_err(|e| SigoError::ProfileErr(path, e.to_string()))
+}
+
+fn read_overrides(name: &str) -> Result<Table, SigoError> {
+    let path = profile_path(name)?;
+    let content = fs::read_to_string(&path).map_err(|e| SigoError::FileReadErr(path.clone(), e))?;
+    toml::from_str(&content).map_err(|e| SigoError::ProfileErr(path, e.to_string()))
+}
+
+fn write_overrides(name: &str, overrides: &Table) -> Result<(), SigoError> {
+    let path = profile_path(name)?;
+    let content = toml::to_string(overrides)
+        .map_err(|e| SigoError::ProfileErr(path.clone(), e.to_string()))?;
+    let dir = profiles_dir()?;
+    fs::create_dir_all(&dir).map_err(|e| SigoError::FileCreateErr(dir.clone(), e))?;
+    fs::write(&path, content).map_err(|e| SigoError::FileWriteErr(path, e))
+}
+
+/// Keys of `overrides` replacing those of `base`, merging tables like `[urgency]` key by key
+fn merge(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(value)) => merge(base, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn with_overrides(base: &MyConfig, name: &str) -> Result<MyConfig, SigoError> {
+    let mut table = to_table(base)?;
+    merge(&mut table, read_overrides(name)?);
+    let path = profile_path(name)?;
+    table
+        .try_into()
+        .map_err(|e: toml::de::Error| SigoError::ProfileErr(path, e.to_string()))
+}
+
+/// The config, with the overrides of the profile if one is given; a new profile keeps its sigos
+/// in a data directory of its own next to that of the config
+pub fn load(profile: Option<String>) -> Result<MyConfig, SigoError> {
+    project_dirs()?;
+    let base = load_base()?;
+    let Some(name) = profile else {
+        return Ok(base);
+    };
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(SigoError::InvalidProfileName(name));
+    }
+    if !profile_path(&name)?.is_file() {
+        let mut overrides = Table::new();
+        let data = format!("{}-{}", base.data.trim_end_matches(['/', '\\']), name);
+        overrides.insert("data".to_owned(), toml::Value::from(data));
+        write_overrides(&name, &overrides)?;
+    }
+    let cfg = with_overrides(&base, &name)?;
+    let _ = PROFILE.set(name);
+    Ok(cfg)
+}
+
+/// Change the config file as it is now, e.g. after edits made while the command ran; with a
+/// profile, the keys changed from the config are written to the profile instead
+pub fn update<F>(change: F) -> Result<(), SigoError>
+where
+    F: FnOnce(&mut MyConfig),
+{
+    if !storage::may_write()? {
+        return Ok(());
+    }
+    let Some(name) = profile() else {
+        let mut cfg = load_base()?;
+        change(&mut cfg);
+        return confy::store_path(path()?, cfg).map_err(SigoError::ConfigWriteErr);
+    };
+    let base = load_base()?;
+    let mut cfg = with_overrides(&base, name)?;
+    change(&mut cfg);
+    let (base, changed) = (to_table(&base)?, to_table(&cfg)?);
+    let mut overrides = read_overrides(name)?;
+    overrides.retain(|key, _| changed.contains_key(key));
+    for (key, value) in changed {
+        if overrides.contains_key(&key) || base.get(&key) != Some(&value) {
+            overrides.insert(key, value);
+        }
+    }
+    write_overrides(name, &overrides)
+}
+
+/// Sigos in any state matching the filter, e.g. `filter = "status:ready +next"`
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ReportDefinition {
+    pub filter: String,
+    /// Like `--sort` of `sigo list`, most urgent first by default
+    pub sort: Option<String>,
+    /// Like `--columns` of `sigo list`
+    pub columns: Option<String>,
+}
+
+/// Weights of each factor in the urgency of a sigo, like taskwarrior's `urgency.*.coefficient`
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct UrgencyCoefficients {
+    pub priority_h: f64,
+    pub priority_m: f64,
+    pub priority_l: f64,
+    pub due: f64,
+    pub age: f64,
+    pub tags: f64,
+    pub blocked: f64,
+}
+
+impl MyConfig {
+    /// Resolve `me` to the configured assignee
+    pub fn resolve_assignee(&self, assignee: Option<String>) -> Option<String> {
+        match assignee.as_deref() {
+            Some("me") => self.assignee.clone(),
+            _ => assignee,
+        }
+    }
+
+    /// Command line of the editor to use
+    pub fn editor(&self) -> String {
+        self.editor
+            .clone()
+            .or_else(|| std::env::var("VISUAL").ok())
+            .or_else(|| std::env::var("EDITOR").ok())
+            .filter(|e| !e.trim().is_empty())
+            .unwrap_or_else(|| "vi".to_owned())
+    }
+
+    pub fn sort(&self) -> Result<Option<Sort>, SigoError> {
+        parse_sort(&self.sort)
+    }
+
+    /// Names of the columns given on the command line, or else in the config for the report
+    pub fn report_columns(&self, report: &str, given: Vec<String>) -> Vec<String> {
+        if !given.is_empty() {
+            return given;
+        }
+        split_columns(self.columns.get(report))
+    }
+
+    /// Headers of the columns shown after the fixed ones
+    pub fn extra_columns(&self) -> Vec<String> {
+        let mut columns = vec!["urg".to_owned()];
+        columns.extend(self.udas.iter().cloned());
+        columns
+    }
+}
+
+impl ReportDefinition {
+    pub fn sort(&self) -> Result<Option<Sort>, SigoError> {
+        parse_sort(&self.sort)
+    }
+
+    pub fn columns(&self) -> Vec<String> {
+        split_columns(self.columns.as_ref())
+    }
+}
+
+fn parse_sort(sort: &Option<String>) -> Result<Option<Sort>, SigoError> {
+    sort.as_deref()
+        .map(|sort| sort.parse().map_err(SigoError::InvalidSort))
+        .transpose()
+}
+
+fn split_columns(columns: Option<&String>) -> Vec<String> {
+    columns
+        .map(|columns| columns.split(',').map(|c| c.trim().to_owned()).collect())
+        .unwrap_or_default()
 }
 
 impl ::std::default::Default for MyConfig {
     fn default() -> Self {
-        let xdg_dirs = xdg::BaseDirectories::with_prefix("sigotowarrior").expect("XDG is not used");
-        let data_dir = xdg_dirs.get_data_home();
         Self {
-            data: data_dir
-                .into_os_string()
-                .into_string()
-                .expect("XDG_DATA_HOME is not set"),
+            // `$XDG_DATA_HOME/sigo` on Linux, left empty if it cannot be written in the config,
+            // which the command then fails for unless sigos are kept elsewhere
+            data: project_dirs()
+                .ok()
+                .and_then(|dirs| dirs.data_dir().to_str().map(str::to_owned))
+                .unwrap_or_default(),
+            storage: Backend::Json,
+            key_file: None,
+            assignee: None,
+            udas: Vec::new(),
+            urgency: UrgencyCoefficients::default(),
+            contexts: BTreeMap::new(),
+            editor: None,
+            sort: None,
+            regex_ignore_case: false,
+            limit: None,
+            compress_archives: false,
+            monotonic_ids: false,
+            durability: Durability::Safe,
+            keep_backups: None,
+            columns: BTreeMap::new(),
+            reports: BTreeMap::new(),
+            queries: BTreeMap::new(),
+        }
+    }
+}
+
+impl ::std::default::Default for UrgencyCoefficients {
+    fn default() -> Self {
+        Self {
+            priority_h: 6.0,
+            priority_m: 3.9,
+            priority_l: 1.8,
+            due: 12.0,
+            age: 2.0,
+            tags: 1.0,
+            blocked: -5.0,
         }
     }
 }
@@ -0,0 +1,67 @@
+use std::{cell::RefCell, fs, path::PathBuf};
+
+use crate::{
+    config::MyConfig,
+    error::SigoError,
+    storage::{self, Backend},
+};
+
+const FILE_NAME: &str = "context";
+
+thread_local! {
+    /// Active context while sigos are kept in memory
+    static IN_MEMORY: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+fn context_path(cfg: &MyConfig) -> PathBuf {
+    let mut path = PathBuf::from(&cfg.data);
+    path.push(FILE_NAME);
+    path
+}
+
+/// Name of the active context, if any
+pub fn read_active(cfg: &MyConfig) -> Result<Option<String>, SigoError> {
+    if cfg.storage == Backend::Memory {
+        return Ok(IN_MEMORY.with_borrow(Clone::clone));
+    }
+    let path = context_path(cfg);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let name = fs::read_to_string(&path).map_err(|e| SigoError::FileReadErr(path.clone(), e))?;
+    Ok(Some(name.trim().to_owned()).filter(|n| !n.is_empty()))
+}
+
+/// Activate the context, or deactivate with `None`
+pub fn write_active(cfg: &MyConfig, name: Option<&str>) -> Result<(), SigoError> {
+    let path = context_path(cfg);
+    if let Some(name) = name.filter(|name| !cfg.contexts.contains_key(*name)) {
+        return Err(SigoError::UnknownContext(name.to_owned()));
+    }
+    if cfg.storage == Backend::Memory {
+        IN_MEMORY.set(name.map(str::to_owned));
+        return Ok(());
+    }
+    if !storage::may_write()? {
+        return Ok(());
+    }
+    match name {
+        Some(name) => fs::write(&path, name).map_err(|e| SigoError::FileWriteErr(path.clone(), e)),
+        None if path.is_file() => {
+            fs::remove_file(&path).map_err(|e| SigoError::FileWriteErr(path.clone(), e))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Filter words of the active context
+pub fn active_filter(cfg: &MyConfig) -> Result<Vec<String>, SigoError> {
+    let Some(name) = read_active(cfg)? else {
+        return Ok(Vec::new());
+    };
+    let filter = cfg
+        .contexts
+        .get(&name)
+        .ok_or(SigoError::UnknownContext(name))?;
+    Ok(filter.split_whitespace().map(str::to_owned).collect())
+}
@@ -0,0 +1,132 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+
+use crate::{config::MyConfig, error::SigoError};
+
+/// Start of encrypted contents, followed by the nonce and the ciphertext in base64
+const PREFIX: &str = "sigo-encrypted:";
+const NONCE_LEN: usize = 12;
+
+/// Encryption of the sigos written to the data directory with ChaCha20-Poly1305, keyed by a file
+/// of 32 random bytes in base64
+#[derive(Clone, Default)]
+pub struct Crypt {
+    /// Key of encrypted contents read, which are passed through if not encrypted
+    read_key: Option<PathBuf>,
+    /// Key of contents written, which are written as they are without one
+    write_key: Option<PathBuf>,
+}
+
+impl Crypt {
+    /// Encryption with the key file of the config, if any
+    pub fn new(cfg: &MyConfig) -> Self {
+        Self {
+            read_key: cfg.key_file.clone(),
+            write_key: cfg.key_file.clone(),
+        }
+    }
+
+    /// Reading with the key file of the config and writing with another one, or in plain text
+    pub fn rekeying(cfg: &MyConfig, write_key: Option<PathBuf>) -> Self {
+        Self {
+            read_key: cfg.key_file.clone(),
+            write_key,
+        }
+    }
+
+    fn cipher(key_file: &Path) -> Result<ChaCha20Poly1305, SigoError> {
+        let err = |message: &str| SigoError::KeyErr(key_file.to_path_buf(), message.to_owned());
+        let encoded = fs::read_to_string(key_file)
+            .map_err(|e| SigoError::FileReadErr(key_file.to_path_buf(), e))?;
+        let key = STANDARD
+            .decode(encoded.trim())
+            .map_err(|_| err("not in base64"))?;
+        if key.len() != 32 {
+            return Err(err("not 32 bytes long"));
+        }
+        Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+    }
+
+    /// The contents as written, encrypted if there is a key to write with
+    pub fn seal(&self, plain: String) -> Result<String, SigoError> {
+        let sealed = self.seal_bytes(plain.into_bytes())?;
+        // Either the plain text given or base64
+        Ok(String::from_utf8(sealed).unwrap_or_default())
+    }
+
+    /// The contents read from the path, decrypted if they were encrypted
+    pub fn open(&self, path: &Path, text: String) -> Result<String, SigoError> {
+        let plain = self.open_bytes(path, text.into_bytes())?;
+        String::from_utf8(plain).map_err(|_| SigoError::DecryptErr(path.to_path_buf()))
+    }
+
+    /// Like `seal`, for contents which are not text such as compressed ones
+    pub fn seal_bytes(&self, plain: Vec<u8>) -> Result<Vec<u8>, SigoError> {
+        let Some(key_file) = &self.write_key else {
+            return Ok(plain);
+        };
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = Self::cipher(key_file)?
+            .encrypt(&nonce, plain.as_slice())
+            .map_err(|_| SigoError::KeyErr(key_file.clone(), "unable to encrypt".to_owned()))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(format!("{}{}", PREFIX, STANDARD.encode(sealed)).into_bytes())
+    }
+
+    /// Whether contents are written with another key than they are read with
+    pub fn rekeys(&self) -> bool {
+        self.read_key != self.write_key
+    }
+
+    /// Whether the contents written are encrypted
+    pub fn encrypts(&self) -> bool {
+        self.write_key.is_some()
+    }
+
+    /// Whether contents starting with the bytes were encrypted
+    pub fn is_sealed(start: &[u8]) -> bool {
+        start.starts_with(PREFIX.as_bytes())
+    }
+
+    pub fn open_bytes(&self, path: &Path, contents: Vec<u8>) -> Result<Vec<u8>, SigoError> {
+        let Some(encoded) = contents.trim_ascii_end().strip_prefix(PREFIX.as_bytes()) else {
+            return Ok(contents);
+        };
+        let Some(key_file) = &self.read_key else {
+            return Err(SigoError::EncryptedData(path.to_path_buf()));
+        };
+        let sealed = STANDARD
+            .decode(encoded)
+            .map_err(|_| SigoError::DecryptErr(path.to_path_buf()))?;
+        if sealed.len() < NONCE_LEN {
+            return Err(SigoError::DecryptErr(path.to_path_buf()));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        Self::cipher(key_file)?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SigoError::DecryptErr(path.to_path_buf()))
+    }
+}
+
+/// Write a new random key to the file, readable only by its owner
+pub fn generate_key(key_file: &Path) -> Result<(), SigoError> {
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+    fs::write(key_file, STANDARD.encode(key))
+        .map_err(|e| SigoError::FileWriteErr(key_file.to_path_buf(), e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(key_file, fs::Permissions::from_mode(0o600))
+            .map_err(|e| SigoError::FileWriteErr(key_file.to_path_buf(), e))?;
+    }
+    Ok(())
+}
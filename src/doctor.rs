@@ -0,0 +1,242 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::PathBuf,
+};
+
+use serde_json::Value;
+
+use crate::{
+    backup,
+    config::MyConfig,
+    error::SigoError,
+    storage::{self, Backend, Storage},
+    task::{CompletedTask, DeletedTask, ReadyTask, RecurringTask, Task, WaitingTask},
+};
+
+/// Collections of the open sigos, whose ids are in use
+const OPEN: [&str; 2] = [ReadyTask::FILE_NAME, WaitingTask::FILE_NAME];
+
+/// Part of the names of the files written before being renamed over the ones they replace
+const TEMPORARY_MARK: &str = ".sigo-tmp-";
+
+struct Finding {
+    problem: String,
+    /// Whether `--fix` repairs it
+    fixable: bool,
+}
+
+impl Finding {
+    fn new(problem: String, fixable: bool) -> Self {
+        Self { problem, fixable }
+    }
+}
+
+/// Check the sigos and the files of the data directory, repairing what can be with `fix`
+pub fn run(cfg: &MyConfig, fix: bool) -> Result<String, SigoError> {
+    if fix {
+        backup::safeguard(cfg)?;
+    }
+    let findings = storage::transaction(cfg, || {
+        let storage = storage::open(cfg);
+        let mut findings = temporary_files(cfg, &*storage, fix)?;
+        let broken = unreadable(cfg, &*storage, &mut findings)?;
+        if OPEN.iter().any(|name| broken.contains(*name)) {
+            findings.push(Finding::new(
+                "ids and links between sigos not checked, as open sigos could not be read"
+                    .to_owned(),
+                false,
+            ));
+            return Ok(findings);
+        }
+        findings.extend(duplicate_ids(cfg, &*storage, fix)?);
+        findings.extend(dangling_links(&*storage, fix)?);
+        Ok(findings)
+    })?;
+
+    if findings.is_empty() {
+        return Ok("No problems found".to_owned());
+    }
+    let fixable = findings.iter().filter(|f| f.fixable).count();
+    let mut lines = findings
+        .iter()
+        .map(|f| match (f.fixable, fix) {
+            (true, true) => format!("{} (fixed)", f.problem),
+            (true, false) => format!("{} (fixable)", f.problem),
+            (false, _) => f.problem.clone(),
+        })
+        .collect::<Vec<String>>();
+    lines.push(match fix {
+        true => format!("Found {} problems, fixed {}", findings.len(), fixable),
+        false if fixable > 0 => format!(
+            "Found {} problems; run with --fix to fix {} of them",
+            findings.len(),
+            fixable
+        ),
+        false => format!("Found {} problems", findings.len()),
+    });
+    Ok(lines.join("\n"))
+}
+
+/// Files of writes which did not finish, once those which can be finished are
+fn temporary_files(
+    cfg: &MyConfig,
+    storage: &dyn Storage,
+    fix: bool,
+) -> Result<Vec<Finding>, SigoError> {
+    if cfg.storage == Backend::Memory {
+        return Ok(Vec::new());
+    }
+    // Finishes a write to several files left behind
+    storage.names()?;
+    let dir = PathBuf::from(&cfg.data);
+    let entries = fs::read_dir(&dir).map_err(|e| SigoError::FileReadErr(dir.clone(), e))?;
+    let mut findings = Vec::new();
+    for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+        let is_temporary = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.contains(TEMPORARY_MARK));
+        if !is_temporary || !path.is_file() {
+            continue;
+        }
+        if fix && storage::may_write()? {
+            fs::remove_file(&path).map_err(|e| SigoError::FileWriteErr(path.clone(), e))?;
+        }
+        findings.push(Finding::new(
+            format!("{:?} is left behind by a write which did not finish", path),
+            true,
+        ));
+    }
+    Ok(findings)
+}
+
+/// Record the collections which cannot be read as sigos, returning their names
+fn unreadable(
+    cfg: &MyConfig,
+    storage: &dyn Storage,
+    findings: &mut Vec<Finding>,
+) -> Result<HashSet<String>, SigoError> {
+    let mut broken = HashSet::new();
+    let mut errors = Vec::new();
+    for name in storage.names()? {
+        let read = match name.as_str() {
+            ReadyTask::FILE_NAME => ReadyTask::read_tasks(cfg).map(drop),
+            WaitingTask::FILE_NAME => WaitingTask::read_tasks(cfg).map(drop),
+            CompletedTask::FILE_NAME => CompletedTask::read_tasks(cfg).map(drop),
+            DeletedTask::FILE_NAME => DeletedTask::read_tasks(cfg).map(drop),
+            RecurringTask::FILE_NAME => RecurringTask::read_tasks(cfg).map(drop),
+            archive => CompletedTask::read_tasks_from(cfg, archive).map(drop),
+        };
+        if let Err(e) = read {
+            // Collections kept in one file fail with the same error
+            let error = e.to_string().trim().to_owned();
+            if !errors.contains(&error) {
+                errors.push(error);
+            }
+            broken.insert(name);
+        }
+    }
+    findings.extend(errors.into_iter().map(|error| Finding::new(error, false)));
+    Ok(broken)
+}
+
+/// The first open sigo with an id another open sigo had before it, by collection and position
+fn find_duplicate(storage: &dyn Storage) -> Result<Option<(&'static str, usize, u32)>, SigoError> {
+    let mut seen = HashSet::new();
+    for name in OPEN {
+        for (position, task) in storage.values(name)?.iter().enumerate() {
+            let Some(id) = task["id"].as_u64().and_then(|id| u32::try_from(id).ok()) else {
+                continue;
+            };
+            if !seen.insert(id) {
+                return Ok(Some((name, position, id)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Open sigos sharing an id, of which all but the first are given new ones by `fix`
+fn duplicate_ids(
+    cfg: &MyConfig,
+    storage: &dyn Storage,
+    fix: bool,
+) -> Result<Vec<Finding>, SigoError> {
+    if !fix {
+        let mut counts = BTreeMap::<u32, usize>::new();
+        for name in OPEN {
+            for task in storage.values(name)? {
+                if let Some(id) = task["id"].as_u64().and_then(|id| u32::try_from(id).ok()) {
+                    *counts.entry(id).or_default() += 1;
+                }
+            }
+        }
+        return Ok(counts
+            .into_iter()
+            .filter(|(_, count)| *count > 1)
+            .map(|(id, count)| Finding::new(format!("id {} is used by {} sigos", id, count), true))
+            .collect());
+    }
+    let mut findings = Vec::new();
+    while let Some((name, position, id)) = find_duplicate(storage)? {
+        let new_id = Task::issue_task_id(cfg)?;
+        let mut tasks = storage.values(name)?;
+        tasks[position]["id"] = Value::from(new_id);
+        storage.write(name, &serde_json::to_string(&tasks)?)?;
+        findings.push(Finding::new(
+            format!(
+                "id {} is used by another sigo, given {} instead",
+                id, new_id
+            ),
+            true,
+        ));
+    }
+    Ok(findings)
+}
+
+/// Dependencies and parents of open sigos which are not open sigos, dropped by `fix`
+fn dangling_links(storage: &dyn Storage, fix: bool) -> Result<Vec<Finding>, SigoError> {
+    let open = storage.ids(&OPEN)?;
+    let is_missing = |id: &Value| id.as_u64().is_some_and(|id| !open.contains(&(id as u32)));
+    let mut findings = Vec::new();
+    for name in OPEN {
+        let mut tasks = storage.values(name)?;
+        let mut changed = false;
+        for task in tasks.iter_mut() {
+            let id = task["id"].clone();
+            let missing = task["depends"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter(|dep| is_missing(dep))
+                .cloned()
+                .collect::<Vec<Value>>();
+            for dep in &missing {
+                findings.push(Finding::new(
+                    format!("sigo {} depends on {}, which is not open", id, dep),
+                    true,
+                ));
+            }
+            if let Some(depends) = task["depends"].as_array_mut() {
+                changed |= !missing.is_empty();
+                depends.retain(|dep| !missing.contains(dep));
+            }
+            if is_missing(&task["parent_id"]) {
+                findings.push(Finding::new(
+                    format!(
+                        "sigo {} has parent {}, which is not open",
+                        id, task["parent_id"]
+                    ),
+                    true,
+                ));
+                task["parent_id"] = Value::Null;
+                changed = true;
+            }
+        }
+        if fix && changed {
+            storage.write(name, &serde_json::to_string(&tasks)?)?;
+        }
+    }
+    Ok(findings)
+}
@@ -0,0 +1,192 @@
+use std::{collections::BTreeMap, fs, process};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::MyConfig,
+    error::SigoError,
+    task::{Annotation, CompletedTask, Modification, ReadyTask, Task, WaitingTask},
+    utils, Priority,
+};
+
+const HEADER: &str = "\
+# Save and quit to apply the changes. Remove a line to clear the attribute.
+# Dates are YYYY-MM-DD and the estimate is in minutes.
+";
+
+/// Attributes of a sigo as written to the file opened in the editor
+#[derive(Serialize, Deserialize, PartialEq)]
+struct EditForm {
+    description: String,
+    priority: Priority,
+    #[serde(default)]
+    tags: Vec<String>,
+    project: Option<String>,
+    assignee: Option<String>,
+    due: Option<NaiveDate>,
+    scheduled: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    estimate: Option<i64>,
+    points: Option<u32>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    udas: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+}
+
+impl From<&ReadyTask> for EditForm {
+    fn from(task: &ReadyTask) -> Self {
+        Self {
+            description: task.get_main_description(),
+            priority: task.priority,
+            tags: task.tags.clone(),
+            project: task.project.clone(),
+            assignee: task.assignee.clone(),
+            due: task.due,
+            scheduled: task.scheduled,
+            until: task.until,
+            estimate: task.estimate,
+            points: task.points,
+            udas: utils::uda_rows(&task.udas).into_iter().collect(),
+            annotations: task.annotations.clone(),
+        }
+    }
+}
+
+impl From<&WaitingTask> for EditForm {
+    fn from(task: &WaitingTask) -> Self {
+        Self {
+            description: task.get_main_description(),
+            priority: task.priority,
+            tags: task.tags.clone(),
+            project: task.project.clone(),
+            assignee: task.assignee.clone(),
+            due: task.due,
+            scheduled: task.scheduled,
+            until: task.until,
+            estimate: task.estimate,
+            points: task.points,
+            udas: utils::uda_rows(&task.udas).into_iter().collect(),
+            annotations: task.annotations.clone(),
+        }
+    }
+}
+
+impl From<&CompletedTask> for EditForm {
+    fn from(task: &CompletedTask) -> Self {
+        Self {
+            description: task.description.clone(),
+            priority: task.priority,
+            tags: task.tags.clone(),
+            project: task.project.clone(),
+            assignee: task.assignee.clone(),
+            due: None,
+            scheduled: None,
+            until: None,
+            estimate: task.estimate,
+            points: task.points,
+            udas: utils::uda_rows(&task.udas).into_iter().collect(),
+            annotations: task.annotations.clone(),
+        }
+    }
+}
+
+impl EditForm {
+    fn validate(&self, cfg: &MyConfig, completed: bool) -> Result<(), SigoError> {
+        if self.description.trim().is_empty() {
+            return Err(SigoError::InvalidEdit("description is empty".to_owned()));
+        }
+        if let Some(tag) = self.tags.iter().find(|t| t.is_empty() || t.contains(' ')) {
+            return Err(SigoError::InvalidEdit(format!("invalid tag '{}'", tag)));
+        }
+        if let Some(name) = self.udas.keys().find(|k| !cfg.udas.contains(k)) {
+            return Err(SigoError::InvalidEdit(format!(
+                "'{}' is not a configured uda",
+                name
+            )));
+        }
+        if completed && (self.due.is_some() || self.scheduled.is_some() || self.until.is_some()) {
+            return Err(SigoError::InvalidEdit(
+                "completed sigos have no due, scheduled or until date".to_owned(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Changes from `self` to `edited`
+    fn diff(&self, edited: EditForm) -> Modification {
+        fn changed<T: PartialEq>(before: T, after: T) -> Option<T> {
+            Some(after).filter(|after| *after != before)
+        }
+        let mut udas: BTreeMap<String, String> = edited
+            .udas
+            .iter()
+            .filter(|(k, v)| self.udas.get(*k) != Some(*v))
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        for name in self.udas.keys().filter(|k| !edited.udas.contains_key(*k)) {
+            udas.insert(name.to_owned(), String::new());
+        }
+        Modification {
+            description: changed(self.description.clone(), edited.description),
+            priority: changed(self.priority, edited.priority),
+            add_tags: edited
+                .tags
+                .iter()
+                .filter(|t| !self.tags.contains(t))
+                .cloned()
+                .collect(),
+            remove_tags: self
+                .tags
+                .iter()
+                .filter(|t| !edited.tags.contains(t))
+                .cloned()
+                .collect(),
+            project: changed(self.project.clone(), edited.project),
+            assignee: changed(self.assignee.clone(), edited.assignee),
+            udas,
+            due: changed(self.due, edited.due),
+            scheduled: changed(self.scheduled, edited.scheduled),
+            until: changed(self.until, edited.until),
+            estimate: changed(self.estimate, edited.estimate),
+            points: changed(self.points, edited.points),
+            annotations: changed(self.annotations.clone(), edited.annotations),
+            reviewed: false,
+        }
+    }
+}
+
+/// Open the sigo in the editor and return the changes made, or `None` if nothing changed
+pub fn edit(cfg: &MyConfig, task: &Task) -> Result<Option<Modification>, SigoError> {
+    let form = match task {
+        Task::Ready(task) => EditForm::from(task),
+        Task::Waiting(task) => EditForm::from(task),
+        Task::Completed(task) => EditForm::from(task),
+        Task::Deleted(task) => return Err(SigoError::NotOpen(task.uuid)),
+    };
+    let path = std::env::temp_dir().join(format!("sigo-edit-{}.toml", process::id()));
+    let text = toml::to_string(&form).map_err(SigoError::EditSerializeErr)?;
+    fs::write(&path, format!("{}{}", HEADER, text))
+        .map_err(|e| SigoError::FileWriteErr(path.clone(), e))?;
+
+    let editor = cfg.editor();
+    let mut words = editor.split_whitespace();
+    let status = process::Command::new(words.next().unwrap_or("vi"))
+        .args(words)
+        .arg(&path)
+        .status()
+        .map_err(|e| SigoError::EditorErr(editor.clone(), e.to_string()))?;
+    let edited = fs::read_to_string(&path).map_err(|e| SigoError::FileReadErr(path.clone(), e));
+    let _ = fs::remove_file(&path);
+    if !status.success() {
+        return Err(SigoError::EditorErr(editor, status.to_string()));
+    }
+
+    let edited: EditForm = toml::from_str(&edited?).map_err(SigoError::EditParseErr)?;
+    edited.validate(cfg, matches!(task, Task::Completed(_)))?;
+    if edited == form {
+        return Ok(None);
+    }
+    Ok(Some(form.diff(edited)))
+}
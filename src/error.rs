@@ -1,48 +1,189 @@
-use std::{error::Error, fmt, path::PathBuf};
+use std::path::{Path, PathBuf};
+
+use serde_json::error::Category;
+use thiserror::Error;
+use uuid::Uuid;
 
 pub type Result<T> = std::result::Result<T, SigoError>;
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum SigoError {
+    #[error("unable to create file {0:?}: {1}")]
     FileCreateErr(PathBuf, std::io::Error),
+    #[error("unable to read file {0:?}: {1}")]
     FileReadErr(PathBuf, std::io::Error),
+    #[error("unable to write file {0:?}: {1}")]
     FileWriteErr(PathBuf, std::io::Error),
+    #[error("unable to rename file {0:?} to file {1:?}: {2}")]
     FileRenameErr(PathBuf, PathBuf, std::io::Error),
+    #[error("unable to read stdin: {0}")]
+    StdinReadErr(std::io::Error),
+    #[error("unable to attach file {0:?}: {1}")]
+    AttachmentNotFound(PathBuf, std::io::Error),
+    #[error("unable to open {0}: {1}")]
+    OpenErr(String, std::io::Error),
+    #[error("{}", parse_message(&format!("file {:?}", .0), .1))]
     ParseStrToTasksErr(PathBuf, serde_json::Error),
-    ParseTasksToStrErr(serde_json::Error),
+    /// A collection of a file keeping several of them, like the database of the sqlite backend
+    #[error("{}", parse_message(&format!("collection {} of {:?}", .1, .0), .2))]
+    ParseCollectionErr(PathBuf, String, serde_json::Error),
+    #[error("{}", line_message(.0, *.1, .2))]
+    ParseLineErr(PathBuf, usize, serde_json::Error),
+    #[error(
+        "{0:?} is corrupted or was partially written: {1}; run `sigo doctor`, or restore it \
+         with `sigo restore-backup`, and if it was edited by hand remove its checksum"
+    )]
+    Corrupted(PathBuf, String),
+    #[error("unable to parse sigo {0}")]
+    ParseTasksToStrErr(#[from] serde_json::Error),
+    #[error("unable to use database {0:?}: {1}")]
+    DatabaseErr(PathBuf, rusqlite::Error),
+    #[error("{0:?} was changed by another sigo while this one was running; run it again")]
+    Conflict(PathBuf),
+    #[error("{0} needs the sigos stored in files, but they are kept in memory")]
+    InMemory(&'static str),
+    #[error("unable to use key file {0:?}: {1}")]
+    KeyErr(PathBuf, String),
+    #[error("{0:?} is encrypted; set key_file in the config to read it")]
+    EncryptedData(PathBuf),
+    #[error(
+        "unable to decrypt {0:?}: the key file is not the one it was encrypted with, or it is damaged"
+    )]
+    DecryptErr(PathBuf),
+    #[error("{0:?} is in format version {1}, written by a newer sigo; upgrade sigo to read it")]
+    NewerFormat(PathBuf, u32),
+    #[error("not found sigo {0}")]
     TaskNotFound(u32),
+    #[error("not found sigo uuid {0}")]
+    UuidNotFound(String),
+    #[error("uuid {0} matches more than one sigo")]
+    AmbiguousUuid(String),
+    #[error("recurring sigo needs a due date")]
+    RecurWithoutDue,
+    #[error("recurring sigo {0} is due on the last date there can be; change its due date")]
+    RecurPastLastDate(u32),
+    #[error("no id is left above those issued; turn off monotonic_ids or renumber the sigos")]
+    NoIdLeft,
+    #[error("no home directory to find the config in; set $HOME")]
+    NoHomeDir,
+    #[error(
+        "no data directory, as the default one is not valid UTF-8; set data in the config or \
+         give --data-dir"
+    )]
+    NoDataDir,
+    #[error("sigo {0} still has children which are not done")]
+    OpenChildren(u32),
+    #[error("sigo {0} is already active")]
+    AlreadyActive(u32),
+    #[error("no active sigo")]
+    NoActiveTask,
+    #[error("sigo {0} has no attachment")]
+    NoAttachment(u32),
+    #[error("unknown context {0}")]
+    UnknownContext(String),
+    #[error("unknown report {0}")]
+    UnknownReport(String),
+    #[error("unknown query @{0}")]
+    UnknownQuery(String),
+    #[error("unknown backup {0}")]
+    UnknownBackup(String),
+    #[error(
+        "{0} journaled changes were made since the backup and would be lost; give --force to \
+         restore it anyway"
+    )]
+    JournalWouldBeLost(usize),
+    #[error("invalid query name '{0}': use letters, digits, - and _")]
+    InvalidQueryName(String),
+    #[error("unable to read config {0:?}: {1}")]
+    ConfigLoadErr(PathBuf, confy::ConfyError),
+    #[error("unable to write config: {0}")]
+    ConfigWriteErr(confy::ConfyError),
+    #[error("invalid profile name '{0}': use letters, digits, '-' and '_'")]
+    InvalidProfileName(String),
+    #[error("unable to use profile {0:?}: {1}")]
+    ProfileErr(PathBuf, String),
+    #[error("sigo {0} is not ready or waiting")]
+    NotOpen(Uuid),
+    #[error("sigo {0} is already completed")]
+    AlreadyCompleted(String),
+    #[error("sigo {0} is not waiting")]
+    NotWaiting(String),
+    #[error("sigo {0} is already waiting")]
+    AlreadyWaiting(String),
+    #[error("sigo {0} is waiting, so it cannot be started before it is back")]
+    NotReady(String),
+    #[error("sigo {0} is not completed")]
+    NotCompleted(Uuid),
+    #[error("nothing to undo")]
+    NothingToUndo,
+    #[error("nothing to roll back: no snapshot of a destructive command is left in the trash")]
+    NothingInTrash,
+    #[error("the command would change the data, which is opened with --read-only")]
+    ReadOnly,
+    #[error("unable to run editor {0}: {1}")]
+    EditorErr(String, String),
+    #[error("unable to write sigo for editing: {0}")]
+    EditSerializeErr(toml::ser::Error),
+    #[error("invalid edit, nothing changed:\n{0}")]
+    EditParseErr(toml::de::Error),
+    #[error("invalid edit, nothing changed: {0}")]
+    InvalidEdit(String),
+    #[error("invalid pattern: {0}")]
+    InvalidPattern(regex::Error),
+    #[error("invalid filter: {0}")]
+    InvalidFilter(String),
+    #[error("invalid sort in config: {0}")]
+    InvalidSort(String),
+    #[error("unknown column '{0}' ({1})")]
+    UnknownColumn(String, String),
+    #[error("no annotation contains {0}")]
+    NoMatchingAnnotation(String),
+    /// Outputs of the sigos which succeeded and errors of those which failed
+    #[error("{}", failures_message(.0, .1))]
+    Failures(Vec<String>, Vec<(String, SigoError)>),
 }
 
-impl fmt::Display for SigoError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            SigoError::FileCreateErr(path, ref err) => {
-                writeln!(f, "unable to create file {:?}: {}", path, err)
-            }
-            SigoError::FileReadErr(path, ref err) => {
-                writeln!(f, "unable to read file {:?}: {}", path, err)
-            }
-            SigoError::FileWriteErr(path, ref err) => {
-                writeln!(f, "unable to write file {:?}: {}", path, err)
-            }
-            SigoError::FileRenameErr(srcpath, tarpath, ref err) => writeln!(
-                f,
-                "unable to rename file {:?} to file {:?}: {}",
-                srcpath, tarpath, err
-            ),
-            SigoError::ParseStrToTasksErr(path, ref err) => {
-                writeln!(f, "unbale to parse file {:?}: {}", path, err)
-            }
-            SigoError::ParseTasksToStrErr(err) => writeln!(f, "unbale to parse sigo {}", err),
-            SigoError::TaskNotFound(id) => writeln!(f, "not found sigo {}", id),
-        }
+fn parse_message(what: &str, err: &serde_json::Error) -> String {
+    match err.classify() {
+        Category::Data => format!(
+            "{} is not in the format this sigo reads: {}; it may have been edited by hand or \
+             written by another program",
+            what, err
+        ),
+        Category::Syntax | Category::Eof => format!(
+            "unable to parse {}: {}; it is corrupted or was partially written",
+            what, err
+        ),
+        Category::Io => format!("unable to parse {}: {}", what, err),
     }
 }
 
-impl Error for SigoError {}
-
-impl From<serde_json::Error> for SigoError {
-    fn from(value: serde_json::Error) -> Self {
-        SigoError::ParseTasksToStrErr(value)
+fn line_message(path: &Path, line: usize, err: &serde_json::Error) -> String {
+    match err.classify() {
+        Category::Data => format!(
+            "line {} of file {:?} is not a sigo in the format this sigo reads: {}; fix or remove \
+             the line",
+            line, path, err
+        ),
+        _ => format!(
+            "unable to parse line {} of file {:?}: {}; it is corrupted or was partially written, \
+             fix or remove the line",
+            line, path, err
+        ),
     }
 }
+
+fn failures_message(outputs: &[String], failures: &[(String, SigoError)]) -> String {
+    let mut lines = vec![format!(
+        "failed for {} of {} sigos",
+        failures.len(),
+        outputs.len() + failures.len()
+    )];
+    lines.extend(
+        failures
+            .iter()
+            .map(|(task_ref, err)| format!("  {}: {}", task_ref, err)),
+    );
+    lines.extend(outputs.iter().cloned());
+    lines.join("\n")
+}
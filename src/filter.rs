@@ -0,0 +1,338 @@
+use chrono::{Local, NaiveDate};
+use regex::{Regex, RegexBuilder};
+
+use crate::{config::MyConfig, error::SigoError, task::Task, utils};
+
+const STATUSES: [&str; 4] = ["ready", "waiting", "completed", "deleted"];
+
+/// Which sigos a command applies to, e.g. `project:home and (+urgent or due.before:friday)`
+#[derive(Debug, Clone)]
+pub enum Filter {
+    /// Every sigo, given by an empty filter
+    All,
+    Tag(String),
+    /// Tag computed from the sigo instead of stored, like `+OVERDUE`
+    Virtual(VirtualTag),
+    /// The project or one of its sub projects, no project when empty
+    Project(String),
+    /// Nobody when empty
+    Assignee(String),
+    Uda(String, String),
+    Status(String),
+    DueBefore(NaiveDate),
+    DueAfter(NaiveDate),
+    ScheduledBefore(NaiveDate),
+    ScheduledAfter(NaiveDate),
+    CompletedBefore(NaiveDate),
+    CompletedAfter(NaiveDate),
+    /// Description containing the word, ignoring case
+    Word(String),
+    /// Description matching the regular expression, given like `description~/pat{3}ern/`
+    Regex(Regex),
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+/// Tags sigos have by their attributes, in capitals like taskwarrior's
+#[derive(Debug, Clone, Copy)]
+pub enum VirtualTag {
+    Overdue,
+    DueToday,
+    Blocked,
+    Waiting,
+    /// Being tracked
+    Active,
+    /// Having any tag
+    Tagged,
+}
+
+impl VirtualTag {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "OVERDUE" => Some(VirtualTag::Overdue),
+            "DUETODAY" => Some(VirtualTag::DueToday),
+            "BLOCKED" => Some(VirtualTag::Blocked),
+            "WAITING" => Some(VirtualTag::Waiting),
+            "ACTIVE" => Some(VirtualTag::Active),
+            "TAGGED" => Some(VirtualTag::Tagged),
+            _ => None,
+        }
+    }
+
+    fn matches(&self, task: &Task) -> bool {
+        let today = Local::now().date_naive();
+        match self {
+            VirtualTag::Overdue => task.due().is_some_and(|due| due < today),
+            VirtualTag::DueToday => task.due() == Some(today),
+            VirtualTag::Blocked => task.is_blocked(),
+            VirtualTag::Waiting => task.status() == "waiting",
+            VirtualTag::Active => task.is_active(),
+            VirtualTag::Tagged => !task.tags().is_empty(),
+        }
+    }
+}
+
+impl Filter {
+    /// Parse command-line words. Terms next to each other must all match, `or` and `not` bind as
+    /// usual and parentheses group terms.
+    pub fn parse(cfg: &MyConfig, words: &[String]) -> Result<Self, SigoError> {
+        Self::parse_text(cfg, &words.join(" "), Vec::new())
+    }
+
+    /// Parse inside the saved queries being expanded
+    fn parse_text(cfg: &MyConfig, text: &str, queries: Vec<String>) -> Result<Self, SigoError> {
+        let tokens = tokenize(text);
+        if tokens.is_empty() {
+            return Ok(Filter::All);
+        }
+        let mut parser = Parser {
+            cfg,
+            tokens,
+            pos: 0,
+            queries,
+        };
+        let filter = parser.or()?;
+        match parser.next() {
+            Some(token) => Err(SigoError::InvalidFilter(format!("unexpected '{}'", token))),
+            None => Ok(filter),
+        }
+    }
+
+    /// Sigos completed from the first day to the last one, both included
+    pub fn completed_between(from: Option<NaiveDate>, to: Option<NaiveDate>) -> Filter {
+        let from = from
+            .and_then(|from| from.pred_opt())
+            .map_or(Filter::All, Filter::CompletedAfter);
+        let to = to
+            .and_then(|to| to.succ_opt())
+            .map_or(Filter::All, Filter::CompletedBefore);
+        from.and(to)
+    }
+
+    pub fn and(self, other: Filter) -> Filter {
+        match (self, other) {
+            (Filter::All, filter) | (filter, Filter::All) => filter,
+            (left, right) => Filter::And(Box::new(left), Box::new(right)),
+        }
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Tag(tag) => task.tags().contains(tag),
+            Filter::Virtual(tag) => tag.matches(task),
+            Filter::Project(project) => utils::is_in_project(task.project(), project),
+            Filter::Assignee(assignee) if assignee.is_empty() => task.assignee().is_none(),
+            Filter::Assignee(assignee) => task.assignee().as_ref() == Some(assignee),
+            Filter::Uda(key, value) => {
+                task.udas().get(key).and_then(|v| v.as_str()) == Some(value.as_str())
+            }
+            Filter::Status(status) => task.status() == status,
+            Filter::DueBefore(date) => task.due().is_some_and(|due| due < *date),
+            Filter::DueAfter(date) => task.due().is_some_and(|due| due > *date),
+            Filter::ScheduledBefore(date) => task.scheduled().is_some_and(|s| s < *date),
+            Filter::ScheduledAfter(date) => task.scheduled().is_some_and(|s| s > *date),
+            Filter::CompletedBefore(date) => task.completed_on().is_some_and(|c| c < *date),
+            Filter::CompletedAfter(date) => task.completed_on().is_some_and(|c| c > *date),
+            Filter::Word(word) => task
+                .description()
+                .to_lowercase()
+                .contains(&word.to_lowercase()),
+            Filter::Regex(regex) => regex.is_match(&task.description()),
+            Filter::Not(filter) => !filter.matches(task),
+            Filter::And(left, right) => left.matches(task) && right.matches(task),
+            Filter::Or(left, right) => left.matches(task) || right.matches(task),
+        }
+    }
+}
+
+fn tag_filter(tag: &str) -> Filter {
+    VirtualTag::parse(tag).map_or_else(|| Filter::Tag(tag.to_owned()), Filter::Virtual)
+}
+
+/// Split into terms and parentheses, which may be attached to the terms as in `(+urgent`.
+/// Regular expressions are kept whole up to their closing slash.
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if token.ends_with('~') => {
+                token.push(c);
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    token.push(c);
+                    if c == '/' && !escaped {
+                        break;
+                    }
+                    escaped = c == '\\' && !escaped;
+                }
+            }
+            '(' | ')' => {
+                tokens.extend(Some(std::mem::take(&mut token)).filter(|t| !t.is_empty()));
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => {
+                tokens.extend(Some(std::mem::take(&mut token)).filter(|t| !t.is_empty()));
+            }
+            c => token.push(c),
+        }
+    }
+    tokens.extend(Some(token).filter(|t| !t.is_empty()));
+    tokens
+}
+
+struct Parser<'a> {
+    cfg: &'a MyConfig,
+    tokens: Vec<String>,
+    pos: usize,
+    /// Names of the saved queries this is in
+    queries: Vec<String>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<Filter, SigoError> {
+        let mut filter = self.and()?;
+        while self.peek() == Some("or") {
+            self.pos += 1;
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, SigoError> {
+        let mut filter = self.unary()?;
+        loop {
+            match self.peek() {
+                Some("and") => self.pos += 1,
+                Some("or") | Some(")") | None => return Ok(filter),
+                Some(_) => {}
+            }
+            filter = Filter::And(Box::new(filter), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Filter, SigoError> {
+        match self.next().as_deref() {
+            Some("not") => Ok(Filter::Not(Box::new(self.unary()?))),
+            Some("(") => {
+                let filter = self.or()?;
+                match self.next().as_deref() {
+                    Some(")") => Ok(filter),
+                    _ => Err(SigoError::InvalidFilter("missing ')'".to_owned())),
+                }
+            }
+            Some(token @ (")" | "and" | "or")) => {
+                Err(SigoError::InvalidFilter(format!("unexpected '{}'", token)))
+            }
+            Some(token) => self.term(token),
+            None => Err(SigoError::InvalidFilter(
+                "missing a term at the end".to_owned(),
+            )),
+        }
+    }
+
+    fn term(&self, token: &str) -> Result<Filter, SigoError> {
+        if let Some(tag) = token.strip_prefix('+').filter(|t| !t.is_empty()) {
+            return Ok(tag_filter(tag));
+        }
+        if let Some(tag) = token.strip_prefix('-').filter(|t| !t.is_empty()) {
+            return Ok(Filter::Not(Box::new(tag_filter(tag))));
+        }
+        if let Some(name) = token.strip_prefix('@').filter(|n| !n.is_empty()) {
+            return self.query(name);
+        }
+        if let Some(regex) = token.strip_prefix("description~") {
+            return self.regex(token, regex);
+        }
+        let Some((key, value)) = token.split_once(':') else {
+            return Ok(Filter::Word(token.to_owned()));
+        };
+        let date = || {
+            utils::parse_date(value)
+                .map_err(|e| SigoError::InvalidFilter(format!("{} in '{}'", e, token)))
+        };
+        let is_attribute = |key: &str| {
+            ["project", "assignee", "status"].contains(&key)
+                || self.cfg.udas.iter().any(|u| u == key)
+        };
+        match key {
+            // Like `project.not:home` for everything but the project
+            key if key.strip_suffix(".not").is_some_and(is_attribute) => {
+                let term = format!("{}:{}", &key[..key.len() - ".not".len()], value);
+                Ok(Filter::Not(Box::new(self.term(&term)?)))
+            }
+            "project" => Ok(Filter::Project(value.to_owned())),
+            // Everybody when nobody is `me`
+            "assignee" => Ok(self
+                .cfg
+                .resolve_assignee(Some(value.to_owned()))
+                .map_or(Filter::All, Filter::Assignee)),
+            "status" if STATUSES.contains(&value) => Ok(Filter::Status(value.to_owned())),
+            "status" => Err(SigoError::InvalidFilter(format!(
+                "unknown status '{}' (ready, waiting, completed or deleted)",
+                value
+            ))),
+            "due.before" => Ok(Filter::DueBefore(date()?)),
+            "due.after" => Ok(Filter::DueAfter(date()?)),
+            "scheduled.before" => Ok(Filter::ScheduledBefore(date()?)),
+            "scheduled.after" => Ok(Filter::ScheduledAfter(date()?)),
+            "completed.before" => Ok(Filter::CompletedBefore(date()?)),
+            "completed.after" => Ok(Filter::CompletedAfter(date()?)),
+            key if self.cfg.udas.iter().any(|uda| uda == key) => {
+                Ok(Filter::Uda(key.to_owned(), value.to_owned()))
+            }
+            _ => Ok(Filter::Word(token.to_owned())),
+        }
+    }
+
+    /// Filter saved by `sigo query save`
+    fn query(&self, name: &str) -> Result<Filter, SigoError> {
+        if self.queries.iter().any(|q| q == name) {
+            return Err(SigoError::InvalidFilter(format!(
+                "query @{} refers to itself",
+                name
+            )));
+        }
+        let text = self
+            .cfg
+            .queries
+            .get(name)
+            .ok_or_else(|| SigoError::UnknownQuery(name.to_owned()))?;
+        let mut queries = self.queries.clone();
+        queries.push(name.to_owned());
+        Filter::parse_text(self.cfg, text, queries)
+    }
+
+    /// `/pattern/`, or `/pattern/i` to ignore case
+    fn regex(&self, token: &str, regex: &str) -> Result<Filter, SigoError> {
+        let invalid =
+            |reason: String| SigoError::InvalidFilter(format!("{} in '{}'", reason, token));
+        let (pattern, flags) = regex
+            .strip_prefix('/')
+            .and_then(|regex| regex.rsplit_once('/'))
+            .ok_or_else(|| invalid("expected /pattern/".to_owned()))?;
+        let ignore_case = match flags {
+            "" => self.cfg.regex_ignore_case,
+            "i" => true,
+            _ => return Err(invalid(format!("unknown flags '{}'", flags))),
+        };
+        RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map(Filter::Regex)
+            .map_err(|e| invalid(e.to_string()))
+    }
+}
@@ -0,0 +1,44 @@
+use std::{cell::Cell, fs, path::PathBuf};
+
+use crate::{
+    config::MyConfig,
+    error::SigoError,
+    storage::{self, Backend},
+};
+
+pub const FILE_NAME: &str = "next_id";
+
+thread_local! {
+    /// Next id issued while sigos are kept in memory
+    static IN_MEMORY: Cell<u32> = const { Cell::new(1) };
+}
+
+fn counter_path(cfg: &MyConfig) -> PathBuf {
+    PathBuf::from(&cfg.data).join(FILE_NAME)
+}
+
+/// Id issued next with `monotonic_ids`, 1 before any was
+pub fn read_next(cfg: &MyConfig) -> Result<u32, SigoError> {
+    if cfg.storage == Backend::Memory {
+        return Ok(IN_MEMORY.get());
+    }
+    let path = counter_path(cfg);
+    if !path.is_file() {
+        return Ok(1);
+    }
+    let next = fs::read_to_string(&path).map_err(|e| SigoError::FileReadErr(path.clone(), e))?;
+    // A broken counter starts over above the ids in use
+    Ok(next.trim().parse().unwrap_or(1))
+}
+
+pub fn write_next(cfg: &MyConfig, next: u32) -> Result<(), SigoError> {
+    if cfg.storage == Backend::Memory {
+        IN_MEMORY.set(next);
+        return Ok(());
+    }
+    storage::write_atomically(
+        &counter_path(cfg),
+        next.to_string().as_bytes(),
+        cfg.durability,
+    )
+}
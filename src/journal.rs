@@ -0,0 +1,249 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tabled::Tabled;
+use uuid::Uuid;
+
+use crate::{
+    config::MyConfig,
+    crypt::Crypt,
+    error::SigoError,
+    storage::{self, Backend},
+    task::{CompletedTask, DeletedTask, ReadyTask, RecurringTask, WaitingTask},
+    utils,
+};
+
+const FILE_NAME: &str = "journal";
+/// Older entries are dropped, so the journal does not grow forever
+const MAX_ENTRIES: usize = 100;
+
+const TASK_FILES: [&str; 5] = [
+    ReadyTask::FILE_NAME,
+    WaitingTask::FILE_NAME,
+    CompletedTask::FILE_NAME,
+    DeletedTask::FILE_NAME,
+    RecurringTask::FILE_NAME,
+];
+
+/// Task files as they were before a command changed them
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub entry: DateTime<Local>,
+    pub command: String,
+    files: BTreeMap<String, String>,
+}
+
+/// Change to a sigo found in the journal
+#[derive(Tabled)]
+pub struct Event {
+    #[tabled(rename = "when", display_with = "utils::display_datetime")]
+    pub entry: DateTime<Local>,
+    pub event: String,
+    pub command: String,
+}
+
+thread_local! {
+    /// Journal while sigos are kept in memory
+    static IN_MEMORY: RefCell<Vec<Entry>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Task files taken before running a command, from whichever storage backend keeps them
+pub struct Snapshot(BTreeMap<String, String>);
+
+fn data_path(cfg: &MyConfig, name: &str) -> PathBuf {
+    let mut path = PathBuf::from(&cfg.data);
+    path.push(name);
+    path
+}
+
+fn read_file(cfg: &MyConfig, crypt: &Crypt, name: &str) -> Result<String, SigoError> {
+    let path = data_path(cfg, name);
+    if !utils::create_file_if_not_exist(&path)? {
+        return Ok("[]".to_owned());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| SigoError::FileReadErr(path.clone(), e))?;
+    crypt.open(&path, content)
+}
+
+fn write_file(cfg: &MyConfig, crypt: &Crypt, name: &str, content: String) -> Result<(), SigoError> {
+    let path = data_path(cfg, name);
+    storage::write_atomically(&path, crypt.seal(content)?.as_bytes(), cfg.durability)
+}
+
+fn read_entries(cfg: &MyConfig) -> Result<Vec<Entry>, SigoError> {
+    if cfg.storage == Backend::Memory {
+        return Ok(IN_MEMORY.with_borrow(Clone::clone));
+    }
+    let journal = read_file(cfg, &Crypt::new(cfg), FILE_NAME)?;
+    serde_json::from_str(&journal)
+        .map_err(|e| SigoError::ParseStrToTasksErr(data_path(cfg, FILE_NAME), e))
+}
+
+/// Number of entries journaled since the journal in the directory, like that of a backup, was
+/// taken, which would be lost by going back to it
+pub fn entries_since(cfg: &MyConfig, dir: &Path) -> Result<usize, SigoError> {
+    let path = dir.join(FILE_NAME);
+    let taken = match fs::read_to_string(&path) {
+        Ok(journal) => serde_json::from_str::<Vec<Entry>>(&Crypt::new(cfg).open(&path, journal)?)
+            .map_err(|e| SigoError::ParseStrToTasksErr(path, e))?
+            .last()
+            .map(|entry| entry.entry),
+        Err(_) => None,
+    };
+    Ok(read_entries(cfg)?
+        .iter()
+        .filter(|entry| taken.is_none_or(|taken| entry.entry > taken))
+        .count())
+}
+
+fn write_entries(cfg: &MyConfig, entries: &[Entry]) -> Result<(), SigoError> {
+    if cfg.storage == Backend::Memory {
+        IN_MEMORY.set(entries.to_vec());
+        return Ok(());
+    }
+    write_file(
+        cfg,
+        &Crypt::new(cfg),
+        FILE_NAME,
+        serde_json::to_string(entries)?,
+    )
+}
+
+impl Snapshot {
+    pub fn take(cfg: &MyConfig) -> Result<Self, SigoError> {
+        let storage = storage::open(cfg);
+        let mut files = BTreeMap::new();
+        for name in TASK_FILES {
+            files.insert(name.to_owned(), storage.read(name)?);
+        }
+        for name in CompletedTask::archive_file_names(cfg)? {
+            let content = storage.read(&name)?;
+            files.insert(name, content);
+        }
+        Ok(Snapshot(files))
+    }
+
+    /// Journal the files changed since the snapshot, if any
+    pub fn record(mut self, cfg: &MyConfig, command: &str) -> Result<(), SigoError> {
+        // Archive files created by the command were empty before it
+        for name in CompletedTask::archive_file_names(cfg)? {
+            self.0.entry(name).or_insert_with(|| "[]".to_owned());
+        }
+        let storage = storage::open(cfg);
+        let mut changed = BTreeMap::new();
+        for (name, before) in self.0 {
+            if storage.read(&name)? != before {
+                changed.insert(name, before);
+            }
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+        let mut entries = read_entries(cfg)?;
+        entries.push(Entry {
+            entry: Local::now(),
+            command: command.to_owned(),
+            files: changed,
+        });
+        let overflow = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..overflow);
+        write_entries(cfg, &entries)
+    }
+}
+
+/// State of the sigos stored in the file
+fn state_of(name: &str) -> Option<&'static str> {
+    match name {
+        ReadyTask::FILE_NAME => Some("ready"),
+        WaitingTask::FILE_NAME => Some("waiting"),
+        CompletedTask::FILE_NAME => Some("completed"),
+        DeletedTask::FILE_NAME => Some("deleted"),
+        RecurringTask::FILE_NAME => None,
+        _ => Some("archived"),
+    }
+}
+
+/// State and attributes of the sigo in the files, ignoring when it was modified
+fn find(files: &BTreeMap<String, String>, uuid: &str) -> Option<(&'static str, Value)> {
+    files.iter().find_map(|(name, content)| {
+        let state = state_of(name)?;
+        let tasks = serde_json::from_str::<Vec<Value>>(content).ok()?;
+        let mut task = tasks.into_iter().find(|t| t["uuid"] == uuid)?;
+        task.as_object_mut()?.remove("modified_at");
+        Some((state, task))
+    })
+}
+
+fn describe(before: Option<(&str, Value)>, after: Option<(&str, Value)>) -> Option<String> {
+    let event = match (before, after) {
+        (None, Some(("completed", _))) => "logged".to_owned(),
+        (None, Some(_)) => "created".to_owned(),
+        (Some((_, _)), None) => "purged".to_owned(),
+        (Some((before, _)), Some((after, _))) if before != after => match (before, after) {
+            (_, "waiting") => "waited".to_owned(),
+            ("waiting", "ready") => "resumed".to_owned(),
+            (_, "ready") => "restored".to_owned(),
+            (_, after) => after.to_owned(),
+        },
+        (Some((_, before)), Some((_, after))) if before != after => "modified".to_owned(),
+        _ => return None,
+    };
+    Some(event)
+}
+
+/// Changes to the sigo recorded in the journal, oldest first
+pub fn history(cfg: &MyConfig, uuid: Uuid) -> Result<Vec<Event>, SigoError> {
+    let storage = storage::open(cfg);
+    let mut files = BTreeMap::new();
+    for name in TASK_FILES
+        .iter()
+        .map(|name| name.to_string())
+        .chain(CompletedTask::archive_file_names(cfg)?)
+    {
+        let content = storage.read(&name)?;
+        files.insert(name, content);
+    }
+    let uuid = uuid.to_string();
+    let mut events = Vec::new();
+    // Walk back from the current files, restoring them as they were before each entry
+    for entry in read_entries(cfg)?.into_iter().rev() {
+        let after = find(&files, &uuid);
+        files.extend(entry.files);
+        if let Some(event) = describe(find(&files, &uuid), after) {
+            events.push(Event {
+                entry: entry.entry,
+                event,
+                command: entry.command,
+            });
+        }
+    }
+    events.reverse();
+    Ok(events)
+}
+
+/// Write the journal again, read and written with the keys given
+pub fn rekey(cfg: &MyConfig, crypt: &Crypt) -> Result<(), SigoError> {
+    let journal = read_file(cfg, crypt, FILE_NAME)?;
+    write_file(cfg, crypt, FILE_NAME, journal)
+}
+
+/// Restore the files changed by the last journaled command and drop its entry
+pub fn undo(cfg: &MyConfig) -> Result<Entry, SigoError> {
+    let mut entries = read_entries(cfg)?;
+    let entry = entries.pop().ok_or(SigoError::NothingToUndo)?;
+    let collections = entry
+        .files
+        .iter()
+        .map(|(name, content)| (name.as_str(), content.clone()))
+        .collect::<Vec<(&str, String)>>();
+    storage::open(cfg).write_all(&collections)?;
+    write_entries(cfg, &entries)?;
+    Ok(entry)
+}
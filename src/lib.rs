@@ -0,0 +1,827 @@
+use std::{fs, path::PathBuf};
+
+use chrono::{Days, Months, NaiveDate};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use config::MyConfig;
+use serde::{Deserialize, Serialize};
+use storage::{Backend, Mode};
+use strum::Display;
+use task::{TaskRef, TaskRefs};
+
+mod backup;
+mod chart;
+pub mod command;
+pub mod config;
+mod context;
+mod crypt;
+mod doctor;
+mod edit;
+pub mod error;
+pub mod filter;
+mod ids;
+mod journal;
+pub mod migrations;
+mod operations;
+mod report;
+mod sort;
+pub mod storage;
+pub mod task;
+mod trash;
+mod utils;
+
+#[derive(Parser, Clone)]
+#[command(author, version, about, long_about = None)]
+pub struct AppArg {
+    #[clap(subcommand)]
+    command: Command,
+
+    /// Profile with sigos and config overrides of its own, created when first used
+    #[arg(long, global = true, env = "SIGO_PROFILE")]
+    profile: Option<String>,
+
+    /// Directory of the sigos, in place of the one in the config
+    #[arg(long, global = true, env = "SIGO_HOME")]
+    data_dir: Option<PathBuf>,
+
+    /// Keep the sigos in memory with the default config, reading and writing no files
+    #[arg(long, global = true)]
+    ephemeral: bool,
+
+    /// Run the command, showing what it would change instead of writing anything
+    #[arg(short = 'n', long, global = true)]
+    dry_run: bool,
+
+    /// Fail before writing anything for commands which change the sigos or other data
+    #[arg(long, global = true, conflicts_with = "dry_run")]
+    read_only: bool,
+}
+
+#[derive(Subcommand, Clone)]
+enum Command {
+    /// Add sigo
+    Add {
+        /// Description, +tags and project:name
+        #[arg(required = true)]
+        description: Vec<String>,
+
+        /// Priority(H/M/L)
+        #[arg(value_enum, short, long, default_value_t = Priority::M)]
+        priority: Priority,
+
+        /// Waiting
+        #[arg(short, long)]
+        waiting: bool,
+
+        /// Due date(YYYY-MM-DD, today, tomorrow or weekday name)
+        #[arg(short, long, value_parser = utils::parse_date)]
+        due: Option<NaiveDate>,
+
+        /// Date not to list the sigo before(YYYY-MM-DD, today, tomorrow or weekday name)
+        #[arg(short, long, value_parser = utils::parse_date)]
+        scheduled: Option<NaiveDate>,
+
+        /// Estimated time to finish(e.g. 30min, 2h)
+        #[arg(short, long, value_parser = utils::parse_duration)]
+        estimate: Option<chrono::Duration>,
+
+        /// Effort points
+        #[arg(long)]
+        points: Option<u32>,
+
+        /// Date after which the sigo is pointless and gets deleted
+        #[arg(long, value_parser = utils::parse_date)]
+        until: Option<NaiveDate>,
+
+        /// Recurrence, which needs a due date
+        #[arg(value_enum, short, long)]
+        recur: Option<Recurrence>,
+
+        /// Ids, ranges or uuids of sigos which must be done before this one(comma separated)
+        #[arg(long)]
+        depends: Vec<TaskRefs>,
+
+        /// Id or uuid of the parent sigo
+        #[arg(long)]
+        parent: Option<TaskRef>,
+    },
+
+    /// Modify sigo
+    Modify {
+        /// Ids, ranges like 7-9 or uuids(comma separated)
+        id: TaskRefs,
+
+        /// New description, +tags to add, -tags to remove, project:name and assignee:name.
+        /// Options have to come before these words
+        #[arg(allow_hyphen_values = true)]
+        words: Vec<String>,
+
+        /// Description text
+        #[arg(short, long)]
+        text: Option<String>,
+
+        /// Priority(H/M/L)
+        #[arg(value_enum, short, long)]
+        priority: Option<Priority>,
+
+        /// Due date(YYYY-MM-DD, today, tomorrow or weekday name)
+        #[arg(short, long, value_parser = utils::parse_date)]
+        due: Option<NaiveDate>,
+
+        /// Date not to list the sigo before
+        #[arg(short, long, value_parser = utils::parse_date)]
+        scheduled: Option<NaiveDate>,
+
+        /// Date after which the sigo is pointless and gets deleted
+        #[arg(long, value_parser = utils::parse_date)]
+        until: Option<NaiveDate>,
+
+        /// Estimated time to finish(e.g. 30min, 2h)
+        #[arg(short, long, value_parser = utils::parse_duration)]
+        estimate: Option<chrono::Duration>,
+
+        /// Effort points
+        #[arg(long)]
+        points: Option<u32>,
+    },
+
+    /// Add a copy of the sigo as a new ready sigo
+    Duplicate {
+        /// Ids, ranges like 7-9 or uuids(comma separated)
+        id: TaskRefs,
+
+        /// New description, +tags to add, -tags to remove, project:name and assignee:name
+        #[arg(allow_hyphen_values = true)]
+        words: Vec<String>,
+    },
+
+    /// Show everything about the sigo
+    Info {
+        /// Ids, ranges like 7-9 or uuids
+        #[arg(required = true)]
+        ids: Vec<TaskRefs>,
+    },
+
+    /// Edit the sigo in the editor
+    Edit {
+        /// Ids, ranges like 7-9 or uuids
+        #[arg(required = true)]
+        ids: Vec<TaskRefs>,
+    },
+
+    /// Record work already done as a completed sigo
+    Log {
+        /// Description, +tags and project:name
+        #[arg(required = true)]
+        description: Vec<String>,
+
+        /// Priority(H/M/L)
+        #[arg(value_enum, short, long, default_value_t = Priority::M)]
+        priority: Priority,
+
+        /// Time it took(e.g. 30min, 2h)
+        #[arg(short, long, value_parser = utils::parse_duration)]
+        estimate: Option<chrono::Duration>,
+
+        /// Effort points
+        #[arg(long)]
+        points: Option<u32>,
+    },
+
+    /// Done sigo
+    Done {
+        /// Ids, ranges like 7-9 or uuids, or a filter like +tag or project:name
+        #[arg(required = true)]
+        targets: Vec<String>,
+
+        /// Do not ask for confirmation when completing by filter
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Move completed sigos back to ready
+    Restore {
+        /// Uuids of completed sigos
+        #[arg(required = true)]
+        ids: Vec<TaskRefs>,
+    },
+
+    /// Start working on ready sigo
+    Start {
+        /// Ids, ranges like 7-9 or uuids
+        #[arg(required = true)]
+        ids: Vec<TaskRefs>,
+    },
+
+    /// Stop working on the active sigo
+    Stop,
+
+    /// Delete sigo without completing it, ending its recurrence if it recurs
+    Delete {
+        /// Ids, ranges like 7-9 or uuids
+        #[arg(required = true)]
+        ids: Vec<TaskRefs>,
+
+        /// Keep a recurring sigo recurring, issuing it again for the same date on the next recur
+        #[arg(long)]
+        keep_series: bool,
+    },
+
+    /// Go through waiting and stale ready sigos one by one, deciding what to do with each
+    Review {
+        /// Ready sigos not modified in this many days are stale
+        #[arg(short, long, default_value_t = 7)]
+        days: i64,
+    },
+
+    /// Permanently remove deleted sigos, or old completed sigos with --before or --keep-last
+    #[command(group(ArgGroup::new("completed").args(["before", "keep_last"]).multiple(true)))]
+    Purge {
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Purge completed sigos done before the date
+        #[arg(long, value_parser = utils::parse_date)]
+        before: Option<NaiveDate>,
+
+        /// Purge completed sigos except the N most recently done
+        #[arg(long, value_name = "N")]
+        keep_last: Option<usize>,
+
+        /// Move the purged completed sigos to the archive instead of removing them
+        #[arg(long, requires = "completed")]
+        archive: bool,
+    },
+
+    /// Move sigos completed long ago to yearly archive files
+    Archive {
+        /// Archive sigos completed more than this many days ago
+        #[arg(short, long, default_value_t = 90)]
+        days: u64,
+    },
+
+    /// Change sigo waiting
+    Wait {
+        /// Ids, ranges like 7-9 or uuids
+        #[arg(required = true)]
+        ids: Vec<TaskRefs>,
+
+        /// Description text
+        #[arg(short, long)]
+        text: Option<String>,
+
+        /// Date to return to ready automatically
+        #[arg(short, long, value_parser = utils::parse_date)]
+        until: Option<NaiveDate>,
+
+        /// Why the sigo is waiting, annotated when it comes back
+        #[arg(short, long)]
+        reason: Option<String>,
+    },
+
+    /// Wait until a date given relatively, e.g. 3d or monday
+    Snooze {
+        /// Ids, ranges like 7-9 or uuids(comma separated)
+        id: TaskRefs,
+
+        /// Duration(e.g. 2h, 3d, 1w) or date(e.g. tomorrow, monday, 2024-04-01)
+        #[arg(value_parser = utils::parse_snooze)]
+        until: NaiveDate,
+    },
+
+    /// Change sigo ready
+    Back {
+        /// Ids, ranges like 7-9 or uuids
+        #[arg(required = true)]
+        ids: Vec<TaskRefs>,
+
+        /// Description text
+        #[arg(short, long)]
+        text: Option<String>,
+    },
+
+    /// Annotate existing sigo
+    Annotate {
+        /// Ids, ranges like 7-9 or uuids(comma separated)
+        id: TaskRefs,
+
+        /// Annotation text
+        text: String,
+    },
+
+    /// Add text to the end of the description of a sigo in any state
+    Append {
+        /// Ids, ranges like 7-9 or uuids(comma separated)
+        id: TaskRefs,
+
+        /// Text to add
+        text: String,
+    },
+
+    /// Add text to the beginning of the description of a sigo in any state
+    Prepend {
+        /// Ids, ranges like 7-9 or uuids(comma separated)
+        id: TaskRefs,
+
+        /// Text to add
+        text: String,
+    },
+
+    /// Remove annotations containing the pattern
+    Denotate {
+        /// Ids, ranges like 7-9 or uuids(comma separated)
+        id: TaskRefs,
+
+        /// Text contained in the annotations to remove
+        pattern: String,
+    },
+
+    /// Attach file path or URL to sigo
+    Attach {
+        /// Ids, ranges like 7-9 or uuids(comma separated)
+        id: TaskRefs,
+
+        /// File path or URL
+        attachment: String,
+    },
+
+    /// Open the first attachment of sigo
+    Open {
+        /// Ids, ranges like 7-9 or uuids
+        #[arg(required = true)]
+        ids: Vec<TaskRefs>,
+    },
+
+    /// List ready sigos
+    List {
+        /// Filter like +tag, project:name or an expression with and, or, not and parentheses
+        filter: Vec<String>,
+
+        /// Group by project
+        #[arg(short, long)]
+        group: bool,
+
+        /// Group by the attribute, with sigos without it under (none)
+        #[arg(long, value_enum, conflicts_with = "group")]
+        group_by: Option<GroupBy>,
+
+        /// Show children indented under their parents
+        #[arg(long)]
+        tree: bool,
+
+        /// Include sigos scheduled in the future
+        #[arg(short, long)]
+        all: bool,
+
+        /// Sort by urgency
+        #[arg(short, long)]
+        urgency: bool,
+
+        /// Sort keys with + for ascending or - for descending, e.g. due+,priority-
+        #[arg(short, long)]
+        sort: Option<sort::Sort>,
+
+        /// Columns to show, e.g. id,description,due,tags
+        #[arg(short, long, value_delimiter = ',')]
+        columns: Vec<String>,
+
+        /// Show at most this many sigos, or all of them with 0
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Page to show, counted from 1 in pages of the limit
+        #[arg(long, default_value_t = 1,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+        page: usize,
+    },
+
+    /// Print the number of sigos matching the filter, ready ones unless states are given
+    Count {
+        /// Filter like +tag, project:name or an expression with and, or, not and parentheses
+        filter: Vec<String>,
+
+        /// Count ready sigos
+        #[arg(short, long)]
+        ready: bool,
+
+        /// Count waiting sigos
+        #[arg(short, long)]
+        waiting: bool,
+
+        /// Count completed sigos
+        #[arg(short, long)]
+        completed: bool,
+
+        /// Count deleted sigos
+        #[arg(short, long)]
+        deleted: bool,
+    },
+
+    /// Print ids of the ready sigos matching the filter, e.g. for `xargs`
+    Ids {
+        /// Filter like +tag, project:name or an expression with and, or, not and parentheses
+        filter: Vec<String>,
+
+        /// Include waiting sigos
+        #[arg(short, long)]
+        waiting: bool,
+
+        /// Separate ids by newlines instead of spaces
+        #[arg(short, long)]
+        newline: bool,
+    },
+
+    /// Show the most urgent ready sigo
+    Next {
+        /// Filter like +tag, project:name or an expression with and, or, not and parentheses
+        filter: Vec<String>,
+
+        /// Number of sigos to show
+        #[arg(short, default_value_t = 1)]
+        n: usize,
+
+        /// Only print the id and description, e.g. for a shell prompt
+        #[arg(short, long)]
+        brief: bool,
+
+        /// Columns to show, e.g. id,description,due,tags
+        #[arg(short, long, value_delimiter = ',')]
+        columns: Vec<String>,
+    },
+
+    /// List ready, waiting and completed sigos in one table
+    All {
+        /// Filter like +tag, project:name or an expression with and, or, not and parentheses
+        filter: Vec<String>,
+
+        /// Sort keys with + for ascending or - for descending, e.g. due+,priority-
+        #[arg(short, long)]
+        sort: Option<sort::Sort>,
+
+        /// Columns to show, e.g. id,status,description,due
+        #[arg(short, long, value_delimiter = ',')]
+        columns: Vec<String>,
+
+        /// Include archived sigos
+        #[arg(long)]
+        archived: bool,
+    },
+
+    /// Run a report defined in the config, or list them without a name
+    Report { name: Option<String> },
+
+    /// Save, remove or list filters used as @name
+    Query {
+        #[command(subcommand)]
+        action: Option<QueryAction>,
+    },
+
+    /// Show, set or clear(with `none`) the context filtering lists
+    Context { name: Option<String> },
+
+    /// Generate the next sigos of recurring sigos
+    Recur,
+
+    /// List completed sigos
+    Completed {
+        /// Filter like +tag, project:name or an expression with and, or, not and parentheses
+        filter: Vec<String>,
+
+        /// Only sigos completed within the duration(e.g. 7d, 2w)
+        #[arg(short, long, value_parser = utils::parse_duration)]
+        since: Option<chrono::Duration>,
+
+        /// Only sigos completed on or after the date
+        #[arg(long, value_parser = utils::parse_date)]
+        from: Option<NaiveDate>,
+
+        /// Only sigos completed on or before the date
+        #[arg(long, value_parser = utils::parse_date)]
+        to: Option<NaiveDate>,
+
+        /// Include archived sigos
+        #[arg(long)]
+        archived: bool,
+
+        /// Columns to show, e.g. id,description,due,tags
+        #[arg(short, long, value_delimiter = ',')]
+        columns: Vec<String>,
+    },
+
+    /// Ready sigos not modified for a while, the longest untouched first
+    Stale {
+        /// Sigos not modified in this many days are stale
+        #[arg(short, long, default_value_t = 30)]
+        days: i64,
+    },
+
+    /// Tags in use with the number of open sigos having them
+    Tags,
+
+    /// Projects and sub projects with the number of sigos in each state and how much is done
+    Projects {
+        /// Include archived sigos
+        #[arg(long)]
+        archived: bool,
+    },
+
+    /// Open sigos due within the window or overdue, the earliest first
+    Due {
+        /// Window from today(e.g. 7d, 2w)
+        #[arg(long = "in", default_value = "7d", value_parser = utils::parse_duration)]
+        within: chrono::Duration,
+    },
+
+    /// Open sigos depending on others, with the sigos they wait for
+    Blocked,
+
+    /// Ready sigos other sigos depend on, with the sigos they block
+    Unblocked,
+
+    /// List sigos deleted because their until date passed
+    Expired {
+        /// Only sigos expired within the duration(e.g. 7d, 2w)
+        #[arg(short, long, value_parser = utils::parse_duration)]
+        since: Option<chrono::Duration>,
+    },
+
+    /// Totals per state, completions per week, common tags and projects and long waits
+    Stats {
+        /// Include archived sigos
+        #[arg(long)]
+        archived: bool,
+    },
+
+    /// Month with the number of sigos due or scheduled on each day
+    Calendar {
+        /// Month to show(YYYY-MM), this month by default
+        #[arg(value_parser = utils::parse_month)]
+        month: Option<NaiveDate>,
+
+        /// List the sigos under each day
+        #[arg(short, long)]
+        due: bool,
+    },
+
+    /// Open sigos as trees of their children and dependencies
+    Tree {
+        /// Filter of the sigos at the top like +tag, project:name or an expression
+        filter: Vec<String>,
+    },
+
+    /// Chart of open and completed sigos over time
+    Burndown {
+        /// Show days instead of weeks
+        #[arg(short, long)]
+        daily: bool,
+
+        /// Number of days or weeks to show
+        #[arg(short, long, default_value_t = 8)]
+        periods: u32,
+    },
+
+    /// Ready, waiting and recently completed sigos, oldest age and estimates per project
+    Summary,
+
+    /// Revert the last change, walking further back when repeated
+    Undo {
+        /// Roll back the last purge, delete of several sigos or replay from the snapshot taken
+        /// before it, even once the journal no longer has it
+        #[arg(long)]
+        last_destructive: bool,
+    },
+
+    /// Rebuild the sigos from the operations log, undoably
+    Replay,
+
+    /// Copy the data directory to a new backup, taken also before purge and replay
+    Backup,
+
+    /// Replace the data directory with a backup
+    RestoreBackup {
+        /// Backup to restore, as listed by --list
+        #[arg(required_unless_present = "list")]
+        timestamp: Option<String>,
+
+        /// List the backups, oldest first
+        #[arg(long, conflicts_with = "timestamp")]
+        list: bool,
+
+        /// Restore even if changes journaled since the backup would be lost
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Encrypt the sigos, the journal and the operations log with the key file, generated if it
+    /// does not exist, and from then on
+    Encrypt { key_file: PathBuf },
+
+    /// Write the sigos in plain text again and stop using the key file
+    Decrypt,
+
+    /// List the profiles, marking the one in use
+    Profiles,
+
+    /// Check the data for broken files, duplicate ids and links to missing sigos
+    Doctor {
+        /// Repair what can be safely, after taking a backup
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Copy the sigos to another storage backend and use it from then on
+    Migrate {
+        #[arg(value_enum)]
+        to: storage::Backend,
+    },
+
+    /// Changes to the sigo recorded in the journal
+    History {
+        /// Id or uuid
+        id: TaskRef,
+    },
+
+    /// Search descriptions and annotations of ready, waiting and completed sigos
+    Search {
+        pattern: String,
+
+        /// Ignore case
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Treat the pattern as a regular expression
+        #[arg(short, long)]
+        regex: bool,
+
+        /// Include archived sigos
+        #[arg(long)]
+        archived: bool,
+    },
+
+    /// Ready, active, waiting and today's completed sigos side by side
+    Board {
+        /// Width of the board, the terminal's $COLUMNS or 80 by default
+        #[arg(short, long)]
+        width: Option<usize>,
+    },
+
+    /// One-screen overview of the counts, the most urgent sigos, the active one and the streak
+    Dashboard,
+
+    /// Open sigos per project by how long ago they were added
+    Age,
+
+    /// Sigos completed yesterday, worked on today and blocked, for a daily standup
+    Standup,
+
+    /// Time tracked per sigo and per project for each day
+    Timesheet {
+        /// This week from Monday instead of today
+        #[arg(short, long)]
+        week: bool,
+
+        /// Print the time per sigo as CSV, in minutes
+        #[arg(long)]
+        csv: bool,
+    },
+
+    /// Points completed per week
+    Velocity {
+        /// Number of weeks to show, including this week
+        #[arg(short, long, default_value_t = 4)]
+        weeks: u32,
+
+        /// Include archived sigos
+        #[arg(long)]
+        archived: bool,
+    },
+
+    /// List waiting sigos
+    Waiting {
+        /// Filter like +tag, project:name or an expression with and, or, not and parentheses
+        filter: Vec<String>,
+
+        /// Group by project
+        #[arg(short, long)]
+        group: bool,
+
+        /// Group by the attribute, with sigos without it under (none)
+        #[arg(long, value_enum, conflicts_with = "group")]
+        group_by: Option<GroupBy>,
+
+        /// Columns to show, e.g. id,description,due,tags
+        #[arg(short, long, value_delimiter = ',')]
+        columns: Vec<String>,
+    },
+}
+
+#[derive(
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    ValueEnum,
+    Debug,
+    Default,
+    Display,
+    Serialize,
+    Deserialize,
+)]
+pub enum Priority {
+    H,
+    #[default]
+    M,
+    L,
+}
+
+#[derive(Subcommand, Clone)]
+enum QueryAction {
+    /// Save the filter under the name, replacing the one saved before
+    Save {
+        name: String,
+
+        /// Filter like +tag, project:name or an expression with and, or, not and parentheses
+        #[arg(required = true)]
+        filter: Vec<String>,
+    },
+
+    /// Remove the saved filter
+    Remove { name: String },
+}
+
+/// Attribute lists are split into sections by
+#[derive(Copy, Clone, ValueEnum, Debug)]
+enum GroupBy {
+    Project,
+    Tag,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug, Display, Serialize, Deserialize)]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Recurrence {
+    /// The date after, `None` past the last date there can be
+    fn next(self, date: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::Daily => date.checked_add_days(Days::new(1)),
+            Recurrence::Weekly => date.checked_add_days(Days::new(7)),
+            Recurrence::Monthly => date.checked_add_months(Months::new(1)),
+            Recurrence::Yearly => date.checked_add_months(Months::new(12)),
+        }
+    }
+}
+
+/// Run the command given on the command line, exiting with status 1 if it fails
+pub fn main() {
+    // Parse args and load the config of the profile, if any
+    let cli = AppArg::parse();
+    storage::set_mode(match (cli.read_only, cli.dry_run) {
+        (true, _) => Mode::ReadOnly,
+        (_, true) => Mode::DryRun,
+        _ => Mode::Write,
+    });
+    let mut cfg = if cli.ephemeral {
+        MyConfig {
+            storage: Backend::Memory,
+            ..MyConfig::default()
+        }
+    } else {
+        match config::load(cli.profile.clone()) {
+            Ok(cfg) => cfg,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    };
+    if let Some(dir) = &cli.data_dir {
+        cfg.data = dir.to_string_lossy().into_owned();
+    }
+
+    if cfg.storage != Backend::Memory && cfg.data.is_empty() {
+        eprintln!("Error: {}", error::SigoError::NoDataDir);
+        std::process::exit(1);
+    }
+
+    // if task dir doesnot exist, create dir
+    let sigo_path = PathBuf::from(&cfg.data);
+    if cfg.storage != Backend::Memory && !sigo_path.is_dir() {
+        let _ = fs::create_dir_all(sigo_path);
+    }
+
+    // Run command
+    match command::run(&cfg, cli) {
+        Ok(output) => println!("{}", output),
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    }
+}
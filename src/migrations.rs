@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use chrono::Local;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::error::SigoError;
+
+/// Steps upgrading the tasks file from the version of their index to the next one
+const STEPS: [fn(Value) -> Value; 3] = [add_generation, persist_uuids, add_checksum];
+
+/// Version of the tasks file written by this sigo
+pub const VERSION: u32 = STEPS.len() as u32;
+
+/// Version of the tasks file, from before versions were written for those without one
+pub fn version_of(file: &Value) -> u32 {
+    match file {
+        Value::Array(_) => 0,
+        file => file["version"].as_u64().map_or(1, |v| v as u32),
+    }
+}
+
+/// The tasks file upgraded to the current version one step at a time
+pub fn upgrade(path: &Path, mut file: Value) -> Result<Value, SigoError> {
+    let version = version_of(&file);
+    if version > VERSION {
+        return Err(SigoError::NewerFormat(path.to_path_buf(), version));
+    }
+    for step in &STEPS[version as usize..] {
+        file = step(file);
+    }
+    if let Some(file) = file.as_object_mut() {
+        file.insert("version".to_owned(), Value::from(VERSION));
+    }
+    Ok(file)
+}
+
+/// 0 to 1: the sigos were a bare array, before there was a generation counting the writes
+fn add_generation(file: Value) -> Value {
+    serde_json::json!({ "generation": 0, "tasks": file })
+}
+
+/// 1 to 2: sigos from before they had uuids and creation dates got new ones each time they
+/// were read, so store them
+fn persist_uuids(mut file: Value) -> Value {
+    let now = Value::from(Local::now().to_rfc3339());
+    let tasks = file["tasks"].as_array_mut().into_iter().flatten();
+    for task in tasks.filter(|t| t["status"] != "recurring") {
+        if let Some(task) = task.as_object_mut() {
+            task.entry("uuid")
+                .or_insert_with(|| Value::from(Uuid::new_v4().to_string()));
+            task.entry("created_at").or_insert_with(|| now.clone());
+        }
+    }
+    file
+}
+
+/// 2 to 3: the tasks file is closed by a checksum, added when it is next written, so that an
+/// older sigo does not write it without one
+fn add_checksum(file: Value) -> Value {
+    file
+}
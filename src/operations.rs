@@ -0,0 +1,294 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    config::MyConfig,
+    crypt::Crypt,
+    error::SigoError,
+    storage::{self, Durability, Storage},
+};
+
+pub const FILE_NAME: &str = "operations";
+
+/// Change to a single sigo, one JSON line of the operations log
+#[derive(Serialize, Deserialize)]
+pub struct Operation {
+    pub at: DateTime<Local>,
+    pub command: String,
+    /// `imported`, `added`, `modified`, `moved` or `removed`
+    pub event: String,
+    /// The uuid of the sigo, or the collection and id for those without one
+    pub key: String,
+    /// Collection the sigo is in afterwards, none once removed
+    pub collection: Option<String>,
+    pub task: Option<Value>,
+}
+
+fn log_path(cfg: &MyConfig) -> PathBuf {
+    PathBuf::from(&cfg.data).join(FILE_NAME)
+}
+
+/// Key of a sigo, with the collection it is in and its attributes
+type Keyed = (String, (String, Value));
+
+fn key_of(collection: &str, task: &Value) -> String {
+    match task["uuid"].as_str() {
+        Some(uuid) => uuid.to_owned(),
+        None => format!("{}#{}", collection, task["id"]),
+    }
+}
+
+/// Storage which appends every change made through it to the operations log
+pub struct Logged {
+    pub inner: Box<dyn Storage>,
+    pub log: PathBuf,
+    /// Encryption of each line
+    pub crypt: Crypt,
+    pub durability: Durability,
+}
+
+impl Logged {
+    pub fn new(cfg: &MyConfig, inner: Box<dyn Storage>) -> Self {
+        Self {
+            inner,
+            log: log_path(cfg),
+            crypt: Crypt::new(cfg),
+            durability: cfg.durability,
+        }
+    }
+
+    /// Keys of the sigos of the collections in their order, with the collection they are in
+    fn keyed(&self, collections: &[(&str, String)]) -> Result<Vec<Keyed>, SigoError> {
+        let mut keyed = Vec::new();
+        for (name, content) in collections {
+            let tasks = serde_json::from_str::<Vec<Value>>(content)
+                .map_err(|e| self.inner.parse_error(name, e))?;
+            for task in tasks {
+                keyed.push((key_of(name, &task), (name.to_string(), task)));
+            }
+        }
+        Ok(keyed)
+    }
+
+    fn append(&self, operations: &[Operation]) -> Result<(), SigoError> {
+        if operations.is_empty() {
+            return Ok(());
+        }
+        let mut lines = String::new();
+        for operation in operations {
+            lines.push_str(&self.crypt.seal(serde_json::to_string(operation)?)?);
+            lines.push('\n');
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log)
+            .map_err(|e| SigoError::FileWriteErr(self.log.clone(), e))?;
+        file.write_all(lines.as_bytes())
+            .and_then(|_| match self.durability {
+                Durability::Safe => file.sync_data(),
+                Durability::Fast => Ok(()),
+            })
+            .map_err(|e| SigoError::FileWriteErr(self.log.clone(), e))
+    }
+
+    /// Every stored sigo as imported, so replaying a log started after them still has them
+    fn import_all(&self, command: &str) -> Result<(), SigoError> {
+        let mut collections = Vec::new();
+        for name in self.inner.names()? {
+            let content = self.inner.read(&name)?;
+            collections.push((name, content));
+        }
+        let collections = collections
+            .iter()
+            .map(|(name, content)| (name.as_str(), content.clone()))
+            .collect::<Vec<(&str, String)>>();
+        let now = Local::now();
+        let operations = self
+            .keyed(&collections)?
+            .into_iter()
+            .map(|(key, (collection, task))| Operation {
+                at: now,
+                command: command.to_owned(),
+                event: "imported".to_owned(),
+                key,
+                collection: Some(collection),
+                task: Some(task),
+            })
+            .collect::<Vec<Operation>>();
+        self.append(&operations)
+    }
+}
+
+impl Storage for Logged {
+    fn location(&self, name: &str) -> PathBuf {
+        self.inner.location(name)
+    }
+
+    fn parse_error(&self, name: &str, err: serde_json::Error) -> SigoError {
+        self.inner.parse_error(name, err)
+    }
+
+    fn read(&self, name: &str) -> Result<String, SigoError> {
+        self.inner.read(name)
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<(), SigoError> {
+        self.write_all(&[(name, content.to_owned())])
+    }
+
+    fn write_all(&self, collections: &[(&str, String)]) -> Result<(), SigoError> {
+        let command = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+        if !self.log.is_file() {
+            self.import_all(&command)?;
+        }
+        let mut before = Vec::new();
+        for (name, _) in collections {
+            before.push((*name, self.inner.read(name)?));
+        }
+        let before = self
+            .keyed(&before)?
+            .into_iter()
+            .collect::<BTreeMap<String, (String, Value)>>();
+        let after = self.keyed(collections)?;
+        let after_keys = after
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect::<HashSet<&str>>();
+        self.inner.write_all(collections)?;
+
+        let now = Local::now();
+        let mut operations = Vec::new();
+        for (key, (collection, task)) in &after {
+            let event = match before.get(key) {
+                None => "added",
+                Some((was_in, _)) if was_in != collection => "moved",
+                Some((_, was)) if was != task => "modified",
+                Some(_) => continue,
+            };
+            operations.push(Operation {
+                at: now,
+                command: command.clone(),
+                event: event.to_owned(),
+                key: key.clone(),
+                collection: Some(collection.clone()),
+                task: Some(task.clone()),
+            });
+        }
+        for key in before
+            .keys()
+            .filter(|key| !after_keys.contains(key.as_str()))
+        {
+            operations.push(Operation {
+                at: now,
+                command: command.clone(),
+                event: "removed".to_owned(),
+                key: key.clone(),
+                collection: None,
+                task: None,
+            });
+        }
+        self.append(&operations)
+    }
+
+    fn names(&self) -> Result<Vec<String>, SigoError> {
+        self.inner.names()
+    }
+
+    fn find_by_id(&self, name: &str, id: u32) -> Result<Option<Value>, SigoError> {
+        self.inner.find_by_id(name, id)
+    }
+
+    fn count(&self, name: &str) -> Result<usize, SigoError> {
+        self.inner.count(name)
+    }
+
+    fn ids(&self, names: &[&str]) -> Result<HashSet<u32>, SigoError> {
+        self.inner.ids(names)
+    }
+
+    fn for_each(
+        &self,
+        name: &str,
+        f: &mut dyn FnMut(Value) -> Result<(), SigoError>,
+    ) -> Result<(), SigoError> {
+        self.inner.for_each(name, f)
+    }
+}
+
+/// Lines of the log, decrypted
+fn read_lines(cfg: &MyConfig, crypt: &Crypt) -> Result<Vec<String>, SigoError> {
+    let path = log_path(cfg);
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| SigoError::FileReadErr(path.clone(), e))?;
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| crypt.open(&path, line.to_owned()))
+        .collect()
+}
+
+pub fn read_operations(cfg: &MyConfig) -> Result<Vec<Operation>, SigoError> {
+    let path = log_path(cfg);
+    read_lines(cfg, &Crypt::new(cfg))?
+        .iter()
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| SigoError::ParseStrToTasksErr(path.clone(), e))
+        })
+        .collect()
+}
+
+/// Write the log again, read and written with the keys given
+pub fn rekey(cfg: &MyConfig, crypt: &Crypt) -> Result<(), SigoError> {
+    let path = log_path(cfg);
+    if !path.is_file() {
+        return Ok(());
+    }
+    let mut content = String::new();
+    for line in read_lines(cfg, crypt)? {
+        content.push_str(&crypt.seal(line)?);
+        content.push('\n');
+    }
+    storage::write_atomically(&path, content.as_bytes(), cfg.durability)
+}
+
+/// Collections rebuilt by applying the operations in order, keeping the order sigos were added in
+pub fn replay(operations: &[Operation]) -> BTreeMap<String, Vec<Value>> {
+    let mut collections: BTreeMap<String, Vec<(String, Value)>> = BTreeMap::new();
+    for operation in operations {
+        let replaced = match (&operation.collection, &operation.task) {
+            (Some(collection), Some(task)) => collections
+                .get_mut(collection)
+                .and_then(|tasks| tasks.iter_mut().find(|(key, _)| *key == operation.key))
+                .map(|(_, stored)| *stored = task.clone())
+                .is_some(),
+            _ => false,
+        };
+        if replaced {
+            continue;
+        }
+        for tasks in collections.values_mut() {
+            tasks.retain(|(key, _)| *key != operation.key);
+        }
+        if let (Some(collection), Some(task)) = (&operation.collection, &operation.task) {
+            collections
+                .entry(collection.clone())
+                .or_default()
+                .push((operation.key.clone(), task.clone()));
+        }
+    }
+    collections
+        .into_iter()
+        .map(|(name, tasks)| (name, tasks.into_iter().map(|(_, task)| task).collect()))
+        .collect()
+}
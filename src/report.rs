@@ -0,0 +1,976 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Datelike, Days, Duration, Local, NaiveDate};
+use regex::Regex;
+use tabled::Tabled;
+
+use crate::{
+    chart::Node,
+    filter::Filter,
+    task::{Annotation, CompletedTask, DeletedTask, Interval, ReadyTask, Task, WaitingTask},
+    utils,
+};
+
+/// Workload and progress of a project
+#[derive(Tabled)]
+pub struct ProjectSummary {
+    pub project: String,
+    pub ready: usize,
+    pub waiting: usize,
+    /// Completed in the last 7 days
+    #[tabled(rename = "done 7d")]
+    pub done_last_week: usize,
+    /// Age of the oldest ready or waiting sigo
+    #[tabled(display_with = "utils::display_option_age")]
+    pub oldest: Option<DateTime<Local>>,
+    /// Ready sigos without estimate
+    pub unestimated: usize,
+    /// Total estimate of ready sigos
+    #[tabled(display_with = "utils::display_minutes")]
+    pub estimate: i64,
+}
+
+impl ProjectSummary {
+    pub fn from_tasks(
+        ready: Vec<ReadyTask>,
+        waiting: Vec<WaitingTask>,
+        completed: Vec<CompletedTask>,
+    ) -> Vec<Self> {
+        let week_ago = Local::now() - Duration::days(7);
+        let completed = completed
+            .into_iter()
+            .filter(|t| t.completed_at.is_some_and(|c| c >= week_ago))
+            .collect();
+        let mut ready = utils::group_by_project(ready, |t| &t.project);
+        let mut waiting = utils::group_by_project(waiting, |t| &t.project);
+        let mut completed = utils::group_by_project(completed, |t| &t.project);
+        let mut projects = ready
+            .keys()
+            .chain(waiting.keys())
+            .chain(completed.keys())
+            .cloned()
+            .collect::<Vec<String>>();
+        projects.sort();
+        projects.dedup();
+        projects
+            .into_iter()
+            .map(|project| {
+                let ready = ready.remove(&project).unwrap_or_default();
+                let waiting = waiting.remove(&project).unwrap_or_default();
+                let done_last_week = completed.remove(&project).unwrap_or_default().len();
+                let oldest = ready
+                    .iter()
+                    .map(|t| t.created_at)
+                    .chain(waiting.iter().map(|t| t.created_at))
+                    .min();
+                Self {
+                    project,
+                    ready: ready.len(),
+                    waiting: waiting.len(),
+                    done_last_week,
+                    oldest,
+                    unestimated: ready.iter().filter(|t| t.estimate.is_none()).count(),
+                    estimate: ready.iter().filter_map(|t| t.estimate).sum(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Sigos and points completed in a week
+#[derive(Tabled)]
+pub struct WeekVelocity {
+    /// Monday of the week
+    pub week: NaiveDate,
+    pub sigos: usize,
+    pub points: u32,
+}
+
+impl WeekVelocity {
+    /// Velocity of the last `weeks` weeks, oldest first
+    pub fn from_completed(tasks: &[CompletedTask], weeks: u32) -> Vec<Self> {
+        let today = Local::now().date_naive();
+        let this_week = today - Days::new(today.weekday().num_days_from_monday().into());
+        (0..weeks)
+            .rev()
+            .map(|n| {
+                let week = this_week - Days::new(7 * u64::from(n));
+                let completed = tasks
+                    .iter()
+                    .filter(|t| {
+                        t.completed_at.is_some_and(|c| {
+                            let date = c.date_naive();
+                            week <= date && date < week + Days::new(7)
+                        })
+                    })
+                    .collect::<Vec<&CompletedTask>>();
+                Self {
+                    week,
+                    sigos: completed.len(),
+                    points: completed.iter().filter_map(|t| t.points).sum(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Open and completed sigos at the end of a day or week
+pub struct BurndownPoint {
+    /// First day of the period
+    pub period: NaiveDate,
+    pub open: usize,
+    /// Completed so far
+    pub completed: usize,
+}
+
+impl BurndownPoint {
+    /// Points of the last `periods` days, or weeks starting on Monday, oldest first
+    pub fn from_tasks(
+        ready: &[ReadyTask],
+        waiting: &[WaitingTask],
+        completed: &[CompletedTask],
+        deleted: &[DeletedTask],
+        daily: bool,
+        periods: u32,
+    ) -> Vec<Self> {
+        let today = Local::now().date_naive();
+        let (this_period, days) = if daily {
+            (today, 1)
+        } else {
+            let monday = today - Days::new(today.weekday().num_days_from_monday().into());
+            (monday, 7)
+        };
+        // Days each sigo was created and closed, if it was
+        let spans = ready
+            .iter()
+            .map(|t| (t.created_at.date_naive(), None))
+            .chain(waiting.iter().map(|t| (t.created_at.date_naive(), None)))
+            .chain(
+                deleted
+                    .iter()
+                    .map(|t| (t.created_at.date_naive(), Some(t.modified_at.date_naive()))),
+            )
+            .collect::<Vec<(NaiveDate, Option<NaiveDate>)>>();
+        let done = completed
+            .iter()
+            .map(|t| (t.created_at.date_naive(), t.done_at().date_naive()))
+            .collect::<Vec<(NaiveDate, NaiveDate)>>();
+        (0..periods)
+            .rev()
+            .map(|n| {
+                let period = this_period - Days::new(days * u64::from(n));
+                let end = (period + Days::new(days - 1)).min(today);
+                let open_at = |created: NaiveDate, closed: Option<NaiveDate>| {
+                    created <= end && closed.is_none_or(|c| c > end)
+                };
+                Self {
+                    period,
+                    open: spans.iter().filter(|(c, d)| open_at(*c, *d)).count()
+                        + done.iter().filter(|(c, d)| open_at(*c, Some(*d))).count(),
+                    completed: done.iter().filter(|(_, d)| *d <= end).count(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Description or annotation of a sigo matching `sigo search`
+#[derive(Tabled)]
+pub struct SearchHit {
+    pub state: &'static str,
+    /// Id, or the head of the uuid for completed sigos
+    pub id: String,
+    pub text: String,
+}
+
+impl SearchHit {
+    fn find(
+        state: &'static str,
+        id: String,
+        description: String,
+        annotations: &[Annotation],
+        pattern: &Regex,
+    ) -> Vec<Self> {
+        std::iter::once(description)
+            .chain(annotations.iter().map(|a| a.to_string()))
+            .filter(|text| pattern.is_match(text))
+            .map(|text| Self {
+                state,
+                id: id.clone(),
+                text,
+            })
+            .collect()
+    }
+
+    pub fn search(
+        ready: &[ReadyTask],
+        waiting: &[WaitingTask],
+        completed: &[CompletedTask],
+        pattern: &Regex,
+    ) -> Vec<Self> {
+        let ready = ready.iter().flat_map(|t| {
+            let description = t.get_main_description();
+            Self::find(
+                "ready",
+                t.id.to_string(),
+                description,
+                &t.annotations,
+                pattern,
+            )
+        });
+        let waiting = waiting.iter().flat_map(|t| {
+            let description = t.get_main_description();
+            Self::find(
+                "waiting",
+                t.id.to_string(),
+                description,
+                &t.annotations,
+                pattern,
+            )
+        });
+        let completed = completed.iter().flat_map(|t| {
+            let id = t.uuid.to_string()[..8].to_owned();
+            Self::find(
+                "completed",
+                id,
+                t.description.clone(),
+                &t.annotations,
+                pattern,
+            )
+        });
+        ready.chain(waiting).chain(completed).collect()
+    }
+}
+
+/// Attribute of a sigo shown by `sigo info`
+#[derive(Tabled)]
+pub struct InfoRow {
+    pub name: String,
+    pub value: String,
+}
+
+impl InfoRow {
+    /// Non-empty attributes of the sigo, with the ready and waiting sigos linked to it
+    pub fn from_task(task: &Task, ready: &[ReadyTask], waiting: &[WaitingTask]) -> Vec<Self> {
+        let mut rows = match task {
+            Task::Ready(task) => {
+                let mut rows = vec![("State".to_owned(), "ready".to_owned())];
+                rows.extend(task.info_rows());
+                rows.extend(Self::links(task.id, ready, waiting));
+                rows
+            }
+            Task::Waiting(task) => {
+                let mut rows = vec![("State".to_owned(), "waiting".to_owned())];
+                rows.extend(task.info_rows());
+                rows.push((
+                    "Waiting until".to_owned(),
+                    utils::display_option_date(&task.wait_until),
+                ));
+                rows.push((
+                    "Reason".to_owned(),
+                    utils::display_option_string(&task.reason),
+                ));
+                rows.push((
+                    "Waiting since".to_owned(),
+                    utils::display_option_datetime(&task.waiting_since),
+                ));
+                rows.extend(Self::links(task.id, ready, waiting));
+                rows
+            }
+            Task::Completed(task) => {
+                let mut rows = vec![("State".to_owned(), "completed".to_owned())];
+                rows.extend(task.info_rows());
+                rows
+            }
+            Task::Deleted(task) => {
+                let state = if task.expired { "expired" } else { "deleted" };
+                let mut rows = vec![("State".to_owned(), state.to_owned())];
+                rows.extend(task.info_rows());
+                rows
+            }
+        };
+        rows.retain(|(_, value)| !value.is_empty());
+        rows.into_iter()
+            .map(|(name, value)| Self { name, value })
+            .collect()
+    }
+
+    /// Sigos blocked by or children of the sigo `id`
+    fn links(id: u32, ready: &[ReadyTask], waiting: &[WaitingTask]) -> Vec<(String, String)> {
+        let blocking = ready
+            .iter()
+            .filter(|t| t.depends.contains(&id))
+            .map(|t| t.id)
+            .chain(
+                waiting
+                    .iter()
+                    .filter(|t| t.depends.contains(&id))
+                    .map(|t| t.id),
+            )
+            .collect::<Vec<u32>>();
+        let children = ready
+            .iter()
+            .filter(|t| t.parent_id == Some(id))
+            .map(|t| t.id)
+            .chain(
+                waiting
+                    .iter()
+                    .filter(|t| t.parent_id == Some(id))
+                    .map(|t| t.id),
+            )
+            .collect::<Vec<u32>>();
+        vec![
+            ("Blocking".to_owned(), utils::display_ids(&blocking)),
+            ("Children".to_owned(), utils::display_ids(&children)),
+        ]
+    }
+}
+
+/// Name shared by sigos, like a tag or a project, and how many sigos have it
+#[derive(Tabled)]
+pub struct Tally {
+    pub name: String,
+    pub sigos: usize,
+}
+
+impl Tally {
+    /// The `top` most common names, most common first
+    pub fn top<'a, I>(names: I, top: usize) -> Vec<Self>
+    where
+        I: IntoIterator<Item = &'a String>,
+    {
+        let mut counts: HashMap<&String, usize> = HashMap::new();
+        for name in names {
+            *counts.entry(name).or_default() += 1;
+        }
+        let mut tallies = counts
+            .into_iter()
+            .map(|(name, sigos)| Self {
+                name: name.to_owned(),
+                sigos,
+            })
+            .collect::<Vec<Self>>();
+        tallies.sort_by(|a, b| b.sigos.cmp(&a.sigos).then_with(|| a.name.cmp(&b.name)));
+        tallies.truncate(top);
+        tallies
+    }
+}
+
+/// Waiting sigo and how long it has been waiting
+#[derive(Tabled)]
+pub struct WaitingStay {
+    pub id: u32,
+    pub description: String,
+    /// When it started waiting
+    #[tabled(rename = "waiting", display_with = "utils::display_age")]
+    pub since: DateTime<Local>,
+}
+
+impl WaitingStay {
+    /// The `top` sigos waiting the longest, longest first
+    pub fn longest(tasks: &[WaitingTask], top: usize) -> Vec<Self> {
+        let mut stays = tasks
+            .iter()
+            .map(|t| Self {
+                id: t.id,
+                description: t.get_main_description(),
+                since: t.waiting_since(),
+            })
+            .collect::<Vec<Self>>();
+        stays.sort_by_key(|s| s.since);
+        stays.truncate(top);
+        stays
+    }
+}
+
+/// Totals per state and average ages
+pub fn stats_rows(
+    ready: &[ReadyTask],
+    waiting: &[WaitingTask],
+    completed: &[CompletedTask],
+    deleted: &[DeletedTask],
+) -> Vec<InfoRow> {
+    let average = |durations: Vec<Duration>| match durations.len() {
+        0 => "".to_owned(),
+        n => utils::format_duration(durations.iter().sum::<Duration>() / n as i32),
+    };
+    let now = Local::now();
+    let open_ages = ready
+        .iter()
+        .map(|t| now - t.created_at)
+        .chain(waiting.iter().map(|t| now - t.created_at))
+        .collect();
+    let lead_times = completed
+        .iter()
+        .filter_map(|t| t.completed_at.map(|c| c - t.created_at))
+        .collect();
+    [
+        ("Ready", ready.len().to_string()),
+        ("Waiting", waiting.len().to_string()),
+        ("Completed", completed.len().to_string()),
+        ("Deleted", deleted.len().to_string()),
+        ("Average age of open sigos", average(open_ages)),
+        ("Average time to complete", average(lead_times)),
+    ]
+    .into_iter()
+    .map(|(name, value)| InfoRow {
+        name: name.to_owned(),
+        value,
+    })
+    .collect()
+}
+
+/// Open sigo with what it is placed under in a dependency tree
+struct TreeTask {
+    task: Task,
+    parent_id: Option<u32>,
+    depends: Vec<u32>,
+}
+
+/// Open sigos under their parents and the sigos depending on them, from the sigos nothing else
+/// needs which match the filter. Ready sigos nothing blocks are the ones to do first.
+pub fn dependency_tree(
+    ready: Vec<ReadyTask>,
+    waiting: Vec<WaitingTask>,
+    filter: &Filter,
+) -> Vec<Node> {
+    let tasks = ready
+        .into_iter()
+        .map(|t| {
+            (
+                t.id,
+                TreeTask {
+                    parent_id: t.parent_id,
+                    depends: t.depends.clone(),
+                    task: Task::Ready(t),
+                },
+            )
+        })
+        .chain(waiting.into_iter().map(|t| {
+            (
+                t.id,
+                TreeTask {
+                    parent_id: t.parent_id,
+                    depends: t.depends.clone(),
+                    task: Task::Waiting(t),
+                },
+            )
+        }))
+        .collect::<BTreeMap<u32, TreeTask>>();
+    let mut children = BTreeMap::<u32, Vec<u32>>::new();
+    for (id, task) in &tasks {
+        if let Some(parent_id) = task.parent_id.filter(|p| tasks.contains_key(p)) {
+            children.entry(parent_id).or_default().push(*id);
+        }
+    }
+    let needed = tasks
+        .values()
+        .flat_map(|t| t.depends.iter().copied())
+        .collect::<HashSet<u32>>();
+    tasks
+        .iter()
+        .filter(|(id, t)| {
+            !needed.contains(id)
+                && t.parent_id.is_none_or(|p| !tasks.contains_key(&p))
+                && filter.matches(&t.task)
+        })
+        .map(|(id, _)| tree_node(*id, "", &tasks, &children, &mut Vec::new()))
+        .collect()
+}
+
+fn tree_node(
+    id: u32,
+    relation: &str,
+    tasks: &BTreeMap<u32, TreeTask>,
+    children: &BTreeMap<u32, Vec<u32>>,
+    path: &mut Vec<u32>,
+) -> Node {
+    let task = &tasks[&id];
+    let own_children = children.get(&id).cloned().unwrap_or_default();
+    let marker = match task.task {
+        Task::Waiting(_) => "[W]",
+        _ if !task.depends.is_empty() || !own_children.is_empty() => "[-]",
+        _ => "[ ]",
+    };
+    let mut label = format!("{} {} {}{}", marker, id, task.task.description(), relation);
+    if path.contains(&id) {
+        label.push_str(" (cycle)");
+        return Node {
+            label,
+            children: Vec::new(),
+        };
+    }
+    path.push(id);
+    let mut nodes = Vec::new();
+    for child in &own_children {
+        nodes.push(tree_node(*child, "", tasks, children, path));
+    }
+    for dep in task.depends.iter().filter(|dep| tasks.contains_key(dep)) {
+        nodes.push(tree_node(*dep, " (dependency)", tasks, children, path));
+    }
+    path.pop();
+    Node {
+        label,
+        children: nodes,
+    }
+}
+
+/// Open sigo waiting for others to be done
+#[derive(Tabled)]
+pub struct BlockedTask {
+    pub id: u32,
+    pub description: String,
+    #[tabled(rename = "blocked by")]
+    pub blocked_by: String,
+}
+
+/// Ready sigo other open sigos wait for
+#[derive(Tabled)]
+pub struct BlockingTask {
+    pub id: u32,
+    pub description: String,
+    pub blocks: String,
+}
+
+/// Ids and descriptions of the open sigos, like `3 fix bug, 5 write docs`
+fn describe_ids(ids: &[u32], descriptions: &BTreeMap<u32, String>) -> String {
+    ids.iter()
+        .map(|id| match descriptions.get(id) {
+            Some(description) => format!("{} {}", id, description),
+            None => id.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn open_dependencies(ready: &[ReadyTask], waiting: &[WaitingTask]) -> Vec<(u32, String, Vec<u32>)> {
+    ready
+        .iter()
+        .map(|t| (t.id, t.get_main_description(), t.depends.clone()))
+        .chain(
+            waiting
+                .iter()
+                .map(|t| (t.id, t.get_main_description(), t.depends.clone())),
+        )
+        .collect()
+}
+
+impl BlockedTask {
+    /// Open sigos depending on others, by id
+    pub fn from_tasks(ready: &[ReadyTask], waiting: &[WaitingTask]) -> Vec<Self> {
+        let tasks = open_dependencies(ready, waiting);
+        let descriptions = tasks
+            .iter()
+            .map(|(id, description, _)| (*id, description.clone()))
+            .collect::<BTreeMap<u32, String>>();
+        let mut rows = tasks
+            .iter()
+            .filter(|(_, _, depends)| !depends.is_empty())
+            .map(|(id, description, depends)| Self {
+                id: *id,
+                description: description.clone(),
+                blocked_by: describe_ids(depends, &descriptions),
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| row.id);
+        rows
+    }
+}
+
+impl BlockingTask {
+    /// Ready sigos other open sigos depend on, those blocking the most first
+    pub fn from_tasks(ready: &[ReadyTask], waiting: &[WaitingTask]) -> Vec<Self> {
+        let tasks = open_dependencies(ready, waiting);
+        let descriptions = tasks
+            .iter()
+            .map(|(id, description, _)| (*id, description.clone()))
+            .collect::<BTreeMap<u32, String>>();
+        let mut rows = ready
+            .iter()
+            .map(|t| {
+                let blocks = tasks
+                    .iter()
+                    .filter(|(_, _, depends)| depends.contains(&t.id))
+                    .map(|(id, _, _)| *id)
+                    .collect::<Vec<u32>>();
+                (t, blocks)
+            })
+            .filter(|(_, blocks)| !blocks.is_empty())
+            .map(|(t, blocks)| {
+                (
+                    blocks.len(),
+                    Self {
+                        id: t.id,
+                        description: t.get_main_description(),
+                        blocks: describe_ids(&blocks, &descriptions),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|(count, row)| (std::cmp::Reverse(*count), row.id));
+        rows.into_iter().map(|(_, row)| row).collect()
+    }
+}
+
+/// Tag and how many sigos have it, with those no open sigo has anymore left at 0 open
+#[derive(Tabled)]
+pub struct TagCount {
+    pub tag: String,
+    /// Ready and waiting sigos having the tag
+    pub open: usize,
+}
+
+impl TagCount {
+    /// Tags of sigos in any state by name, those left only on closed sigos counting none
+    pub fn from_tasks(tasks: &[Task]) -> Vec<Self> {
+        let mut counts: BTreeMap<&String, usize> = BTreeMap::new();
+        for task in tasks {
+            let open = matches!(task, Task::Ready(_) | Task::Waiting(_));
+            for tag in task.tags() {
+                *counts.entry(tag).or_default() += usize::from(open);
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(tag, open)| Self {
+                tag: tag.to_owned(),
+                open,
+            })
+            .collect()
+    }
+}
+
+/// Project with the sigos in it and its sub projects
+#[derive(Tabled)]
+pub struct ProjectProgress {
+    /// Last part of the name, indented under the parent project
+    pub project: String,
+    pub ready: usize,
+    pub waiting: usize,
+    pub completed: usize,
+    #[tabled(rename = "done")]
+    pub percent: String,
+}
+
+impl ProjectProgress {
+    /// Projects with their parents before them, and sigos without a project last
+    pub fn from_tasks(
+        ready: &[ReadyTask],
+        waiting: &[WaitingTask],
+        completed: &[CompletedTask],
+    ) -> Vec<Self> {
+        // Counts of ready, waiting and completed sigos by the parts of the project name
+        let mut counts: BTreeMap<Vec<&str>, [usize; 3]> = BTreeMap::new();
+        let mut none = [0; 3];
+        let projects = ready
+            .iter()
+            .map(|t| (&t.project, 0))
+            .chain(waiting.iter().map(|t| (&t.project, 1)))
+            .chain(completed.iter().map(|t| (&t.project, 2)));
+        for (project, state) in projects {
+            let Some(project) = project else {
+                none[state] += 1;
+                continue;
+            };
+            let parts = project.split('.').collect::<Vec<&str>>();
+            for depth in 1..=parts.len() {
+                counts.entry(parts[..depth].to_vec()).or_default()[state] += 1;
+            }
+        }
+        let mut rows = counts
+            .into_iter()
+            .map(|(parts, counts)| {
+                let name = format!("{}{}", "  ".repeat(parts.len() - 1), parts[parts.len() - 1]);
+                Self::new(name, counts)
+            })
+            .collect::<Vec<Self>>();
+        if none.iter().any(|count| *count > 0) {
+            rows.push(Self::new("(none)".to_owned(), none));
+        }
+        rows
+    }
+
+    fn new(project: String, [ready, waiting, completed]: [usize; 3]) -> Self {
+        let total = ready + waiting + completed;
+        Self {
+            project,
+            ready,
+            waiting,
+            completed,
+            percent: format!("{}%", (completed * 100).checked_div(total).unwrap_or(0)),
+        }
+    }
+}
+
+/// Time tracked on a sigo in a day
+#[derive(Tabled)]
+pub struct TimesheetRow {
+    pub date: NaiveDate,
+    /// Id, or the head of the uuid for closed sigos
+    pub id: String,
+    pub description: String,
+    pub project: String,
+    #[tabled(display_with = "utils::display_minutes")]
+    pub time: i64,
+}
+
+/// Time tracked on a project in a day
+#[derive(Tabled)]
+pub struct ProjectTime {
+    pub date: NaiveDate,
+    pub project: String,
+    #[tabled(display_with = "utils::display_minutes")]
+    pub time: i64,
+}
+
+/// Seconds tracked in each day of the interval, split at midnight
+fn seconds_per_day(interval: &Interval) -> Vec<(NaiveDate, i64)> {
+    let end = interval.end.unwrap_or_else(Local::now);
+    let mut start = interval.start;
+    let mut days = Vec::new();
+    while start < end {
+        let midnight = start
+            .date_naive()
+            .succ_opt()
+            .and_then(|day| day.and_hms_opt(0, 0, 0))
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+            .map_or(end, |midnight| midnight.min(end));
+        days.push((start.date_naive(), (midnight - start).num_seconds()));
+        start = midnight;
+    }
+    days
+}
+
+impl TimesheetRow {
+    /// Time tracked from the first day to the last one, by day and then by project and id
+    pub fn from_tasks(tasks: &[Task], from: NaiveDate, to: NaiveDate) -> Vec<Self> {
+        let mut rows = tasks
+            .iter()
+            .flat_map(|task| {
+                let mut seconds: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+                for (date, secs) in task.intervals().iter().flat_map(seconds_per_day) {
+                    if from <= date && date <= to {
+                        *seconds.entry(date).or_default() += secs;
+                    }
+                }
+                seconds.into_iter().map(move |(date, secs)| Self {
+                    date,
+                    id: task.id().map_or_else(
+                        || task.uuid().to_string()[..8].to_owned(),
+                        |id| id.to_string(),
+                    ),
+                    description: task.description(),
+                    project: task.project().clone().unwrap_or_default(),
+                    time: secs / 60,
+                })
+            })
+            .collect::<Vec<Self>>();
+        rows.sort_by(|a, b| (a.date, &a.project, &a.id).cmp(&(b.date, &b.project, &b.id)));
+        rows
+    }
+
+    /// Rows as comma separated values with a header, time in minutes
+    pub fn to_csv(rows: &[Self]) -> String {
+        let quote = |field: &str| {
+            if field.contains([',', '"', '\n']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_owned()
+            }
+        };
+        let mut lines = vec!["date,id,description,project,minutes".to_owned()];
+        lines.extend(rows.iter().map(|row| {
+            format!(
+                "{},{},{},{},{}",
+                row.date,
+                quote(&row.id),
+                quote(&row.description),
+                quote(&row.project),
+                row.time
+            )
+        }));
+        lines.join("\n")
+    }
+}
+
+impl ProjectTime {
+    /// Totals of the rows per day and project
+    pub fn from_rows(rows: &[TimesheetRow]) -> Vec<Self> {
+        let mut times: BTreeMap<(NaiveDate, &String), i64> = BTreeMap::new();
+        for row in rows {
+            *times.entry((row.date, &row.project)).or_default() += row.time;
+        }
+        times
+            .into_iter()
+            .map(|((date, project), time)| Self {
+                date,
+                project: match project.as_str() {
+                    "" => "(none)".to_owned(),
+                    project => project.to_owned(),
+                },
+                time,
+            })
+            .collect()
+    }
+}
+
+/// Sigos completed since the time, by project with their number and tracked time, to paste into
+/// a status email
+pub fn accomplishments(completed: &[CompletedTask], since: DateTime<Local>) -> String {
+    let done = completed
+        .iter()
+        .filter(|t| t.completed_at.is_some_and(|c| c >= since))
+        .cloned()
+        .collect::<Vec<CompletedTask>>();
+    let minutes = |tasks: &[CompletedTask]| -> i64 {
+        tasks
+            .iter()
+            .flat_map(|t| &t.intervals)
+            .map(Interval::minutes)
+            .sum()
+    };
+    let plural = |count: usize| if count == 1 { "sigo" } else { "sigos" };
+    let mut lines = vec![format!(
+        "Completed since {}: {} {}, {} tracked",
+        since.date_naive(),
+        done.len(),
+        plural(done.len()),
+        utils::display_minutes(&minutes(&done))
+    )];
+    let mut groups = utils::group_by_project(done, |t| &t.project)
+        .into_iter()
+        .collect::<Vec<_>>();
+    // Sigos without a project last
+    groups.sort_by_key(|(project, _)| (project == "(none)", project.clone()));
+    for (project, mut tasks) in groups {
+        tasks.sort_by_key(|t| t.completed_at);
+        lines.push(String::new());
+        lines.push(format!(
+            "{}: {} {}, {} tracked",
+            project,
+            tasks.len(),
+            plural(tasks.len()),
+            utils::display_minutes(&minutes(&tasks))
+        ));
+        lines.extend(tasks.iter().map(|t| format!("  - {}", t.description)));
+    }
+    lines.join("\n")
+}
+
+/// What was completed yesterday, what is being worked on today and what is blocked
+pub fn standup(
+    ready: &[ReadyTask],
+    waiting: &[WaitingTask],
+    completed: &[CompletedTask],
+) -> String {
+    let today = Local::now().date_naive();
+    let yesterday = completed
+        .iter()
+        .filter(|t| {
+            t.completed_at
+                .is_some_and(|c| today.pred_opt() == Some(c.date_naive()))
+        })
+        .map(|t| t.description.clone())
+        .collect::<Vec<String>>();
+    // Active sigos and those tracked earlier today
+    let in_progress = ready
+        .iter()
+        .filter(|t| {
+            t.intervals
+                .iter()
+                .any(|i| i.end.is_none_or(|end| end.date_naive() == today))
+        })
+        .map(|t| {
+            let active = if t.is_active() { " (active)" } else { "" };
+            format!("{} {}{}", t.id, t.get_main_description(), active)
+        })
+        .collect::<Vec<String>>();
+    let blocked = BlockedTask::from_tasks(ready, waiting)
+        .into_iter()
+        .map(|t| format!("{} {} (blocked by {})", t.id, t.description, t.blocked_by))
+        .collect::<Vec<String>>();
+    [
+        ("Yesterday", yesterday),
+        ("Today", in_progress),
+        ("Blocked", blocked),
+    ]
+    .into_iter()
+    .map(|(title, items)| {
+        let items = if items.is_empty() {
+            vec!["  (nothing)".to_owned()]
+        } else {
+            items.iter().map(|item| format!("  - {}", item)).collect()
+        };
+        format!("{}\n{}", title, items.join("\n"))
+    })
+    .collect::<Vec<String>>()
+    .join("\n\n")
+}
+
+/// Open sigos of a project by how long ago they were added
+#[derive(Tabled)]
+pub struct AgeBuckets {
+    pub project: String,
+    #[tabled(rename = "<1w")]
+    pub week: usize,
+    #[tabled(rename = "1-4w")]
+    pub month: usize,
+    #[tabled(rename = "1-3m")]
+    pub quarter: usize,
+    #[tabled(rename = ">3m")]
+    pub older: usize,
+}
+
+impl AgeBuckets {
+    /// Projects by name, with sigos without a project last
+    pub fn from_tasks(tasks: Vec<Task>) -> Vec<Self> {
+        let now = Local::now();
+        let mut groups = utils::group_by_project(tasks, |t| t.project())
+            .into_iter()
+            .collect::<Vec<_>>();
+        groups.sort_by_key(|(project, _)| (project == "(none)", project.clone()));
+        groups
+            .into_iter()
+            .map(|(project, tasks)| {
+                let mut buckets = Self {
+                    project,
+                    week: 0,
+                    month: 0,
+                    quarter: 0,
+                    older: 0,
+                };
+                for task in tasks {
+                    let days = (now - task.created_at()).num_days();
+                    match days {
+                        ..7 => buckets.week += 1,
+                        7..28 => buckets.month += 1,
+                        28..91 => buckets.quarter += 1,
+                        _ => buckets.older += 1,
+                    }
+                }
+                buckets
+            })
+            .collect()
+    }
+}
+
+/// Consecutive days with a completion up to today, or up to yesterday before any today
+pub fn completion_streak(completed: &[CompletedTask]) -> usize {
+    let days = completed
+        .iter()
+        .filter_map(|t| t.completed_at)
+        .map(|c| c.date_naive())
+        .collect::<HashSet<NaiveDate>>();
+    let today = Local::now().date_naive();
+    let mut day = if days.contains(&today) {
+        Some(today)
+    } else {
+        today.pred_opt()
+    };
+    let mut streak = 0;
+    while let Some(d) = day.filter(|d| days.contains(d)) {
+        streak += 1;
+        day = d.pred_opt();
+    }
+    streak
+}
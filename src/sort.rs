@@ -0,0 +1,103 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use clap::ValueEnum;
+
+use crate::{config::MyConfig, task::Task};
+
+/// Attribute sigos can be sorted by
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum SortField {
+    Id,
+    Description,
+    Priority,
+    Project,
+    Due,
+    Scheduled,
+    Age,
+    Urgency,
+}
+
+const FIELD_NAMES: &str = "id, description, priority, project, due, scheduled, age or urgency";
+
+#[derive(Copy, Clone, Debug)]
+struct SortKey {
+    field: SortField,
+    descending: bool,
+}
+
+/// Order of sigos given like `due+,priority-`, by the first key and then the next ones on ties
+#[derive(Clone, Debug)]
+pub struct Sort(Vec<SortKey>);
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys = Vec::new();
+        for key in s.split(',').map(str::trim).filter(|k| !k.is_empty()) {
+            let (name, descending) = match key.strip_suffix('-') {
+                Some(name) => (name, true),
+                None => (key.strip_suffix('+').unwrap_or(key), false),
+            };
+            let field = SortField::from_str(name, true)
+                .map_err(|_| format!("unknown sort key '{}' ({})", name, FIELD_NAMES))?;
+            keys.push(SortKey { field, descending });
+        }
+        if keys.is_empty() {
+            return Err("no sort key".to_owned());
+        }
+        Ok(Sort(keys))
+    }
+}
+
+impl Sort {
+    /// Sort the sigos, keeping the order of those which compare equal
+    pub fn sort<T>(&self, cfg: &MyConfig, tasks: Vec<T>) -> Vec<T>
+    where
+        T: Clone + Into<Task>,
+    {
+        let mut keyed = tasks
+            .into_iter()
+            .map(|t| (t.clone().into(), t))
+            .collect::<Vec<(Task, T)>>();
+        keyed.sort_by(|(a, _), (b, _)| self.compare(cfg, a, b));
+        keyed.into_iter().map(|(_, t)| t).collect()
+    }
+
+    fn compare(&self, cfg: &MyConfig, a: &Task, b: &Task) -> Ordering {
+        self.0
+            .iter()
+            .map(|key| {
+                let ordering = match key.field {
+                    SortField::Id => return some_first(&a.id(), &b.id(), key),
+                    SortField::Description => a.description().cmp(&b.description()),
+                    // Higher priorities come later, like larger numbers
+                    SortField::Priority => b.priority().cmp(&a.priority()),
+                    SortField::Project => return some_first(a.project(), b.project(), key),
+                    SortField::Due => return some_first(&a.due(), &b.due(), key),
+                    SortField::Scheduled => return some_first(&a.scheduled(), &b.scheduled(), key),
+                    // Older sigos come later
+                    SortField::Age => b.created_at().cmp(&a.created_at()),
+                    SortField::Urgency => a.urgency(cfg).total_cmp(&b.urgency(cfg)),
+                };
+                if key.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Compare present values in the order of the key, with missing ones last either way
+fn some_first<T: Ord>(a: &Option<T>, b: &Option<T>, key: &SortKey) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if key.descending => b.cmp(a),
+        (Some(a), Some(b)) => a.cmp(b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
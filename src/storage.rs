@@ -0,0 +1,1802 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashSet},
+    fmt,
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use clap::ValueEnum;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::{
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::Value;
+use strum::Display;
+
+use crate::{
+    config::MyConfig,
+    crypt::Crypt,
+    error::SigoError,
+    ids, migrations,
+    operations::{self, Logged},
+    task::{CompletedTask, DeletedTask, ReadyTask, RecurringTask, WaitingTask},
+    utils,
+};
+
+const DATABASE_FILE_NAME: &str = "sigo.db";
+const TASKS_FILE_NAME: &str = "tasks";
+/// Where the sigos are in the tasks file, so one is read without parsing all of them
+const INDEX_FILE_NAME: &str = "index";
+/// Renames finishing a write to several files, left behind if sigo stopped before it finished
+const TRANSACTION_FILE_NAME: &str = "transaction";
+/// Extension of the files of the `jsonl` backend
+const LINES_EXTENSION: &str = "jsonl";
+/// Extension of collections compressed with gzip
+const GZIP_EXTENSION: &str = "gz";
+
+/// Where the collections of sigos, like ready sigos or a yearly archive, are kept
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum, Display,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Backend {
+    /// A JSON file of every sigo with its status, and one per yearly archive
+    #[default]
+    Json,
+    /// A JSON Lines file of every sigo with its status, one per line, and one per yearly archive
+    Jsonl,
+    /// Rows of a SQLite database in the data directory
+    Sqlite,
+    /// Kept only while sigo runs, without journal or operations log, for tests and `--ephemeral`
+    #[value(skip)]
+    Memory,
+}
+
+/// How far writes to the data directory go before sigo carries on
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Durability {
+    /// Sync the files and their directory to the disk, so the sigos written survive a power
+    /// failure
+    #[default]
+    Safe,
+    /// Leave the syncing to the system, faster but possibly losing the last writes on a power
+    /// failure
+    Fast,
+}
+
+/// Collections of sigos stored by name, each given as a JSON array
+///
+/// Every read and write of sigos goes through it, from `read_tasks` and `write_tasks` of the
+/// sigo types to the journal, so a backend is added by implementing it and opening it in
+/// `open_with` for a new `Backend`.
+pub trait Storage {
+    /// File keeping the collection, for error messages
+    fn location(&self, name: &str) -> PathBuf;
+
+    /// Error of a collection which is not a list of sigos
+    fn parse_error(&self, name: &str, err: serde_json::Error) -> SigoError {
+        SigoError::ParseStrToTasksErr(self.location(name), err)
+    }
+
+    /// The collection, `[]` if it does not exist yet
+    fn read(&self, name: &str) -> Result<String, SigoError>;
+
+    fn write(&self, name: &str, content: &str) -> Result<(), SigoError>;
+
+    /// Write the collections together, like moving a sigo from one to another
+    fn write_all(&self, collections: &[(&str, String)]) -> Result<(), SigoError> {
+        for (name, content) in collections {
+            self.write(name, content)?;
+        }
+        Ok(())
+    }
+
+    /// Names of the stored collections, sorted
+    fn names(&self) -> Result<Vec<String>, SigoError>;
+
+    /// The sigo of the collection with the id, reading only that one where the backend can
+    fn find_by_id(&self, name: &str, id: u32) -> Result<Option<Value>, SigoError> {
+        Ok(self.values(name)?.into_iter().find(|t| t["id"] == id))
+    }
+
+    /// Number of sigos in the collection
+    fn count(&self, name: &str) -> Result<usize, SigoError> {
+        Ok(self.values(name)?.len())
+    }
+
+    fn values(&self, name: &str) -> Result<Vec<Value>, SigoError> {
+        serde_json::from_str(&self.read(name)?).map_err(|e| self.parse_error(name, e))
+    }
+
+    /// Ids of the sigos in the collections
+    fn ids(&self, names: &[&str]) -> Result<HashSet<u32>, SigoError> {
+        let mut ids = HashSet::new();
+        for name in names {
+            ids.extend(ids_of(&self.values(name)?));
+        }
+        Ok(ids)
+    }
+
+    /// Pass the sigos of the collection to `f` one at a time, parsing each only when it is
+    /// passed where the backend can, so large collections like archives are not read whole
+    fn for_each(
+        &self,
+        name: &str,
+        f: &mut dyn FnMut(Value) -> Result<(), SigoError>,
+    ) -> Result<(), SigoError> {
+        self.values(name)?.into_iter().try_for_each(f)
+    }
+}
+
+/// Visitor of a JSON array passing each element to `f` as soon as it is parsed
+struct EachElement<'a> {
+    f: &'a mut dyn FnMut(Value) -> Result<(), SigoError>,
+    /// Error of `f`, which stops the parsing
+    error: &'a mut Option<SigoError>,
+}
+
+impl<'de> Visitor<'de> for EachElement<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of sigos")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<Value>()? {
+            if let Err(e) = (self.f)(value) {
+                *self.error = Some(e);
+                return Err(de::Error::custom("stopped"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for EachElement<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+/// Visitor of the tasks file passing its sigos to `f` as they are parsed, unless it is of an
+/// older version, which is upgraded as a whole before its sigos are read
+struct EachInTasksFile<'a> {
+    tasks: EachElement<'a>,
+    generation: &'a mut Option<u64>,
+    outdated: &'a mut bool,
+}
+
+impl<'de> Visitor<'de> for EachInTasksFile<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a tasks file")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut current = false;
+        let mut tasks = Some(self.tasks);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "version" => current = map.next_value::<u32>()? == migrations::VERSION,
+                "generation" => *self.generation = Some(map.next_value()?),
+                // Written after the version, so the sigos of an older file are never passed
+                "tasks" if current => match tasks.take() {
+                    Some(tasks) => map.next_value_seed(tasks)?,
+                    None => return Err(de::Error::duplicate_field("tasks")),
+                },
+                _ if !current => {
+                    *self.outdated = true;
+                    return Err(de::Error::custom("outdated"));
+                }
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The tasks file of version 0, a bare array
+    fn visit_seq<A: SeqAccess<'de>>(self, _: A) -> Result<(), A::Error> {
+        *self.outdated = true;
+        Err(de::Error::custom("outdated"))
+    }
+}
+
+/// Reader summing the bytes passed through it but the last few, which hold the checksum
+/// closing the tasks file, so the file is checked without being held whole
+struct Summing<R> {
+    inner: R,
+    hasher: crc32fast::Hasher,
+    /// Bytes read last, not summed yet
+    tail: Vec<u8>,
+}
+
+impl<R: Read> Summing<R> {
+    /// Longer than the checksum closing the tasks file with the whitespace around it
+    const TAIL: usize = 64;
+
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: crc32fast::Hasher::new(),
+            tail: Vec::new(),
+        }
+    }
+
+    /// Read the rest and fail if it does not match the checksum closing it, like
+    /// `verify_embedded`
+    fn verify(mut self, path: &Path) -> Result<(), SigoError> {
+        io::copy(&mut self, &mut io::sink())
+            .map_err(|e| SigoError::FileReadErr(path.to_path_buf(), e))?;
+        let Some((body, sum)) = without_checksum(&self.tail) else {
+            return Ok(());
+        };
+        self.hasher.update(body);
+        match format!("{:08x}", self.hasher.finalize()).as_bytes() == sum {
+            true => Ok(()),
+            false => Err(SigoError::Corrupted(
+                path.to_path_buf(),
+                "its contents do not match the checksum closing it".to_owned(),
+            )),
+        }
+    }
+}
+
+impl<R: Read> Read for Summing<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.tail.extend_from_slice(&buf[..read]);
+        let summed = self.tail.len().saturating_sub(Self::TAIL);
+        self.hasher.update(&self.tail[..summed]);
+        self.tail.drain(..summed);
+        Ok(read)
+    }
+}
+
+/// Pass the sigos of the JSON array read from the reader to `f` as they are parsed
+fn stream_array(
+    reader: impl Read,
+    path: &Path,
+    f: &mut dyn FnMut(Value) -> Result<(), SigoError>,
+) -> Result<(), SigoError> {
+    let err = |e| SigoError::ParseStrToTasksErr(path.to_path_buf(), e);
+    let mut error = None;
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let parsed = deserializer.deserialize_seq(EachElement {
+        f,
+        error: &mut error,
+    });
+    if let Some(e) = error {
+        return Err(e);
+    }
+    parsed.map_err(err)?;
+    deserializer.end().map_err(err)
+}
+
+fn ids_of(tasks: &[Value]) -> impl Iterator<Item = u32> + '_ {
+    tasks
+        .iter()
+        .filter_map(|t| t["id"].as_u64()?.try_into().ok())
+}
+
+/// Fail for what needs the sigos stored in files, like the journal, if they are kept in memory
+pub fn ensure_on_disk(cfg: &MyConfig, what: &'static str) -> Result<(), SigoError> {
+    match cfg.storage {
+        Backend::Memory => Err(SigoError::InMemory(what)),
+        _ => Ok(()),
+    }
+}
+
+/// Whether commands write their changes
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Write,
+    /// Commands changing anything fail before writing
+    ReadOnly,
+    /// Commands run as usual, but their changes are reported instead of written
+    DryRun,
+}
+
+static MODE: OnceLock<Mode> = OnceLock::new();
+
+pub fn set_mode(mode: Mode) {
+    let _ = MODE.set(mode);
+}
+
+pub fn mode() -> Mode {
+    MODE.get().copied().unwrap_or_default()
+}
+
+/// Whether to go on writing: failing in read-only mode, and false in a dry run, whose changes
+/// are not written
+pub fn may_write() -> Result<bool, SigoError> {
+    match mode() {
+        Mode::Write => Ok(true),
+        Mode::ReadOnly => Err(SigoError::ReadOnly),
+        Mode::DryRun => Ok(false),
+    }
+}
+
+/// Collections a dry run would have written, in the order first written
+static DISCARDED: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+fn discard(collections: &[(&str, String)]) {
+    let mut discarded = DISCARDED.lock().unwrap();
+    for (name, content) in collections {
+        match discarded
+            .iter_mut()
+            .find(|(discarded, _)| discarded == name)
+        {
+            Some((_, discarded)) => *discarded = content.clone(),
+            None => discarded.push((name.to_string(), content.clone())),
+        }
+    }
+}
+
+/// A sigo as shown in the changes of a dry run, by id if it has one
+fn label(task: &Value) -> String {
+    let description = match &task["description"] {
+        Value::Array(words) => words
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<&str>>()
+            .join(" "),
+        description => description.as_str().unwrap_or_default().to_owned(),
+    };
+    match task["id"].as_u64() {
+        Some(id) => format!("sigo {} \"{}\"", id, description),
+        None => format!("sigo \"{}\"", description),
+    }
+}
+
+/// Changes to the sigos the dry run would have written, one per line
+pub fn dry_run_changes(cfg: &MyConfig) -> Result<Vec<String>, SigoError> {
+    let discarded = std::mem::take(&mut *DISCARDED.lock().unwrap());
+    let storage = open(cfg);
+    // Sigos are told apart by their uuid, the recurring ones without one by all of them
+    let key = |task: &Value| match &task["uuid"] {
+        Value::Null => task.to_string(),
+        uuid => uuid.to_string(),
+    };
+    let mut changes = Vec::new();
+    for (name, content) in discarded {
+        let before = storage.values(&name)?;
+        let after = serde_json::from_str::<Vec<Value>>(&content)
+            .map_err(|e| storage.parse_error(&name, e))?;
+        for task in &after {
+            match before.iter().find(|t| key(t) == key(task)) {
+                None => changes.push(format!("add {} to {}", label(task), name)),
+                Some(old) if old != task => {
+                    changes.push(format!("change {} in {}", label(task), name))
+                }
+                Some(_) => {}
+            }
+        }
+        for task in before
+            .iter()
+            .filter(|t| !after.iter().any(|a| key(a) == key(t)))
+        {
+            changes.push(format!("remove {} from {}", label(task), name));
+        }
+    }
+    Ok(changes)
+}
+
+/// Storage of the backend chosen in the config, logging the changes made through it and
+/// holding them back during a `transaction`
+pub fn open(cfg: &MyConfig) -> Box<dyn Storage> {
+    let inner: Box<dyn Storage> = match cfg.storage {
+        Backend::Memory => Box::new(Memory),
+        backend => Box::new(Logged::new(cfg, open_backend(cfg, backend))),
+    };
+    Box::new(Staged { inner })
+}
+
+thread_local! {
+    /// Collections written during the running transaction, in the order first written
+    static STAGED: RefCell<Option<Vec<(String, String)>>> = const { RefCell::new(None) };
+}
+
+/// Run the command holding back its writes to the storage of the config, which are written
+/// together at the end if it succeeds, so that it changes either all of the sigos it wrote or
+/// none of them; a command failing for only some of its sigos keeps the changes to the others,
+/// which its error reports
+pub fn transaction<T>(
+    cfg: &MyConfig,
+    command: impl FnOnce() -> Result<T, SigoError>,
+) -> Result<T, SigoError> {
+    STAGED.set(Some(Vec::new()));
+    let output = command();
+    let staged = STAGED.take().unwrap_or_default();
+    if !matches!(output, Ok(_) | Err(SigoError::Failures(..))) {
+        return output;
+    }
+    let collections = staged
+        .iter()
+        .map(|(name, content)| (name.as_str(), content.clone()))
+        .collect::<Vec<(&str, String)>>();
+    if mode() == Mode::DryRun {
+        discard(&collections);
+    } else if !collections.is_empty() {
+        open(cfg).write_all(&collections)?;
+    }
+    output
+}
+
+/// Storage reading the collections written during the running transaction as written, and
+/// passing everything through outside of one
+struct Staged {
+    inner: Box<dyn Storage>,
+}
+
+impl Staged {
+    fn staged(&self, name: &str) -> Option<String> {
+        STAGED.with_borrow(|staged| {
+            staged
+                .as_ref()?
+                .iter()
+                .find(|(staged, _)| staged == name)
+                .map(|(_, content)| content.clone())
+        })
+    }
+}
+
+impl Storage for Staged {
+    fn location(&self, name: &str) -> PathBuf {
+        self.inner.location(name)
+    }
+
+    fn parse_error(&self, name: &str, err: serde_json::Error) -> SigoError {
+        self.inner.parse_error(name, err)
+    }
+
+    fn read(&self, name: &str) -> Result<String, SigoError> {
+        match self.staged(name) {
+            Some(content) => Ok(content),
+            None => self.inner.read(name),
+        }
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<(), SigoError> {
+        self.write_all(&[(name, content.to_owned())])
+    }
+
+    /// Fails at once in read-only mode, before anything is written
+    fn write_all(&self, collections: &[(&str, String)]) -> Result<(), SigoError> {
+        let may_write = may_write()?;
+        let staged = STAGED.with_borrow_mut(|staged| {
+            let Some(staged) = staged.as_mut() else {
+                return false;
+            };
+            for (name, content) in collections {
+                match staged.iter_mut().find(|(staged, _)| staged == name) {
+                    Some((_, staged)) => *staged = content.clone(),
+                    None => staged.push((name.to_string(), content.clone())),
+                }
+            }
+            true
+        });
+        match (staged, may_write) {
+            (true, _) => Ok(()),
+            (false, true) => self.inner.write_all(collections),
+            (false, false) => {
+                discard(collections);
+                Ok(())
+            }
+        }
+    }
+
+    fn names(&self) -> Result<Vec<String>, SigoError> {
+        let mut names = self.inner.names()?;
+        STAGED.with_borrow(|staged| {
+            if let Some(staged) = staged {
+                names.extend(staged.iter().map(|(name, _)| name.clone()));
+            }
+        });
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    fn find_by_id(&self, name: &str, id: u32) -> Result<Option<Value>, SigoError> {
+        match self.staged(name) {
+            Some(_) => Ok(self.values(name)?.into_iter().find(|t| t["id"] == id)),
+            None => self.inner.find_by_id(name, id),
+        }
+    }
+
+    fn count(&self, name: &str) -> Result<usize, SigoError> {
+        match self.staged(name) {
+            Some(_) => Ok(self.values(name)?.len()),
+            None => self.inner.count(name),
+        }
+    }
+
+    fn ids(&self, names: &[&str]) -> Result<HashSet<u32>, SigoError> {
+        let (staged, stored): (Vec<&str>, Vec<&str>) =
+            names.iter().partition(|name| self.staged(name).is_some());
+        let mut ids = self.inner.ids(&stored)?;
+        for name in staged {
+            ids.extend(ids_of(&self.values(name)?));
+        }
+        Ok(ids)
+    }
+
+    fn for_each(
+        &self,
+        name: &str,
+        f: &mut dyn FnMut(Value) -> Result<(), SigoError>,
+    ) -> Result<(), SigoError> {
+        match self.staged(name) {
+            Some(_) => self.values(name)?.into_iter().try_for_each(f),
+            None => self.inner.for_each(name, f),
+        }
+    }
+}
+
+pub fn open_backend(cfg: &MyConfig, backend: Backend) -> Box<dyn Storage> {
+    open_with(cfg, backend, Crypt::new(cfg))
+}
+
+/// Storage of the backend encrypting and decrypting the sigos with the keys given
+pub fn open_with(cfg: &MyConfig, backend: Backend, crypt: Crypt) -> Box<dyn Storage> {
+    let dir = PathBuf::from(&cfg.data);
+    match backend {
+        Backend::Json => Box::new(JsonFiles {
+            path: dir.join(TASKS_FILE_NAME),
+            dir,
+            crypt,
+            compress: cfg.compress_archives,
+            durability: cfg.durability,
+        }),
+        Backend::Jsonl => Box::new(JsonLines {
+            path: dir.join(format!("{}.{}", TASKS_FILE_NAME, LINES_EXTENSION)),
+            dir,
+            crypt,
+            durability: cfg.durability,
+        }),
+        Backend::Sqlite => Box::new(Sqlite {
+            path: dir.join(DATABASE_FILE_NAME),
+            crypt,
+            durability: cfg.durability,
+        }),
+        Backend::Memory => Box::new(Memory),
+    }
+}
+
+/// Generations of the stores, counting the writes to them, as first read by the running command
+static SEEN: Mutex<BTreeMap<PathBuf, u64>> = Mutex::new(BTreeMap::new());
+static WROTE: AtomicBool = AtomicBool::new(false);
+
+/// Forget the generations seen, before running a command, or running it again
+pub fn begin() {
+    SEEN.lock().unwrap().clear();
+    WROTE.store(false, Ordering::SeqCst);
+}
+
+/// Whether the running command has written anything, after which it is unsafe to run it again
+pub fn wrote() -> bool {
+    WROTE.load(Ordering::SeqCst)
+}
+
+fn saw(store: &Path, generation: u64) {
+    SEEN.lock()
+        .unwrap()
+        .entry(store.to_path_buf())
+        .or_insert(generation);
+}
+
+/// Fail if another process wrote the store since the running command first read it
+fn check(store: &Path, generation: u64) -> Result<(), SigoError> {
+    match SEEN.lock().unwrap().get(store) {
+        Some(seen) if *seen != generation => Err(SigoError::Conflict(store.to_path_buf())),
+        _ => Ok(()),
+    }
+}
+
+fn written(store: &Path, generation: u64) {
+    SEEN.lock().unwrap().insert(store.to_path_buf(), generation);
+    WROTE.store(true, Ordering::SeqCst);
+}
+
+/// Names of the files which are not collections of sigos
+const OTHER_FILES: [&str; 8] = [
+    "journal",
+    "context",
+    ids::FILE_NAME,
+    operations::FILE_NAME,
+    DATABASE_FILE_NAME,
+    TASKS_FILE_NAME,
+    INDEX_FILE_NAME,
+    TRANSACTION_FILE_NAME,
+];
+
+/// Collections kept together in the tasks file, with the status their sigos are stored with
+const STATUSES: [(&str, &str); 5] = [
+    (ReadyTask::FILE_NAME, "ready"),
+    (WaitingTask::FILE_NAME, "waiting"),
+    (CompletedTask::FILE_NAME, "completed"),
+    (DeletedTask::FILE_NAME, "deleted"),
+    (RecurringTask::FILE_NAME, "recurring"),
+];
+
+fn status_of(name: &str) -> Option<&'static str> {
+    STATUSES
+        .iter()
+        .find(|(file_name, _)| *file_name == name)
+        .map(|(_, status)| *status)
+}
+
+struct JsonFiles {
+    dir: PathBuf,
+    /// The tasks file, holding the collections of `STATUSES`
+    path: PathBuf,
+    crypt: Crypt,
+    /// Whether collections outside the tasks file, the archives, are written compressed
+    compress: bool,
+    durability: Durability,
+}
+
+/// Write to a temporary file next to the path, returned to be renamed over it, named after the
+/// whole file name so that files differing only in their extension are staged apart
+fn write_temporary(
+    path: &Path,
+    content: &[u8],
+    durability: Durability,
+) -> Result<PathBuf, SigoError> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".sigo-tmp-{}", std::process::id()));
+    let tmp_path = path.with_file_name(name);
+    let mut file =
+        fs::File::create(&tmp_path).map_err(|e| SigoError::FileCreateErr(tmp_path.clone(), e))?;
+    file.write_all(content)
+        .and_then(|_| match durability {
+            Durability::Safe => file.sync_all(),
+            Durability::Fast => Ok(()),
+        })
+        .map_err(|e| SigoError::FileWriteErr(tmp_path.clone(), e))?;
+    Ok(tmp_path)
+}
+
+fn rename(from: &Path, to: &Path) -> Result<(), SigoError> {
+    fs::rename(from, to)
+        .map_err(|e| SigoError::FileRenameErr(from.to_path_buf(), to.to_path_buf(), e))
+}
+
+/// Sync the directory of the path in `safe` durability, so that files renamed into it or
+/// removed from it stay so after a power failure
+pub fn sync_dir(path: &Path, durability: Durability) -> Result<(), SigoError> {
+    let dir = path.parent().unwrap_or(path);
+    // Directories cannot be opened to be synced on Windows
+    if durability == Durability::Safe && cfg!(unix) {
+        fs::File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .map_err(|e| SigoError::FileWriteErr(dir.to_path_buf(), e))?;
+    }
+    Ok(())
+}
+
+/// Write to a temporary file renamed over the old one, so readers never see half a file
+pub fn write_atomically(
+    path: &Path,
+    content: &[u8],
+    durability: Durability,
+) -> Result<(), SigoError> {
+    if !may_write()? {
+        return Ok(());
+    }
+    rename(&write_temporary(path, content, durability)?, path)?;
+    sync_dir(path, durability)
+}
+
+/// Files of a write to several files, staged next to them, and the files they replace in
+/// another format
+#[derive(Default, Serialize, Deserialize)]
+struct Transaction {
+    renames: Vec<(PathBuf, PathBuf)>,
+    removals: Vec<PathBuf>,
+}
+
+impl Transaction {
+    /// Write the files together: each is staged next to it, then the renames over them are
+    /// recorded in the transaction file of the directory before they are done, so that sigo
+    /// stopping in between leaves either none of them or a transaction finished by `recover`
+    fn commit(
+        dir: &Path,
+        files: &[(PathBuf, Vec<u8>)],
+        removals: Vec<PathBuf>,
+        durability: Durability,
+    ) -> Result<(), SigoError> {
+        let removals = removals
+            .into_iter()
+            .filter(|path| path.is_file())
+            .collect::<Vec<PathBuf>>();
+        if let ([(path, content)], []) = (files, removals.as_slice()) {
+            return write_atomically(path, content, durability);
+        }
+        let mut transaction = Transaction {
+            removals,
+            ..Default::default()
+        };
+        for (path, content) in files {
+            transaction
+                .renames
+                .push((write_temporary(path, content, durability)?, path.clone()));
+        }
+        let record = dir.join(TRANSACTION_FILE_NAME);
+        let content = serde_json::to_string(&transaction)?;
+        write_atomically(&record, content.as_bytes(), durability)?;
+        transaction.apply()?;
+        fs::remove_file(&record).map_err(|e| SigoError::FileWriteErr(record.clone(), e))?;
+        sync_dir(&record, durability)
+    }
+
+    /// Finish a write to several files of the directory which sigo stopped in the middle of
+    fn recover(dir: &Path, durability: Durability) -> Result<(), SigoError> {
+        let record = dir.join(TRANSACTION_FILE_NAME);
+        if !record.is_file() {
+            return Ok(());
+        }
+        let content =
+            fs::read_to_string(&record).map_err(|e| SigoError::FileReadErr(record.clone(), e))?;
+        serde_json::from_str::<Transaction>(&content)
+            .map_err(|e| SigoError::ParseStrToTasksErr(record.clone(), e))?
+            .apply()?;
+        fs::remove_file(&record).map_err(|e| SigoError::FileWriteErr(record.clone(), e))?;
+        sync_dir(&record, durability)
+    }
+
+    /// Finish the renames and removals, skipping those done before sigo stopped
+    fn apply(&self) -> Result<(), SigoError> {
+        for (staged, path) in &self.renames {
+            if staged.is_file() {
+                rename(staged, path)?;
+            }
+        }
+        for path in &self.removals {
+            if path.is_file() {
+                fs::remove_file(path).map_err(|e| SigoError::FileWriteErr(path.clone(), e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Every sigo with its status, and the number of times the file was written
+#[derive(Serialize, Deserialize)]
+struct TasksFile {
+    version: u32,
+    generation: u64,
+    tasks: Vec<Value>,
+}
+
+/// Start of the tasks file of the generation, up to its sigos
+fn tasks_file_header(generation: u64) -> String {
+    format!(
+        r#"{{"version":{},"generation":{},"tasks":["#,
+        migrations::VERSION,
+        generation
+    )
+}
+
+/// Start of the checksum closing the tasks file, of its bytes before it
+const CHECKSUM_KEY: &str = r#","checksum":""#;
+/// Extension of the files next to the archives holding their checksums
+const CHECKSUM_EXTENSION: &str = "sum";
+
+fn checksum(bytes: &[u8]) -> String {
+    format!("{:08x}", crc32fast::hash(bytes))
+}
+
+/// The tasks file without the checksum closing it, or `None` if it has none, as written
+/// before there were checksums
+fn without_checksum(content: &[u8]) -> Option<(&[u8], &[u8])> {
+    let rest = content.trim_ascii_end().strip_suffix(b"\"}")?;
+    let (body, sum) = rest.split_at(rest.len().checked_sub(CHECKSUM_KEY.len() + 8)?);
+    Some((body, sum.strip_prefix(CHECKSUM_KEY.as_bytes())?))
+}
+
+/// Fail if the tasks file does not match the checksum closing it
+fn verify_embedded(path: &Path, content: &[u8]) -> Result<(), SigoError> {
+    match without_checksum(content) {
+        Some((body, sum)) if checksum(body).as_bytes() != sum => Err(SigoError::Corrupted(
+            path.to_path_buf(),
+            "its contents do not match the checksum closing it".to_owned(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The file next to an archive holding its checksum
+fn checksum_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", CHECKSUM_EXTENSION));
+    path.with_file_name(name)
+}
+
+/// Fail if the file does not match the checksum next to it, if there is one
+fn verify_sidecar(path: &Path, content: &[u8]) -> Result<(), SigoError> {
+    let sum_path = checksum_path(path);
+    let Ok(sum) = fs::read_to_string(&sum_path) else {
+        return Ok(());
+    };
+    match sum.trim() == checksum(content) {
+        true => Ok(()),
+        false => Err(SigoError::Corrupted(
+            path.to_path_buf(),
+            format!("its contents do not match the checksum in {:?}", sum_path),
+        )),
+    }
+}
+
+/// Where the sigos of the tasks file of a generation are, written after it, so an index left
+/// behind by another sigo writing the tasks file is told by its generation and length
+#[derive(Default, Serialize, Deserialize)]
+struct Index {
+    generation: u64,
+    len: u64,
+    /// Number of sigos by status
+    counts: BTreeMap<String, usize>,
+    /// Offset and length of the sigos by status and id
+    ids: BTreeMap<String, BTreeMap<u32, (u64, u64)>>,
+}
+
+impl JsonFiles {
+    fn read_file(&self, path: &PathBuf) -> Result<String, SigoError> {
+        let content =
+            fs::read_to_string(path).map_err(|e| SigoError::FileReadErr(path.clone(), e))?;
+        self.crypt.open(path, content)
+    }
+
+    fn write_file(&self, path: &Path, content: String) -> Result<(), SigoError> {
+        write_atomically(path, self.crypt.seal(content)?.as_bytes(), self.durability)
+    }
+
+    fn compressed_path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", name, GZIP_EXTENSION))
+    }
+
+    /// Buffered reader of the file, decrypted and decompressed, which reads it as it goes
+    /// unless it was encrypted or has a checksum to check first
+    fn reader(&self, path: &Path, compressed: bool) -> Result<Box<dyn Read>, SigoError> {
+        let err = |e| SigoError::FileReadErr(path.to_path_buf(), e);
+        let mut file = BufReader::new(fs::File::open(path).map_err(err)?);
+        let sealed = Crypt::is_sealed(file.fill_buf().map_err(err)?);
+        let plain: Box<dyn Read> = if sealed || checksum_path(path).is_file() {
+            let mut content = Vec::new();
+            file.read_to_end(&mut content).map_err(err)?;
+            verify_sidecar(path, &content)?;
+            match sealed {
+                true => Box::new(Cursor::new(self.crypt.open_bytes(path, content)?)),
+                false => Box::new(Cursor::new(content)),
+            }
+        } else {
+            Box::new(file)
+        };
+        Ok(match compressed {
+            true => Box::new(BufReader::new(GzDecoder::new(plain))),
+            false => plain,
+        })
+    }
+
+    /// The file of a collection outside the tasks file, and whether it was written compressed
+    fn collection_path(&self, name: &str) -> (PathBuf, bool) {
+        let compressed = self.compressed_path(name);
+        match compressed.is_file() {
+            true => (compressed, true),
+            false => (self.dir.join(name), false),
+        }
+    }
+
+    /// A collection outside the tasks file, decompressed if it was written compressed
+    fn read_collection(&self, name: &str) -> Result<String, SigoError> {
+        self.recover()?;
+        let (path, compressed) = self.collection_path(name);
+        if !utils::create_file_if_not_exist(&path)? {
+            return Ok("[]".to_owned());
+        }
+        let mut content = String::new();
+        self.reader(&path, compressed)?
+            .read_to_string(&mut content)
+            .map_err(|e| SigoError::FileReadErr(path.clone(), e))?;
+        Ok(content)
+    }
+
+    /// Contents of a collection outside the tasks file as written, with the path to write them
+    /// to and the one written compressed or not before if it was the other way
+    fn encode_collection(
+        &self,
+        name: &str,
+        content: &str,
+    ) -> Result<(PathBuf, Vec<u8>, PathBuf), SigoError> {
+        let plain = self.dir.join(name);
+        let compressed = self.compressed_path(name);
+        if !self.compress {
+            let sealed = self.crypt.seal(content.to_owned())?.into_bytes();
+            return Ok((plain, sealed, compressed));
+        }
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let gzip = encoder
+            .write_all(content.as_bytes())
+            .and_then(|_| encoder.finish())
+            .map_err(|e| SigoError::FileWriteErr(compressed.clone(), e))?;
+        Ok((compressed, self.crypt.seal_bytes(gzip)?, plain))
+    }
+
+    fn recover(&self) -> Result<(), SigoError> {
+        Transaction::recover(&self.dir, self.durability)
+    }
+
+    /// The tasks file, first moving the sigos of the files per state into it
+    fn read_tasks_file(&self) -> Result<TasksFile, SigoError> {
+        self.recover()?;
+        if !self.path.is_file() {
+            self.merge_state_files()?;
+        }
+        let err = |e| SigoError::ParseStrToTasksErr(self.path.clone(), e);
+        let mut content = Vec::new();
+        self.reader(&self.path, false)?
+            .read_to_end(&mut content)
+            .map_err(|e| SigoError::FileReadErr(self.path.clone(), e))?;
+        verify_embedded(&self.path, &content)?;
+        let value = serde_json::from_slice(&content).map_err(err)?;
+        let version = migrations::version_of(&value);
+        let file: TasksFile =
+            serde_json::from_value(migrations::upgrade(&self.path, value)?).map_err(err)?;
+        // Left as it is when nothing may be written
+        if version < migrations::VERSION && mode() == Mode::Write {
+            self.write_tasks_file(&file)?;
+        }
+        saw(&self.path, file.generation);
+        Ok(file)
+    }
+
+    fn write_tasks_file(&self, file: &TasksFile) -> Result<(), SigoError> {
+        let (content, index) = self.encode_tasks_file(file)?;
+        write_atomically(&self.path, &content, self.durability)?;
+        self.write_index(index)
+    }
+
+    /// The tasks file as written, closed by the checksum of what comes before it, with the index
+    /// of its sigos unless it is encrypted
+    fn encode_tasks_file(&self, file: &TasksFile) -> Result<(Vec<u8>, Option<Index>), SigoError> {
+        let mut content = tasks_file_header(file.generation);
+        let mut index = Index {
+            generation: file.generation,
+            ..Default::default()
+        };
+        for (i, task) in file.tasks.iter().enumerate() {
+            if i > 0 {
+                content.push(',');
+            }
+            let json = serde_json::to_string(task)?;
+            let status = task["status"].as_str().unwrap_or_default().to_owned();
+            *index.counts.entry(status.clone()).or_default() += 1;
+            if let Some(id) = task["id"].as_u64().and_then(|id| u32::try_from(id).ok()) {
+                let at = (content.len() as u64, json.len() as u64);
+                index.ids.entry(status).or_default().entry(id).or_insert(at);
+            }
+            content.push_str(&json);
+        }
+        content.push(']');
+        let sum = checksum(content.as_bytes());
+        content.push_str(&format!("{}{}\"}}", CHECKSUM_KEY, sum));
+        index.len = content.len() as u64;
+        match self.crypt.encrypts() {
+            true => Ok((self.crypt.seal(content)?.into_bytes(), None)),
+            false => Ok((content.into_bytes(), Some(index))),
+        }
+    }
+
+    /// Write the index of the tasks file just written, removing the old one if there is none
+    fn write_index(&self, index: Option<Index>) -> Result<(), SigoError> {
+        let path = self.dir.join(INDEX_FILE_NAME);
+        match index {
+            Some(index) => write_atomically(&path, &serde_json::to_vec(&index)?, self.durability),
+            None if path.is_file() => {
+                fs::remove_file(&path).map_err(|e| SigoError::FileWriteErr(path.clone(), e))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// The index if it is of the tasks file as it is, which is then seen as read
+    fn index(&self) -> Result<Option<Index>, SigoError> {
+        self.recover()?;
+        let Ok(content) = fs::read(self.dir.join(INDEX_FILE_NAME)) else {
+            return Ok(None);
+        };
+        let Ok(index) = serde_json::from_slice::<Index>(&content) else {
+            return Ok(None);
+        };
+        let err = |e| SigoError::FileReadErr(self.path.clone(), e);
+        let Ok(mut file) = fs::File::open(&self.path) else {
+            return Ok(None);
+        };
+        let header = tasks_file_header(index.generation);
+        let mut start = vec![0; header.len()];
+        let len = file.metadata().map_err(err)?.len();
+        if len != index.len || file.read_exact(&mut start).is_err() || start != header.as_bytes() {
+            return Ok(None);
+        }
+        saw(&self.path, index.generation);
+        Ok(Some(index))
+    }
+
+    /// The sigo at the offset and length of the tasks file, without its status
+    fn read_at(&self, (offset, len): (u64, u64)) -> Result<Value, SigoError> {
+        let mut file =
+            fs::File::open(&self.path).map_err(|e| SigoError::FileReadErr(self.path.clone(), e))?;
+        let mut json = vec![0; len as usize];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut json))
+            .map_err(|e| SigoError::FileReadErr(self.path.clone(), e))?;
+        let mut task = serde_json::from_slice::<Value>(&json)
+            .map_err(|e| SigoError::ParseStrToTasksErr(self.path.clone(), e))?;
+        if let Some(task) = task.as_object_mut() {
+            task.remove("status");
+        }
+        Ok(task)
+    }
+
+    /// Move the sigos of `ready_tasks` and the other files per state, kept before there was
+    /// the tasks file, into it
+    fn merge_state_files(&self) -> Result<(), SigoError> {
+        let mut tasks = Vec::new();
+        let mut merged = Vec::new();
+        for (name, status) in STATUSES {
+            let path = self.dir.join(name);
+            if !path.is_file() {
+                continue;
+            }
+            let content = self.read_file(&path)?;
+            let values = serde_json::from_str::<Vec<Value>>(&content)
+                .map_err(|e| SigoError::ParseStrToTasksErr(path.clone(), e))?;
+            tasks.extend(values.into_iter().map(|t| with_status(t, status)));
+            merged.push(path);
+        }
+        // In the format of the first tasks file, upgraded when read
+        self.write_file(&self.path, serde_json::to_string(&tasks)?)?;
+        for path in merged {
+            fs::remove_file(&path).map_err(|e| SigoError::FileWriteErr(path.clone(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Pass the sigos of the tasks file with the status, without it, to `f` as they are parsed,
+    /// checking the checksum closing the file once it is read through, so an error may come
+    /// after some sigos were passed
+    fn stream_tasks_of(
+        &self,
+        status: &str,
+        f: &mut dyn FnMut(Value) -> Result<(), SigoError>,
+    ) -> Result<(), SigoError> {
+        self.recover()?;
+        if !self.path.is_file() {
+            return self.tasks_of(status)?.into_iter().try_for_each(f);
+        }
+        let mut of_status = |mut task: Value| match task.as_object_mut() {
+            Some(fields) if fields.get("status").is_some_and(|s| s == status) => {
+                fields.remove("status");
+                f(task)
+            }
+            _ => Ok(()),
+        };
+        let mut error = None;
+        let mut generation = None;
+        let mut outdated = false;
+        let mut summing = Summing::new(self.reader(&self.path, false)?);
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(&mut summing));
+        let parsed = deserializer
+            .deserialize_any(EachInTasksFile {
+                tasks: EachElement {
+                    f: &mut of_status,
+                    error: &mut error,
+                },
+                generation: &mut generation,
+                outdated: &mut outdated,
+            })
+            .and_then(|_| deserializer.end());
+        drop(deserializer);
+        if let Some(e) = error {
+            return Err(e);
+        }
+        if outdated {
+            return self.tasks_of(status)?.into_iter().try_for_each(f);
+        }
+        // A file broken in the middle is told as corrupted rather than as not parsing
+        summing.verify(&self.path)?;
+        parsed.map_err(|e| SigoError::ParseStrToTasksErr(self.path.clone(), e))?;
+        saw(&self.path, generation.unwrap_or_default());
+        Ok(())
+    }
+
+    /// Sigos of the tasks file with the status, without it
+    fn tasks_of(&self, status: &str) -> Result<Vec<Value>, SigoError> {
+        Ok(self
+            .read_tasks_file()?
+            .tasks
+            .into_iter()
+            .filter(|t| t["status"] == status)
+            .map(|mut t| {
+                if let Some(task) = t.as_object_mut() {
+                    task.remove("status");
+                }
+                t
+            })
+            .collect())
+    }
+
+    /// The tasks file with the sigos of the collection replaced
+    fn replace(
+        &self,
+        mut tasks: Vec<Value>,
+        status: &str,
+        name: &str,
+        content: &str,
+    ) -> Result<Vec<Value>, SigoError> {
+        let replacement = serde_json::from_str::<Vec<Value>>(content)
+            .map_err(|e| SigoError::ParseStrToTasksErr(self.location(name), e))?;
+        tasks.retain(|t| t["status"] != status);
+        tasks.extend(replacement.into_iter().map(|t| with_status(t, status)));
+        Ok(tasks)
+    }
+}
+
+fn with_status(mut task: Value, status: &str) -> Value {
+    if let Some(task) = task.as_object_mut() {
+        task.insert("status".to_owned(), Value::from(status));
+    }
+    task
+}
+
+impl Storage for JsonFiles {
+    fn location(&self, name: &str) -> PathBuf {
+        match status_of(name) {
+            Some(_) => self.path.clone(),
+            None if self.compressed_path(name).is_file() => self.compressed_path(name),
+            None => self.dir.join(name),
+        }
+    }
+
+    fn read(&self, name: &str) -> Result<String, SigoError> {
+        match status_of(name) {
+            Some(status) => Ok(serde_json::to_string(&self.tasks_of(status)?)?),
+            None => self.read_collection(name),
+        }
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<(), SigoError> {
+        self.write_all(&[(name, content.to_owned())])
+    }
+
+    /// Collections in the tasks file are replaced in a single write of it, together with the
+    /// other files written, which counts up its generation even if only other files were written
+    fn write_all(&self, collections: &[(&str, String)]) -> Result<(), SigoError> {
+        let TasksFile {
+            generation,
+            mut tasks,
+            ..
+        } = self.read_tasks_file()?;
+        check(&self.path, generation)?;
+        let mut files = Vec::new();
+        let mut removals = Vec::new();
+        for (name, content) in collections {
+            match status_of(name) {
+                Some(status) => tasks = self.replace(tasks, status, name, content)?,
+                None => {
+                    let (path, content, stale) = self.encode_collection(name, content)?;
+                    files.push((checksum_path(&path), checksum(&content).into_bytes()));
+                    files.push((path, content));
+                    removals.push(checksum_path(&stale));
+                    removals.push(stale);
+                }
+            }
+        }
+        let generation = generation + 1;
+        let file = TasksFile {
+            version: migrations::VERSION,
+            generation,
+            tasks,
+        };
+        let (content, index) = self.encode_tasks_file(&file)?;
+        files.push((self.path.clone(), content));
+        Transaction::commit(&self.dir, &files, removals, self.durability)?;
+        written(&self.path, generation);
+        self.write_index(index)
+    }
+
+    fn names(&self) -> Result<Vec<String>, SigoError> {
+        self.recover()?;
+        let entries =
+            fs::read_dir(&self.dir).map_err(|e| SigoError::FileReadErr(self.dir.clone(), e))?;
+        let mut names = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .map(
+                |name| match name.strip_suffix(&format!(".{}", GZIP_EXTENSION)) {
+                    Some(name) => name.to_owned(),
+                    None => name,
+                },
+            )
+            .filter(|name| {
+                !OTHER_FILES.contains(&name.as_str())
+                    && status_of(name).is_none()
+                    && !name.contains('.')
+            })
+            .chain(STATUSES.iter().map(|(name, _)| name.to_string()))
+            .collect::<Vec<String>>();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Ids found in one read of the tasks file
+    fn ids(&self, names: &[&str]) -> Result<HashSet<u32>, SigoError> {
+        let statuses = names
+            .iter()
+            .filter_map(|name| status_of(name))
+            .collect::<Vec<&str>>();
+        let tasks = self
+            .read_tasks_file()?
+            .tasks
+            .into_iter()
+            .filter(|t| statuses.iter().any(|status| t["status"] == *status))
+            .collect::<Vec<Value>>();
+        let mut ids = ids_of(&tasks).collect::<HashSet<u32>>();
+        for name in names.iter().filter(|name| status_of(name).is_none()) {
+            ids.extend(ids_of(&self.values(name)?));
+        }
+        Ok(ids)
+    }
+
+    /// Sigos in the tasks file are read alone where the index has them
+    fn find_by_id(&self, name: &str, id: u32) -> Result<Option<Value>, SigoError> {
+        let Some(status) = status_of(name) else {
+            return Ok(self.values(name)?.into_iter().find(|t| t["id"] == id));
+        };
+        match self.index()? {
+            Some(index) => index
+                .ids
+                .get(status)
+                .and_then(|ids| ids.get(&id))
+                .map(|at| self.read_at(*at))
+                .transpose(),
+            None => Ok(self.tasks_of(status)?.into_iter().find(|t| t["id"] == id)),
+        }
+    }
+
+    fn count(&self, name: &str) -> Result<usize, SigoError> {
+        match (status_of(name), self.index()?) {
+            (Some(status), Some(index)) => Ok(index.counts.get(status).copied().unwrap_or(0)),
+            _ => Ok(self.values(name)?.len()),
+        }
+    }
+
+    /// The tasks file is parsed as it is read too, passing over the sigos of other statuses
+    fn for_each(
+        &self,
+        name: &str,
+        f: &mut dyn FnMut(Value) -> Result<(), SigoError>,
+    ) -> Result<(), SigoError> {
+        if let Some(status) = status_of(name) {
+            return self.stream_tasks_of(status, f);
+        }
+        self.recover()?;
+        let (path, compressed) = self.collection_path(name);
+        if !path.is_file() {
+            return Ok(());
+        }
+        stream_array(self.reader(&path, compressed)?, &path, f)
+    }
+}
+
+/// Line closing each write to a JSON Lines file
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Trailer {
+    version: u32,
+    generation: u64,
+    /// Checksum of the lines of the write it closes, missing from those written before there
+    /// were checksums
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+}
+
+fn trailer_of(line: &Value) -> Option<Trailer> {
+    serde_json::from_value(line.clone()).ok()
+}
+
+/// Sigos of a JSON Lines file up to the line closing the last write which finished
+struct Lines {
+    tasks: Vec<Value>,
+    generation: u64,
+    /// Whether the file ends with that line, so that more can be appended to it
+    appendable: bool,
+}
+
+/// Sigos one per line, those of `STATUSES` in one file with their status and the other
+/// collections like the archives in files of their own, each write closed by a line with its
+/// generation so that one only adding sigos appends them
+struct JsonLines {
+    dir: PathBuf,
+    /// The tasks file, holding the collections of `STATUSES`
+    path: PathBuf,
+    /// Encryption of each line
+    crypt: Crypt,
+    durability: Durability,
+}
+
+impl JsonLines {
+    fn collection_path(&self, name: &str) -> PathBuf {
+        match status_of(name) {
+            Some(_) => self.path.clone(),
+            None => self.dir.join(format!("{}.{}", name, LINES_EXTENSION)),
+        }
+    }
+
+    /// The sigos of the file, leaving out those after the last trailer, written by an append
+    /// which did not finish
+    fn read_lines(&self, path: &Path) -> Result<Lines, SigoError> {
+        Transaction::recover(&self.dir, self.durability)?;
+        if !path.is_file() {
+            return Ok(Lines {
+                tasks: Vec::new(),
+                generation: 0,
+                appendable: true,
+            });
+        }
+        let content =
+            fs::read_to_string(path).map_err(|e| SigoError::FileReadErr(path.to_path_buf(), e))?;
+        // Each line with the offset it starts at, for the checksums of the writes
+        let mut offset = 0;
+        let lines = content
+            .split_inclusive('\n')
+            .map(|line| {
+                offset += line.len();
+                (offset - line.len(), line.trim_end_matches(['\n', '\r']))
+            })
+            .collect::<Vec<(usize, &str)>>();
+        let parse = |number: usize, line: &str| -> Result<Value, SigoError> {
+            let json = self.crypt.open(path, line.to_owned())?;
+            serde_json::from_str(&json)
+                .map_err(|e| SigoError::ParseLineErr(path.to_path_buf(), number + 1, e))
+        };
+        let last = lines
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(number, (_, line))| {
+                let trailer = trailer_of(&parse(number, line).ok()?)?;
+                Some((number, trailer))
+            });
+        // Without any trailer, as written by hand, every line is a sigo
+        let (end, generation) = match &last {
+            Some((_, trailer)) if trailer.version > migrations::VERSION => {
+                return Err(SigoError::NewerFormat(path.to_path_buf(), trailer.version));
+            }
+            Some((number, trailer)) => (*number, trailer.generation),
+            None => (lines.len(), 0),
+        };
+        let mut tasks = Vec::new();
+        let mut write_start = 0;
+        for (number, (start, line)) in lines[..lines.len().min(end + 1)].iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let task = parse(number, line)?;
+            let Some(trailer) = trailer_of(&task) else {
+                tasks.push(task);
+                continue;
+            };
+            let sum = checksum(&content.as_bytes()[write_start..*start]);
+            if trailer.checksum.is_some_and(|expected| expected != sum) {
+                return Err(SigoError::Corrupted(
+                    path.to_path_buf(),
+                    format!(
+                        "the lines closed by line {} do not match its checksum",
+                        number + 1
+                    ),
+                ));
+            }
+            write_start = start + content[*start..].find('\n').map_or(line.len(), |n| n + 1);
+        }
+        if path == self.path {
+            saw(&self.path, generation);
+        }
+        Ok(Lines {
+            tasks,
+            generation,
+            appendable: last.is_some() && end + 1 == lines.len() && content.ends_with('\n'),
+        })
+    }
+
+    /// The sigos as lines closed by a trailer of the generation with their checksum
+    fn encode_lines(&self, tasks: &[Value], generation: u64) -> Result<String, SigoError> {
+        let mut content = String::new();
+        for task in tasks {
+            content.push_str(&self.crypt.seal(serde_json::to_string(task)?)?);
+            content.push('\n');
+        }
+        let trailer = Trailer {
+            version: migrations::VERSION,
+            generation,
+            checksum: Some(checksum(content.as_bytes())),
+        };
+        content.push_str(&self.crypt.seal(serde_json::to_string(&trailer)?)?);
+        content.push('\n');
+        Ok(content)
+    }
+
+    fn append(&self, content: &str) -> Result<(), SigoError> {
+        let err = |e| SigoError::FileWriteErr(self.path.clone(), e);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(err)?;
+        file.write_all(content.as_bytes())
+            .and_then(|_| match self.durability {
+                Durability::Safe => file.sync_data(),
+                Durability::Fast => Ok(()),
+            })
+            .map_err(err)
+    }
+}
+
+impl Storage for JsonLines {
+    fn location(&self, name: &str) -> PathBuf {
+        self.collection_path(name)
+    }
+
+    fn read(&self, name: &str) -> Result<String, SigoError> {
+        let tasks = self.read_lines(&self.collection_path(name))?.tasks;
+        let tasks = match status_of(name) {
+            Some(status) => tasks
+                .into_iter()
+                .filter(|t| t["status"] == status)
+                .map(|mut t| {
+                    if let Some(task) = t.as_object_mut() {
+                        task.remove("status");
+                    }
+                    t
+                })
+                .collect(),
+            None => tasks,
+        };
+        Ok(serde_json::to_string(&tasks)?)
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<(), SigoError> {
+        self.write_all(&[(name, content.to_owned())])
+    }
+
+    /// Sigos only added to the end of collections in the tasks file are appended to it, while
+    /// other writes rewrite the files written together
+    fn write_all(&self, collections: &[(&str, String)]) -> Result<(), SigoError> {
+        let Lines {
+            mut tasks,
+            generation,
+            appendable,
+        } = self.read_lines(&self.path)?;
+        check(&self.path, generation)?;
+        let generation = generation + 1;
+        // Lines are written again when the key they are encrypted with changes
+        let mut appended = Some(Vec::new()).filter(|_| appendable && !self.crypt.rekeys());
+        let mut files = Vec::new();
+        for (name, content) in collections {
+            let path = self.collection_path(name);
+            let replacement = serde_json::from_str::<Vec<Value>>(content)
+                .map_err(|e| SigoError::ParseStrToTasksErr(path.clone(), e))?;
+            let Some(status) = status_of(name) else {
+                files.push((path, self.encode_lines(&replacement, 0)?.into_bytes()));
+                appended = None;
+                continue;
+            };
+            let replacement = replacement
+                .into_iter()
+                .map(|t| with_status(t, status))
+                .collect::<Vec<Value>>();
+            let before = tasks
+                .iter()
+                .filter(|t| t["status"] == status)
+                .cloned()
+                .collect::<Vec<Value>>();
+            match appended.as_mut() {
+                Some(appended) if replacement.starts_with(&before) => {
+                    appended.extend_from_slice(&replacement[before.len()..])
+                }
+                _ => appended = None,
+            }
+            tasks.retain(|t| t["status"] != status);
+            tasks.extend(replacement);
+        }
+        match appended {
+            Some(appended) => self.append(&self.encode_lines(&appended, generation)?)?,
+            None => {
+                let content = self.encode_lines(&tasks, generation)?;
+                files.push((self.path.clone(), content.into_bytes()));
+                Transaction::commit(&self.dir, &files, Vec::new(), self.durability)?;
+            }
+        }
+        written(&self.path, generation);
+        Ok(())
+    }
+
+    fn names(&self) -> Result<Vec<String>, SigoError> {
+        Transaction::recover(&self.dir, self.durability)?;
+        let entries =
+            fs::read_dir(&self.dir).map_err(|e| SigoError::FileReadErr(self.dir.clone(), e))?;
+        let mut names = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter_map(|name| {
+                let name = name.strip_suffix(&format!(".{}", LINES_EXTENSION))?;
+                Some(name.to_owned()).filter(|name| !name.contains('.'))
+            })
+            .filter(|name| *name != TASKS_FILE_NAME)
+            .chain(STATUSES.iter().map(|(name, _)| name.to_string()))
+            .collect::<Vec<String>>();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+}
+
+/// Sigos as rows keyed by their collection and position, with the id for lookups
+struct Sqlite {
+    path: PathBuf,
+    /// Encryption of the JSON of each sigo
+    crypt: Crypt,
+    durability: Durability,
+}
+
+impl Sqlite {
+    fn connect(&self) -> Result<Connection, SigoError> {
+        let err = |e| SigoError::DatabaseErr(self.path.clone(), e);
+        let connection = Connection::open(&self.path).map_err(err)?;
+        let synchronous = match self.durability {
+            Durability::Safe => "FULL",
+            Durability::Fast => "OFF",
+        };
+        connection
+            .pragma_update(None, "synchronous", synchronous)
+            .map_err(err)?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS collections (name TEXT PRIMARY KEY);
+                CREATE TABLE IF NOT EXISTS tasks (
+                    collection TEXT NOT NULL,
+                    position INTEGER NOT NULL,
+                    id INTEGER,
+                    json TEXT NOT NULL,
+                    PRIMARY KEY (collection, position)
+                );
+                CREATE INDEX IF NOT EXISTS tasks_id ON tasks (collection, id);
+                CREATE TABLE IF NOT EXISTS meta (generation INTEGER NOT NULL);
+                INSERT INTO meta (generation) SELECT 0 WHERE NOT EXISTS (SELECT 1 FROM meta);",
+            )
+            .map_err(err)?;
+        saw(&self.path, generation_of(&connection).map_err(err)?);
+        Ok(connection)
+    }
+}
+
+/// Number of transactions which wrote to the database
+fn generation_of(connection: &Connection) -> rusqlite::Result<u64> {
+    connection
+        .query_row("SELECT generation FROM meta", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .map(|generation| generation as u64)
+}
+
+impl Storage for Sqlite {
+    /// The database, which keeps every collection
+    fn location(&self, _name: &str) -> PathBuf {
+        self.path.clone()
+    }
+
+    fn parse_error(&self, name: &str, err: serde_json::Error) -> SigoError {
+        SigoError::ParseCollectionErr(self.path.clone(), name.to_owned(), err)
+    }
+
+    fn read(&self, name: &str) -> Result<String, SigoError> {
+        let err = |e| SigoError::DatabaseErr(self.path.clone(), e);
+        let connection = self.connect()?;
+        let mut statement = connection
+            .prepare("SELECT json FROM tasks WHERE collection = ?1 ORDER BY position")
+            .map_err(err)?;
+        let tasks = statement
+            .query_map([name], |row| row.get::<_, String>(0))
+            .map_err(err)?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(err)?
+            .into_iter()
+            .map(|json| self.crypt.open(&self.path, json))
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(format!("[{}]", tasks.join(",")))
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<(), SigoError> {
+        self.write_all(&[(name, content.to_owned())])
+    }
+
+    /// Replace the rows of the collections in one transaction, which counts up the generation
+    fn write_all(&self, collections: &[(&str, String)]) -> Result<(), SigoError> {
+        let err = |e| SigoError::DatabaseErr(self.path.clone(), e);
+        let mut connection = self.connect()?;
+        // Taking the write lock first, no one else can write between the check and the commit
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(err)?;
+        let generation = generation_of(&transaction).map_err(err)?;
+        check(&self.path, generation)?;
+        for (name, content) in collections {
+            let tasks = serde_json::from_str::<Vec<Value>>(content)
+                .map_err(|e| self.parse_error(name, e))?;
+            transaction
+                .execute(
+                    "INSERT OR IGNORE INTO collections (name) VALUES (?1)",
+                    [name],
+                )
+                .map_err(err)?;
+            transaction
+                .execute("DELETE FROM tasks WHERE collection = ?1", [name])
+                .map_err(err)?;
+            for (position, task) in (0_i64..).zip(&tasks) {
+                transaction
+                    .execute(
+                        "INSERT INTO tasks (collection, position, id, json) VALUES (?1, ?2, ?3, ?4)",
+                        params![
+                            name,
+                            position,
+                            task["id"].as_i64(),
+                            self.crypt.seal(task.to_string())?
+                        ],
+                    )
+                    .map_err(err)?;
+            }
+        }
+        transaction
+            .execute("UPDATE meta SET generation = generation + 1", [])
+            .map_err(err)?;
+        transaction.commit().map_err(err)?;
+        written(&self.path, generation + 1);
+        Ok(())
+    }
+
+    fn names(&self) -> Result<Vec<String>, SigoError> {
+        let err = |e| SigoError::DatabaseErr(self.path.clone(), e);
+        let connection = self.connect()?;
+        let mut statement = connection
+            .prepare("SELECT name FROM collections ORDER BY name")
+            .map_err(err)?;
+        let names = statement
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(err)?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(err)?;
+        Ok(names)
+    }
+
+    fn find_by_id(&self, name: &str, id: u32) -> Result<Option<Value>, SigoError> {
+        let err = |e| SigoError::DatabaseErr(self.path.clone(), e);
+        let json = self
+            .connect()?
+            .query_row(
+                "SELECT json FROM tasks WHERE collection = ?1 AND id = ?2",
+                params![name, id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(err)?;
+        json.map(|json| {
+            let json = self.crypt.open(&self.path, json)?;
+            serde_json::from_str(&json).map_err(|e| self.parse_error(name, e))
+        })
+        .transpose()
+    }
+
+    fn count(&self, name: &str) -> Result<usize, SigoError> {
+        let err = |e| SigoError::DatabaseErr(self.path.clone(), e);
+        self.connect()?
+            .query_row(
+                "SELECT COUNT(*) FROM tasks WHERE collection = ?1",
+                [name],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as usize)
+            .map_err(err)
+    }
+
+    /// Rows are parsed as they are fetched
+    fn for_each(
+        &self,
+        name: &str,
+        f: &mut dyn FnMut(Value) -> Result<(), SigoError>,
+    ) -> Result<(), SigoError> {
+        let err = |e| SigoError::DatabaseErr(self.path.clone(), e);
+        let connection = self.connect()?;
+        let mut statement = connection
+            .prepare("SELECT json FROM tasks WHERE collection = ?1 ORDER BY position")
+            .map_err(err)?;
+        let mut rows = statement.query([name]).map_err(err)?;
+        while let Some(row) = rows.next().map_err(err)? {
+            let json = self.crypt.open(&self.path, row.get(0).map_err(err)?)?;
+            f(serde_json::from_str(&json).map_err(|e| self.parse_error(name, e))?)?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// Collections of the memory backend, shared by every storage opened on the thread, so
+    /// tests running at once each have sigos of their own
+    static MEMORY: RefCell<BTreeMap<String, String>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+struct Memory;
+
+impl Storage for Memory {
+    fn location(&self, name: &str) -> PathBuf {
+        PathBuf::from("(memory)").join(name)
+    }
+
+    fn read(&self, name: &str) -> Result<String, SigoError> {
+        Ok(MEMORY
+            .with_borrow(|memory| memory.get(name).cloned())
+            .unwrap_or_else(|| "[]".to_owned()))
+    }
+
+    fn write(&self, name: &str, content: &str) -> Result<(), SigoError> {
+        MEMORY.with_borrow_mut(|memory| memory.insert(name.to_owned(), content.to_owned()));
+        Ok(())
+    }
+
+    fn names(&self) -> Result<Vec<String>, SigoError> {
+        let mut names = MEMORY.with_borrow(|memory| {
+            memory
+                .keys()
+                .cloned()
+                .chain(STATUSES.iter().map(|(name, _)| name.to_string()))
+                .collect::<Vec<String>>()
+        });
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+}
+
+/// Copy every collection to the other backend in a single write, replacing those it already has
+pub fn migrate(cfg: &MyConfig, to: Backend) -> Result<usize, SigoError> {
+    let from = open(cfg);
+    let names = from.names()?;
+    if !may_write()? {
+        return Ok(names.len());
+    }
+    let collections = names
+        .iter()
+        .map(|name| Ok((name.as_str(), from.read(name)?)))
+        .collect::<Result<Vec<(&str, String)>, SigoError>>()?;
+    open_backend(cfg, to).write_all(&collections)?;
+    Ok(names.len())
+}
@@ -1,93 +1,1210 @@
-use std::{collections::HashSet, io::Write, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
+    fmt,
+    str::FromStr,
+};
 
+use chrono::{DateTime, Datelike, Local, NaiveDate};
 use serde::{Deserialize, Serialize};
 use tabled::Tabled;
+use uuid::Uuid;
 
 use crate::config::MyConfig;
 use crate::error::SigoError;
-use crate::{utils, Priority};
+use crate::filter::Filter;
+use crate::{ids, storage, utils, Priority, Recurrence};
 
-use sigo_macro_derive::{FiledTask, IdAssignedTask};
+use sigo_macro_derive::{FiledTask, FilteredTask, IdAssignedTask};
 
-#[derive(Tabled, Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum Task {
     Ready(ReadyTask),
     Waiting(WaitingTask),
     Completed(CompletedTask),
+    Deleted(DeletedTask),
 }
 
-#[derive(Tabled, Serialize, Deserialize, Debug, Clone, FiledTask, IdAssignedTask)]
+#[derive(Tabled, Serialize, Deserialize, Debug, Clone, FiledTask, FilteredTask, IdAssignedTask)]
 pub struct ReadyTask {
     pub id: u32,
+    #[tabled(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uuid: Uuid,
     #[tabled(rename = "P")]
     pub priority: Priority,
-    #[tabled(display_with = "utils::display_option_vec_string")]
+    #[tabled(display_with("Self::display_description", self))]
     pub description: Option<Vec<String>>,
+    #[tabled(display_with = "utils::display_option_date")]
+    #[serde(default)]
+    pub due: Option<NaiveDate>,
+    #[tabled(rename = "sched", display_with = "utils::display_option_date")]
+    #[serde(default)]
+    pub scheduled: Option<NaiveDate>,
+    #[tabled(display_with = "utils::display_tags")]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[tabled(display_with = "utils::display_option_string")]
+    #[serde(default)]
+    pub project: Option<String>,
+    #[tabled(display_with = "utils::display_option_string")]
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[tabled(rename = "deps", display_with = "utils::display_ids")]
+    #[serde(default)]
+    pub depends: Vec<u32>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub parent_id: Option<u32>,
+    #[tabled(rename = "expires", display_with = "utils::display_option_date")]
+    #[serde(default)]
+    pub until: Option<NaiveDate>,
+    #[tabled(rename = "est", display_with = "utils::display_option_minutes")]
+    #[serde(default)]
+    pub estimate: Option<i64>,
+    #[tabled(rename = "pts", display_with = "utils::display_option_u32")]
+    #[serde(default)]
+    pub points: Option<u32>,
+    #[tabled(rename = "tracked", display_with = "utils::display_intervals")]
+    #[serde(default)]
+    pub intervals: Vec<Interval>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[tabled(rename = "age", display_with = "utils::display_age")]
+    #[serde(default = "Local::now")]
+    pub created_at: DateTime<Local>,
+    #[tabled(skip)]
+    #[serde(default = "Local::now")]
+    pub modified_at: DateTime<Local>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub template: Option<u32>,
+    /// Last time `sigo review` went through the sigo
+    #[tabled(skip)]
+    #[serde(default)]
+    pub reviewed_at: Option<DateTime<Local>>,
+    #[tabled(skip)]
+    #[serde(flatten)]
+    pub udas: BTreeMap<String, serde_json::Value>,
 }
 
-#[derive(Tabled, Serialize, Deserialize, Debug, Clone, FiledTask, IdAssignedTask)]
+#[derive(Tabled, Serialize, Deserialize, Debug, Clone, FiledTask, FilteredTask, IdAssignedTask)]
 pub struct WaitingTask {
     pub id: u32,
+    #[tabled(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uuid: Uuid,
     #[tabled(rename = "P")]
     pub priority: Priority,
-    #[tabled(display_with = "utils::display_option_vec_string")]
+    #[tabled(display_with("Self::display_description", self))]
     pub description: Option<Vec<String>>,
+    #[tabled(display_with = "utils::display_option_date")]
+    #[serde(default)]
+    pub due: Option<NaiveDate>,
+    #[tabled(rename = "sched", display_with = "utils::display_option_date")]
+    #[serde(default)]
+    pub scheduled: Option<NaiveDate>,
+    #[tabled(display_with = "utils::display_tags")]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[tabled(display_with = "utils::display_option_string")]
+    #[serde(default)]
+    pub project: Option<String>,
+    #[tabled(display_with = "utils::display_option_string")]
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[tabled(rename = "deps", display_with = "utils::display_ids")]
+    #[serde(default)]
+    pub depends: Vec<u32>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub parent_id: Option<u32>,
+    #[tabled(rename = "until", display_with = "utils::display_wait_until")]
+    #[serde(default)]
+    pub wait_until: Option<NaiveDate>,
+    #[tabled(display_with = "utils::display_option_string")]
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Unknown for sigos which started waiting before this was recorded
+    #[tabled(rename = "waited", display_with("Self::display_waited", self))]
+    #[serde(default)]
+    pub waiting_since: Option<DateTime<Local>>,
+    #[tabled(rename = "expires", display_with = "utils::display_option_date")]
+    #[serde(default)]
+    pub until: Option<NaiveDate>,
+    #[tabled(rename = "est", display_with = "utils::display_option_minutes")]
+    #[serde(default)]
+    pub estimate: Option<i64>,
+    #[tabled(rename = "pts", display_with = "utils::display_option_u32")]
+    #[serde(default)]
+    pub points: Option<u32>,
+    #[tabled(rename = "tracked", display_with = "utils::display_intervals")]
+    #[serde(default)]
+    pub intervals: Vec<Interval>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[tabled(rename = "age", display_with = "utils::display_age")]
+    #[serde(default = "Local::now")]
+    pub created_at: DateTime<Local>,
+    #[tabled(skip)]
+    #[serde(default = "Local::now")]
+    pub modified_at: DateTime<Local>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub template: Option<u32>,
+    /// Last time `sigo review` went through the sigo
+    #[tabled(skip)]
+    #[serde(default)]
+    pub reviewed_at: Option<DateTime<Local>>,
+    #[tabled(skip)]
+    #[serde(flatten)]
+    pub udas: BTreeMap<String, serde_json::Value>,
 }
 
-#[derive(Tabled, Serialize, Deserialize, Debug, Clone, FiledTask)]
+#[derive(Tabled, Serialize, Deserialize, Debug, Clone, FiledTask, FilteredTask)]
 pub struct CompletedTask {
+    #[tabled(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uuid: Uuid,
+    /// Id the sigo had while it was open, if any
+    #[tabled(skip)]
+    #[serde(default)]
+    pub id: Option<u32>,
+    #[tabled(rename = "P")]
+    #[serde(default)]
+    pub priority: Priority,
+    pub description: String,
+    #[tabled(display_with = "utils::display_tags")]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[tabled(display_with = "utils::display_option_string")]
+    #[serde(default)]
+    pub project: Option<String>,
+    #[tabled(display_with = "utils::display_option_string")]
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[tabled(rename = "est", display_with = "utils::display_option_minutes")]
+    #[serde(default)]
+    pub estimate: Option<i64>,
+    #[tabled(rename = "pts", display_with = "utils::display_option_u32")]
+    #[serde(default)]
+    pub points: Option<u32>,
+    #[tabled(rename = "tracked", display_with = "utils::display_intervals")]
+    #[serde(default)]
+    pub intervals: Vec<Interval>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[tabled(rename = "age", display_with = "utils::display_age")]
+    #[serde(default = "Local::now")]
+    pub created_at: DateTime<Local>,
+    #[tabled(skip)]
+    #[serde(default = "Local::now")]
+    pub modified_at: DateTime<Local>,
+    #[tabled(rename = "completed", display_with = "utils::display_option_datetime")]
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Local>>,
+    #[tabled(skip)]
+    #[serde(flatten)]
+    pub udas: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Tabled, Serialize, Deserialize, Debug, Clone, FiledTask, FilteredTask)]
+pub struct DeletedTask {
+    #[tabled(skip)]
+    #[serde(default = "Uuid::new_v4")]
+    pub uuid: Uuid,
+    #[tabled(rename = "P")]
+    #[serde(default)]
+    pub priority: Priority,
+    pub description: String,
+    #[tabled(display_with = "utils::display_tags")]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[tabled(display_with = "utils::display_option_string")]
+    #[serde(default)]
+    pub project: Option<String>,
+    #[tabled(display_with = "utils::display_option_string")]
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[tabled(rename = "expires", display_with = "utils::display_option_date")]
+    #[serde(default)]
+    pub until: Option<NaiveDate>,
+    #[tabled(rename = "est", display_with = "utils::display_option_minutes")]
+    #[serde(default)]
+    pub estimate: Option<i64>,
+    #[tabled(rename = "pts", display_with = "utils::display_option_u32")]
+    #[serde(default)]
+    pub points: Option<u32>,
+    #[tabled(rename = "tracked", display_with = "utils::display_intervals")]
+    #[serde(default)]
+    pub intervals: Vec<Interval>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[tabled(rename = "age", display_with = "utils::display_age")]
+    #[serde(default = "Local::now")]
+    pub created_at: DateTime<Local>,
+    #[tabled(skip)]
+    #[serde(default = "Local::now")]
+    pub modified_at: DateTime<Local>,
+    #[tabled(skip)]
+    #[serde(default)]
+    pub expired: bool,
+    #[tabled(skip)]
+    #[serde(flatten)]
+    pub udas: BTreeMap<String, serde_json::Value>,
+}
+
+/// Template of a recurring sigo, which generates a new ReadyTask when the previous one is completed
+#[derive(Serialize, Deserialize, Debug, Clone, FiledTask)]
+pub struct RecurringTask {
+    pub id: u32,
+    pub recur: Recurrence,
+    pub description: String,
+    pub priority: Priority,
+    /// Due date of the latest generated instance
+    pub due: NaiveDate,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default = "Local::now")]
+    pub modified_at: DateTime<Local>,
+    /// The latest instance was deleted keeping the series, so the next one is due the same date
+    #[serde(default)]
+    pub reissue: bool,
+}
+
+/// Time span spent on a sigo, open while the sigo is active
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Interval {
+    pub start: DateTime<Local>,
+    pub end: Option<DateTime<Local>>,
+}
+
+/// Changes given to `sigo modify`, where `None` leaves the attribute as it is
+#[derive(Debug, Default)]
+pub struct Modification {
+    pub description: Option<String>,
+    pub priority: Option<Priority>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    /// `Some(None)` clears the project
+    pub project: Option<Option<String>>,
+    /// `Some(None)` clears the assignee
+    pub assignee: Option<Option<String>>,
+    pub udas: BTreeMap<String, String>,
+    pub due: Option<Option<NaiveDate>>,
+    pub scheduled: Option<Option<NaiveDate>>,
+    pub until: Option<Option<NaiveDate>>,
+    pub estimate: Option<Option<i64>>,
+    pub points: Option<Option<u32>>,
+    pub annotations: Option<Vec<Annotation>>,
+    /// Record that `sigo review` went through the sigo
+    pub reviewed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub entry: DateTime<Local>,
     pub description: String,
 }
 
+/// Columns shared by sigos in every state, for reports mixing them
+impl Tabled for Task {
+    const LENGTH: usize = 10;
+
+    fn fields(&self) -> Vec<Cow<'_, str>> {
+        vec![
+            self.id()
+                .map(|id| id.to_string())
+                .unwrap_or_default()
+                .into(),
+            self.status().into(),
+            self.priority().to_string().into(),
+            self.description().into(),
+            utils::display_option_date(&self.due()).into(),
+            utils::display_option_date(&self.scheduled()).into(),
+            utils::display_tags(self.tags()).into(),
+            utils::display_option_string(self.project()).into(),
+            utils::display_option_string(self.assignee()).into(),
+            utils::display_age(&self.created_at()).into(),
+        ]
+    }
+
+    fn headers() -> Vec<Cow<'static, str>> {
+        [
+            "id",
+            "status",
+            "P",
+            "description",
+            "due",
+            "sched",
+            "tags",
+            "project",
+            "assignee",
+            "age",
+        ]
+        .into_iter()
+        .map(Cow::Borrowed)
+        .collect()
+    }
+}
+
+impl From<ReadyTask> for Task {
+    fn from(task: ReadyTask) -> Self {
+        Task::Ready(task)
+    }
+}
+
+impl From<WaitingTask> for Task {
+    fn from(task: WaitingTask) -> Self {
+        Task::Waiting(task)
+    }
+}
+
+impl From<CompletedTask> for Task {
+    fn from(task: CompletedTask) -> Self {
+        Task::Completed(task)
+    }
+}
+
+impl From<DeletedTask> for Task {
+    fn from(task: DeletedTask) -> Self {
+        Task::Deleted(task)
+    }
+}
+
+/// State of a sigo, whose sigos are kept in a collection of their own
+pub trait TaskState: Clone + Serialize + Into<Task> {
+    const COLLECTION: &'static str;
+
+    fn uuid(&self) -> Uuid;
+
+    fn stored(cfg: &MyConfig) -> Result<Vec<Self>, SigoError>;
+
+    /// Ids of the sigos which must be done first, which only open sigos have
+    fn depends_mut(&mut self) -> Option<&mut Vec<u32>> {
+        None
+    }
+}
+
+/// Implement `TaskState` for sigos read with `read_tasks`, keeping their dependencies in the
+/// field given
+macro_rules! task_state {
+    ($state:ty $(, $depends:ident)?) => {
+        impl TaskState for $state {
+            const COLLECTION: &'static str = <$state>::FILE_NAME;
+
+            fn uuid(&self) -> Uuid {
+                self.uuid
+            }
+
+            fn stored(cfg: &MyConfig) -> Result<Vec<Self>, SigoError> {
+                Self::read_tasks(cfg)
+            }
+
+            $(
+                fn depends_mut(&mut self) -> Option<&mut Vec<u32>> {
+                    Some(&mut self.$depends)
+                }
+            )?
+        }
+    };
+}
+
+task_state!(ReadyTask, depends);
+task_state!(WaitingTask, depends);
+task_state!(CompletedTask);
+task_state!(DeletedTask);
+
+/// Sigos of one state as read from their collection, changed here and then written together
+/// with the other collections a change touches
+pub struct TaskStore<S: TaskState> {
+    tasks: Vec<S>,
+}
+
+impl<S: TaskState> TaskStore<S> {
+    pub fn read(cfg: &MyConfig) -> Result<Self, SigoError> {
+        Ok(Self {
+            tasks: S::stored(cfg)?,
+        })
+    }
+
+    pub fn contains(&self, uuid: Uuid) -> bool {
+        self.tasks.iter().any(|t| t.uuid() == uuid)
+    }
+
+    /// Take the sigo out, returning whether it was there
+    pub fn remove(&mut self, uuid: Uuid) -> bool {
+        let len = self.tasks.len();
+        self.tasks.retain(|t| t.uuid() != uuid);
+        self.tasks.len() < len
+    }
+
+    pub fn push(&mut self, task: S) {
+        self.tasks.push(task);
+    }
+
+    /// Drop the id from the dependencies of the sigos, returning whether any depended on it
+    pub fn unblock(&mut self, id: u32) -> bool {
+        let mut changed = false;
+        for depends in self.tasks.iter_mut().filter_map(S::depends_mut) {
+            let len = depends.len();
+            depends.retain(|dep| *dep != id);
+            changed |= depends.len() < len;
+        }
+        changed
+    }
+
+    /// The collection with its contents to write
+    fn encoded(&self) -> Result<(&'static str, String), SigoError> {
+        Ok((S::COLLECTION, serde_json::to_string(&self.tasks)?))
+    }
+}
+
+/// Open sigos of a collection other than those a sigo moves between, if any depended on it
+fn unblocked<S: TaskState>(
+    cfg: &MyConfig,
+    id: u32,
+    moved_between: [&str; 2],
+) -> Result<Option<(&'static str, String)>, SigoError> {
+    if moved_between.contains(&S::COLLECTION) {
+        return Ok(None);
+    }
+    let mut store = TaskStore::<S>::read(cfg)?;
+    match store.unblock(id) {
+        true => Ok(Some(store.encoded()?)),
+        false => Ok(None),
+    }
+}
+
+/// Move the sigo `from` one state `to` another, also dropping it from the dependencies of the
+/// open sigos when `unblocking` its id, writing every collection changed at once so that it is
+/// never in neither or both of them
+pub fn transition<F: TaskState, T: TaskState>(
+    cfg: &MyConfig,
+    from: &F,
+    to: T,
+    unblocking: Option<u32>,
+) -> Result<T, SigoError> {
+    let uuid = from.uuid();
+    let mut from_store = TaskStore::<F>::read(cfg)?;
+    let mut to_store = TaskStore::<T>::read(cfg)?;
+    // Moved since it was read, so moving it again would duplicate it
+    if !from_store.remove(uuid) {
+        let arrived = to_store.contains(uuid);
+        return Err(match (from.clone().into(), to.into()) {
+            (from, Task::Completed(_)) if arrived => SigoError::AlreadyCompleted(from.name()),
+            (Task::Ready(from), _) => SigoError::TaskNotFound(from.id),
+            (Task::Waiting(from), _) => SigoError::TaskNotFound(from.id),
+            _ => SigoError::UuidNotFound(uuid.to_string()),
+        });
+    }
+    to_store.push(to.clone());
+    let mut collections = Vec::new();
+    if let Some(id) = unblocking {
+        from_store.unblock(id);
+        to_store.unblock(id);
+        let moved_between = [F::COLLECTION, T::COLLECTION];
+        collections.extend(unblocked::<ReadyTask>(cfg, id, moved_between)?);
+        collections.extend(unblocked::<WaitingTask>(cfg, id, moved_between)?);
+    }
+    collections.push(from_store.encoded()?);
+    collections.push(to_store.encoded()?);
+    storage::open(cfg).write_all(&collections)?;
+    Ok(to)
+}
+
+/// State of the open sigos, which are closed by completing or deleting them
+pub trait OpenState: TaskState {
+    fn id(&self) -> u32;
+
+    /// The sigo with the attributes it would have if it were ready
+    fn as_ready(&self) -> ReadyTask;
+
+    fn complete(&self, cfg: &MyConfig) -> Result<CompletedTask, SigoError> {
+        Task::ensure_no_open_children(cfg, self.id())?;
+        let completed = CompletedTask::completing(self.as_ready());
+        transition(cfg, self, completed, Some(self.id()))
+    }
+
+    fn delete(&self, cfg: &MyConfig) -> Result<DeletedTask, SigoError> {
+        Task::ensure_no_open_children(cfg, self.id())?;
+        let deleted = DeletedTask::discarding(self.as_ready(), false);
+        transition(cfg, self, deleted, Some(self.id()))
+    }
+
+    /// Delete the sigo because its until date has passed, leaving its children
+    fn expire(&self, cfg: &MyConfig) -> Result<DeletedTask, SigoError> {
+        let deleted = DeletedTask::discarding(self.as_ready(), true);
+        transition(cfg, self, deleted, Some(self.id()))
+    }
+}
+
+impl OpenState for ReadyTask {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn as_ready(&self) -> ReadyTask {
+        self.clone()
+    }
+}
+
+impl OpenState for WaitingTask {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Without the annotation of why it waited, added only when it is returned
+    fn as_ready(&self) -> ReadyTask {
+        ReadyTask {
+            annotations: self.annotations.clone(),
+            ..ReadyTask::from_waiting(self.clone())
+        }
+    }
+}
+
+/// Sigo given on the command line, either by its short id or a prefix of its uuid
+#[derive(Clone, Debug)]
+pub enum TaskRef {
+    Id(u32),
+    Uuid(String),
+}
+
+/// Shortest uuid prefix given without `uuid:`, so that words like `add` are not taken for one
+const MIN_UUID_PREFIX: usize = 8;
+
+impl FromStr for TaskRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(id) = s.parse::<u32>() {
+            return Ok(TaskRef::Id(id));
+        }
+        let (prefix, explicit) = match s.strip_prefix("uuid:") {
+            Some(prefix) => (prefix, true),
+            None => (s, false),
+        };
+        let is_uuid = ((explicit && !prefix.is_empty()) || prefix.len() >= MIN_UUID_PREFIX)
+            && prefix.chars().all(|c| c.is_ascii_hexdigit() || c == '-');
+        if is_uuid {
+            return Ok(TaskRef::Uuid(prefix.to_lowercase()));
+        }
+        Err(format!("'{}' is neither an id nor a uuid", s))
+    }
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskRef::Id(id) => write!(f, "{}", id),
+            TaskRef::Uuid(prefix) => write!(f, "{}", prefix),
+        }
+    }
+}
+
+/// Sigos given as comma separated ids, ranges like `7-9` and uuid prefixes
+#[derive(Clone, Debug)]
+pub struct TaskRefs(pub Vec<TaskRef>);
+
+impl FromStr for TaskRefs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut refs = Vec::new();
+        for part in s.split(',') {
+            // A uuid prefix with a dash has eight characters before it
+            let range = part
+                .split_once('-')
+                .filter(|(start, _)| start.len() < 8)
+                .and_then(|(start, end)| {
+                    Some((start.parse::<u32>().ok()?, end.parse::<u32>().ok()?))
+                });
+            match range {
+                Some((start, end)) if start <= end => refs.extend((start..=end).map(TaskRef::Id)),
+                Some(_) => return Err(format!("'{}' is an empty range", part)),
+                None => refs.push(part.parse()?),
+            }
+        }
+        Ok(TaskRefs(refs))
+    }
+}
+
 impl Task {
+    /// Sigos in every state, without the archived ones
+    pub fn read_all(cfg: &MyConfig) -> Result<Vec<Task>, SigoError> {
+        Ok(ReadyTask::read_tasks(cfg)?
+            .into_iter()
+            .map(Task::Ready)
+            .chain(WaitingTask::read_tasks(cfg)?.into_iter().map(Task::Waiting))
+            .chain(
+                CompletedTask::read_tasks(cfg)?
+                    .into_iter()
+                    .map(Task::Completed),
+            )
+            .chain(DeletedTask::read_tasks(cfg)?.into_iter().map(Task::Deleted))
+            .collect())
+    }
+
     pub fn get_by_id(cfg: &MyConfig, id: u32) -> Result<Task, SigoError> {
-        if let Ok(task) = ReadyTask::get_by_id(cfg, id) {
-            return Ok(Task::Ready(task));
+        match ReadyTask::get_by_id(cfg, id) {
+            Err(SigoError::TaskNotFound(_)) => WaitingTask::get_by_id(cfg, id).map(Task::Waiting),
+            found => found.map(Task::Ready),
+        }
+    }
+
+    /// Whether no open sigo has the id, failing if the sigos cannot be read
+    pub fn is_id_free(cfg: &MyConfig, id: u32) -> Result<bool, SigoError> {
+        match Task::get_by_id(cfg, id) {
+            Ok(_) => Ok(false),
+            Err(SigoError::TaskNotFound(_)) => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Find the sigo referred to by an id or a uuid prefix. Completed sigos can be found only by uuid.
+    pub fn get_by_ref(cfg: &MyConfig, task_ref: &TaskRef) -> Result<Task, SigoError> {
+        match task_ref {
+            TaskRef::Id(id) => Task::get_by_id(cfg, *id),
+            TaskRef::Uuid(prefix) => {
+                let is_match = |uuid: &Uuid| uuid.to_string().starts_with(prefix.as_str());
+                let mut matched = ReadyTask::read_tasks(cfg)?
+                    .into_iter()
+                    .filter(|t| is_match(&t.uuid))
+                    .map(Task::Ready)
+                    .chain(
+                        WaitingTask::read_tasks(cfg)?
+                            .into_iter()
+                            .filter(|t| is_match(&t.uuid))
+                            .map(Task::Waiting),
+                    )
+                    .chain(
+                        CompletedTask::read_tasks(cfg)?
+                            .into_iter()
+                            .filter(|t| is_match(&t.uuid))
+                            .map(Task::Completed),
+                    )
+                    .chain(
+                        DeletedTask::read_tasks(cfg)?
+                            .into_iter()
+                            .filter(|t| is_match(&t.uuid))
+                            .map(Task::Deleted),
+                    )
+                    .collect::<Vec<Task>>();
+                match matched.len() {
+                    1 => Ok(matched.remove(0)),
+                    0 => Err(SigoError::UuidNotFound(prefix.to_owned())),
+                    _ => Err(SigoError::AmbiguousUuid(prefix.to_owned())),
+                }
+            }
+        }
+    }
+
+    /// Find the id of the ready or waiting sigo referred to by an id or a uuid prefix
+    pub fn resolve_id(cfg: &MyConfig, task_ref: &TaskRef) -> Result<u32, SigoError> {
+        match Task::get_by_ref(cfg, task_ref)? {
+            Task::Ready(task) => Ok(task.id),
+            Task::Waiting(task) => Ok(task.id),
+            task => Err(SigoError::NotOpen(task.uuid())),
+        }
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        match self {
+            Task::Ready(task) => task.uuid,
+            Task::Waiting(task) => task.uuid,
+            Task::Completed(task) => task.uuid,
+            Task::Deleted(task) => task.uuid,
+        }
+    }
+
+    pub fn priority(&self) -> Priority {
+        match self {
+            Task::Ready(task) => task.priority,
+            Task::Waiting(task) => task.priority,
+            Task::Completed(task) => task.priority,
+            Task::Deleted(task) => task.priority,
+        }
+    }
+
+    /// Short id of an open sigo
+    pub fn id(&self) -> Option<u32> {
+        match self {
+            Task::Ready(task) => Some(task.id),
+            Task::Waiting(task) => Some(task.id),
+            _ => None,
+        }
+    }
+
+    /// Urgency of an open sigo, zero for the others
+    pub fn urgency(&self, cfg: &MyConfig) -> f64 {
+        match self {
+            Task::Ready(task) => task.urgency(cfg),
+            Task::Waiting(task) => task.urgency(cfg),
+            _ => 0.0,
+        }
+    }
+
+    /// Values of the columns after the fixed ones, like those of ready and waiting sigos
+    pub fn extra_fields(&self, cfg: &MyConfig) -> Vec<String> {
+        match self {
+            Task::Ready(task) => task.extra_fields(cfg),
+            Task::Waiting(task) => task.extra_fields(cfg),
+            task => {
+                let udas = utils::uda_rows(task.udas())
+                    .into_iter()
+                    .collect::<BTreeMap<String, String>>();
+                std::iter::once(String::new())
+                    .chain(
+                        cfg.udas
+                            .iter()
+                            .map(|name| udas.get(name).cloned().unwrap_or_default()),
+                    )
+                    .collect()
+            }
+        }
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self {
+            Task::Ready(_) => "ready",
+            Task::Waiting(_) => "waiting",
+            Task::Completed(_) => "completed",
+            Task::Deleted(_) => "deleted",
+        }
+    }
+
+    pub fn tags(&self) -> &[String] {
+        match self {
+            Task::Ready(task) => &task.tags,
+            Task::Waiting(task) => &task.tags,
+            Task::Completed(task) => &task.tags,
+            Task::Deleted(task) => &task.tags,
+        }
+    }
+
+    pub fn project(&self) -> &Option<String> {
+        match self {
+            Task::Ready(task) => &task.project,
+            Task::Waiting(task) => &task.project,
+            Task::Completed(task) => &task.project,
+            Task::Deleted(task) => &task.project,
+        }
+    }
+
+    pub fn assignee(&self) -> &Option<String> {
+        match self {
+            Task::Ready(task) => &task.assignee,
+            Task::Waiting(task) => &task.assignee,
+            Task::Completed(task) => &task.assignee,
+            Task::Deleted(task) => &task.assignee,
+        }
+    }
+
+    pub fn udas(&self) -> &BTreeMap<String, serde_json::Value> {
+        match self {
+            Task::Ready(task) => &task.udas,
+            Task::Waiting(task) => &task.udas,
+            Task::Completed(task) => &task.udas,
+            Task::Deleted(task) => &task.udas,
+        }
+    }
+
+    pub fn intervals(&self) -> &[Interval] {
+        match self {
+            Task::Ready(task) => &task.intervals,
+            Task::Waiting(task) => &task.intervals,
+            Task::Completed(task) => &task.intervals,
+            Task::Deleted(task) => &task.intervals,
+        }
+    }
+
+    /// Whether time is being tracked on an open sigo
+    pub fn is_active(&self) -> bool {
+        match self {
+            Task::Ready(task) => task.is_active(),
+            Task::Waiting(task) => task.is_active(),
+            _ => false,
+        }
+    }
+
+    /// Whether the sigo is open and depends on others
+    pub fn is_blocked(&self) -> bool {
+        match self {
+            Task::Ready(task) => task.is_blocked(),
+            Task::Waiting(task) => task.is_blocked(),
+            _ => false,
+        }
+    }
+
+    /// Due date of an open sigo
+    pub fn due(&self) -> Option<NaiveDate> {
+        match self {
+            Task::Ready(task) => task.due,
+            Task::Waiting(task) => task.due,
+            _ => None,
+        }
+    }
+
+    /// Scheduled date of an open sigo
+    pub fn scheduled(&self) -> Option<NaiveDate> {
+        match self {
+            Task::Ready(task) => task.scheduled,
+            Task::Waiting(task) => task.scheduled,
+            _ => None,
+        }
+    }
+
+    /// Day a completed sigo was done
+    pub fn completed_on(&self) -> Option<NaiveDate> {
+        match self {
+            Task::Completed(task) => Some(task.done_at().date_naive()),
+            _ => None,
+        }
+    }
+
+    pub fn created_at(&self) -> DateTime<Local> {
+        match self {
+            Task::Ready(task) => task.created_at,
+            Task::Waiting(task) => task.created_at,
+            Task::Completed(task) => task.created_at,
+            Task::Deleted(task) => task.created_at,
         }
-        if let Ok(task) = WaitingTask::get_by_id(cfg, id) {
-            return Ok(Task::Waiting(task));
+    }
+
+    /// Short id of ready and waiting sigos, the uuid of the others
+    pub fn name(&self) -> String {
+        match self {
+            Task::Ready(task) => task.id.to_string(),
+            Task::Waiting(task) => task.id.to_string(),
+            Task::Completed(task) => task.uuid.to_string(),
+            Task::Deleted(task) => task.uuid.to_string(),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Task::Ready(task) => task.get_main_description(),
+            Task::Waiting(task) => task.get_main_description(),
+            Task::Completed(task) => task.description.clone(),
+            Task::Deleted(task) => task.description.clone(),
+        }
+    }
+
+    /// Replace the main description, whatever the state of the sigo
+    pub fn set_description(&self, cfg: &MyConfig, description: &str) -> Result<Task, SigoError> {
+        match self {
+            Task::Deleted(task) => Ok(Task::Deleted(task.set_description(cfg, description)?)),
+            task => task.modify(
+                cfg,
+                &Modification {
+                    description: Some(description.to_owned()),
+                    ..Default::default()
+                },
+            ),
+        }
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        match self {
+            Task::Ready(task) => &task.annotations,
+            Task::Waiting(task) => &task.annotations,
+            Task::Completed(task) => &task.annotations,
+            Task::Deleted(task) => &task.annotations,
+        }
+    }
+
+    /// New ready sigo with the attributes of this one, but not its history
+    pub fn duplicate(&self, cfg: &MyConfig) -> Result<ReadyTask, SigoError> {
+        let new_task = match self {
+            Task::Ready(task) => ReadyTask {
+                due: task.due,
+                scheduled: task.scheduled,
+                until: task.until,
+                tags: task.tags.clone(),
+                project: task.project.clone(),
+                assignee: task.assignee.clone(),
+                depends: task.depends.clone(),
+                parent_id: task.parent_id,
+                estimate: task.estimate,
+                points: task.points,
+                attachments: task.attachments.clone(),
+                udas: task.udas.clone(),
+                ..ReadyTask::new(cfg, &task.get_main_description(), task.priority)?
+            },
+            Task::Waiting(task) => ReadyTask {
+                due: task.due,
+                scheduled: task.scheduled,
+                until: task.until,
+                tags: task.tags.clone(),
+                project: task.project.clone(),
+                assignee: task.assignee.clone(),
+                depends: task.depends.clone(),
+                parent_id: task.parent_id,
+                estimate: task.estimate,
+                points: task.points,
+                attachments: task.attachments.clone(),
+                udas: task.udas.clone(),
+                ..ReadyTask::new(cfg, &task.get_main_description(), task.priority)?
+            },
+            Task::Completed(task) => ReadyTask {
+                tags: task.tags.clone(),
+                project: task.project.clone(),
+                assignee: task.assignee.clone(),
+                estimate: task.estimate,
+                points: task.points,
+                attachments: task.attachments.clone(),
+                udas: task.udas.clone(),
+                ..ReadyTask::new(cfg, &task.description, task.priority)?
+            },
+            Task::Deleted(task) => ReadyTask {
+                tags: task.tags.clone(),
+                project: task.project.clone(),
+                assignee: task.assignee.clone(),
+                estimate: task.estimate,
+                points: task.points,
+                attachments: task.attachments.clone(),
+                udas: task.udas.clone(),
+                ..ReadyTask::new(cfg, &task.description, task.priority)?
+            },
+        };
+        Ok(new_task)
+    }
+
+    /// Modify a ready, waiting or completed sigo
+    pub fn modify(&self, cfg: &MyConfig, modification: &Modification) -> Result<Task, SigoError> {
+        match self {
+            Task::Ready(task) => Ok(Task::Ready(task.modify(cfg, modification)?)),
+            Task::Waiting(task) => Ok(Task::Waiting(task.modify(cfg, modification)?)),
+            Task::Completed(task) => Ok(Task::Completed(task.modify(cfg, modification)?)),
+            Task::Deleted(task) => Err(SigoError::NotOpen(task.uuid)),
         }
-        Err(SigoError::TaskNotFound(id))
     }
 
-    fn issue_task_id(cfg: &MyConfig) -> Result<u32, SigoError> {
-        let ready_tasks = ReadyTask::read_tasks(cfg)?;
-        let waiting_tasks = WaitingTask::read_tasks(cfg)?;
-        let mut using_ids = HashSet::new();
-        for task in ready_tasks.iter() {
-            using_ids.insert(task.id);
+    /// Move ready and waiting sigos whose until date has passed to deleted
+    pub fn expire_all(cfg: &MyConfig) -> Result<Vec<DeletedTask>, SigoError> {
+        let mut expired = Vec::new();
+        for task in WaitingTask::read_tasks(cfg)?
+            .iter()
+            .filter(|t| t.is_expired())
+        {
+            expired.push(task.expire(cfg)?);
         }
-        for task in waiting_tasks.iter() {
-            using_ids.insert(task.id);
+        for task in ReadyTask::read_tasks(cfg)?
+            .iter()
+            .filter(|t| t.is_expired())
+        {
+            expired.push(task.expire(cfg)?);
+        }
+        Ok(expired)
+    }
+
+    /// The ready sigo being worked on, if any
+    pub fn active(cfg: &MyConfig) -> Result<Option<ReadyTask>, SigoError> {
+        Ok(ReadyTask::read_tasks(cfg)?
+            .into_iter()
+            .find(|t| t.is_active()))
+    }
+
+    /// Fail if any ready or waiting sigo is still a child of the sigo
+    pub fn ensure_no_open_children(cfg: &MyConfig, id: u32) -> Result<(), SigoError> {
+        let has_open_children = ReadyTask::read_tasks(cfg)?
+            .iter()
+            .any(|t| t.parent_id == Some(id))
+            || WaitingTask::read_tasks(cfg)?
+                .iter()
+                .any(|t| t.parent_id == Some(id));
+        if has_open_children {
+            return Err(SigoError::OpenChildren(id));
+        }
+        Ok(())
+    }
+
+    /// The smallest id not in use, or with `monotonic_ids` one above every id issued before
+    pub fn issue_task_id(cfg: &MyConfig) -> Result<u32, SigoError> {
+        let using_ids = storage::open(cfg).ids(&[ReadyTask::FILE_NAME, WaitingTask::FILE_NAME])?;
+        if cfg.monotonic_ids {
+            // Ids read from the files may be as large as any id can be
+            let above_using = match using_ids.iter().max() {
+                Some(max) => max.checked_add(1).ok_or(SigoError::NoIdLeft)?,
+                None => 1,
+            };
+            let id = ids::read_next(cfg)?.max(above_using);
+            ids::write_next(cfg, id.checked_add(1).ok_or(SigoError::NoIdLeft)?)?;
+            return Ok(id);
+        }
+        (1..=u32::MAX)
+            .find(|x| !using_ids.contains(x))
+            .ok_or(SigoError::NoIdLeft)
+    }
+}
+
+impl Interval {
+    pub fn minutes(&self) -> i64 {
+        (self.end.unwrap_or_else(Local::now) - self.start).num_minutes()
+    }
+}
+
+impl Modification {
+    pub fn apply_tags(&self, tags: &mut Vec<String>) {
+        tags.retain(|t| !self.remove_tags.contains(t));
+        for tag in self.add_tags.iter() {
+            if !tags.contains(tag) {
+                tags.push(tag.to_owned());
+            }
+        }
+    }
+
+    pub fn apply_udas(&self, udas: &mut BTreeMap<String, serde_json::Value>) {
+        for (key, value) in self.udas.iter() {
+            if value.is_empty() {
+                udas.remove(key);
+            } else {
+                udas.insert(key.to_owned(), serde_json::Value::String(value.to_owned()));
+            }
+        }
+    }
+}
+
+impl Annotation {
+    pub fn new(description: &str) -> Self {
+        Self {
+            entry: Local::now(),
+            description: description.to_owned(),
         }
-        let max_id: u32 = (using_ids.len() + 1).try_into().unwrap();
-        Ok((1u32..=max_id).find(|x| !using_ids.contains(x)).unwrap())
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.entry.format("%Y-%m-%d"), self.description)
     }
 }
 
 impl ReadyTask {
-    const FILE_NAME: &'static str = "ready_tasks";
+    pub const FILE_NAME: &'static str = "ready_tasks";
 
     pub fn new(cfg: &MyConfig, description: &str, priority: Priority) -> Result<Self, SigoError> {
-        let id = Task::issue_task_id(cfg)?;
-        Ok(Self {
+        Ok(Self::with_id(
+            Task::issue_task_id(cfg)?,
+            description,
+            priority,
+        ))
+    }
+
+    /// New sigo with an id already issued
+    fn with_id(id: u32, description: &str, priority: Priority) -> Self {
+        Self {
             id,
+            uuid: Uuid::new_v4(),
             description: Some(vec![description.to_owned()]),
             priority,
-        })
+            due: None,
+            scheduled: None,
+            until: None,
+            tags: Vec::new(),
+            project: None,
+            assignee: None,
+            depends: Vec::new(),
+            parent_id: None,
+            estimate: None,
+            points: None,
+            intervals: Vec::new(),
+            attachments: Vec::new(),
+            annotations: Vec::new(),
+            created_at: Local::now(),
+            modified_at: Local::now(),
+            template: None,
+            reviewed_at: None,
+            udas: BTreeMap::new(),
+        }
     }
 
     fn from_waiting(waiting_task: WaitingTask) -> Self {
+        let mut annotations = waiting_task.annotations;
+        if let Some(reason) = waiting_task.reason {
+            annotations.push(Annotation::new(&format!("waited: {}", reason)));
+        }
         ReadyTask {
             id: waiting_task.id,
+            uuid: waiting_task.uuid,
             description: Some(waiting_task.description.unwrap_or_default()),
             priority: waiting_task.priority,
+            due: waiting_task.due,
+            scheduled: waiting_task.scheduled,
+            until: waiting_task.until,
+            tags: waiting_task.tags,
+            project: waiting_task.project,
+            assignee: waiting_task.assignee,
+            depends: waiting_task.depends,
+            parent_id: waiting_task.parent_id,
+            estimate: waiting_task.estimate,
+            points: waiting_task.points,
+            intervals: waiting_task.intervals,
+            attachments: waiting_task.attachments,
+            annotations,
+            created_at: waiting_task.created_at,
+            modified_at: waiting_task.modified_at,
+            template: waiting_task.template,
+            reviewed_at: waiting_task.reviewed_at,
+            udas: waiting_task.udas,
         }
     }
 
-    pub fn wait(self, cfg: &MyConfig, text: &Option<String>) -> Result<WaitingTask, SigoError> {
-        ReadyTask::delete_by_id(cfg, self.id)?;
-        let task = WaitingTask::add_task(cfg, WaitingTask::from_ready(self))?;
+    /// Start working on the sigo, keeping only one sigo active at a time
+    pub fn start(&self, cfg: &MyConfig) -> Result<Self, SigoError> {
+        if let Some(active) = Task::active(cfg)? {
+            return Err(SigoError::AlreadyActive(active.id));
+        }
+        let mut task = self.clone();
+        task.intervals.push(Interval {
+            start: Local::now(),
+            end: None,
+        });
+        task.update(cfg)?;
+        Ok(task)
+    }
+
+    pub fn stop(&self, cfg: &MyConfig) -> Result<Self, SigoError> {
+        let task = Self {
+            intervals: utils::stop_intervals(self.intervals.clone()),
+            ..self.clone()
+        };
+        task.update(cfg)?;
+        Ok(task)
+    }
+
+    pub fn wait(
+        self,
+        cfg: &MyConfig,
+        text: &Option<String>,
+        until: Option<NaiveDate>,
+        reason: Option<String>,
+    ) -> Result<WaitingTask, SigoError> {
+        let task = WaitingTask {
+            wait_until: until,
+            reason,
+            modified_at: Local::now(),
+            ..WaitingTask::from_ready(self.clone())
+        };
+        let task = transition(cfg, &self, task, None)?;
         if let Some(text) = text {
             task.annotate(cfg, text)?;
         }
@@ -95,25 +1212,473 @@ impl ReadyTask {
     }
 }
 impl WaitingTask {
-    const FILE_NAME: &'static str = "waiting_tasks";
+    /// When the sigo started waiting, or was last modified if unknown
+    pub fn waiting_since(&self) -> DateTime<Local> {
+        self.waiting_since.unwrap_or(self.modified_at)
+    }
+
+    fn display_waited(&self) -> String {
+        utils::display_age(&self.waiting_since())
+    }
+
+    pub const FILE_NAME: &'static str = "waiting_tasks";
+
+    /// Keep waiting until another date
+    pub fn snooze(&self, cfg: &MyConfig, until: NaiveDate) -> Result<Self, SigoError> {
+        let task = Self {
+            wait_until: Some(until),
+            ..self.clone()
+        };
+        task.update(cfg)?;
+        Ok(task)
+    }
 
     fn from_ready(ready_task: ReadyTask) -> Self {
         Self {
             id: ready_task.id,
+            uuid: ready_task.uuid,
             description: Some(ready_task.description.unwrap_or_default()),
             priority: ready_task.priority,
+            due: ready_task.due,
+            scheduled: ready_task.scheduled,
+            until: ready_task.until,
+            tags: ready_task.tags,
+            project: ready_task.project,
+            assignee: ready_task.assignee,
+            depends: ready_task.depends,
+            parent_id: ready_task.parent_id,
+            wait_until: None,
+            reason: None,
+            waiting_since: Some(Local::now()),
+            estimate: ready_task.estimate,
+            points: ready_task.points,
+            intervals: utils::stop_intervals(ready_task.intervals),
+            attachments: ready_task.attachments,
+            annotations: ready_task.annotations,
+            created_at: ready_task.created_at,
+            modified_at: ready_task.modified_at,
+            template: ready_task.template,
+            reviewed_at: ready_task.reviewed_at,
+            udas: ready_task.udas,
         }
     }
 
     pub fn back(self, cfg: &MyConfig, text: &Option<String>) -> Result<ReadyTask, SigoError> {
-        WaitingTask::delete_by_id(cfg, self.id)?;
-        let task = ReadyTask::add_task(cfg, ReadyTask::from_waiting(self))?;
+        let task = ReadyTask {
+            modified_at: Local::now(),
+            ..ReadyTask::from_waiting(self.clone())
+        };
+        let task = transition(cfg, &self, task, None)?;
         if let Some(text) = text {
             task.annotate(cfg, text)?;
         }
         Ok(task)
     }
+
+    /// Return waiting sigos whose wait-until date has come back to ready
+    pub fn wake_expired(cfg: &MyConfig) -> Result<Vec<ReadyTask>, SigoError> {
+        let today = Local::now().date_naive();
+        WaitingTask::read_tasks(cfg)?
+            .into_iter()
+            .filter(|t| t.wait_until.is_some_and(|until| until <= today))
+            .map(|t| t.back(cfg, &None))
+            .collect()
+    }
+}
+impl RecurringTask {
+    pub const FILE_NAME: &'static str = "recurring_tasks";
+
+    pub fn new(cfg: &MyConfig, task: &ReadyTask, recur: Recurrence) -> Result<Self, SigoError> {
+        let due = task.due.ok_or(SigoError::RecurWithoutDue)?;
+        let id = Self::read_tasks(cfg)?
+            .iter()
+            .map(|t| t.id)
+            .max()
+            .unwrap_or_default()
+            + 1;
+        Ok(Self {
+            id,
+            recur,
+            description: task.get_main_description(),
+            priority: task.priority,
+            due,
+            tags: task.tags.clone(),
+            project: task.project.clone(),
+            modified_at: Local::now(),
+            reissue: false,
+        })
+    }
+
+    /// Stop generating instances of the template, returning whether there was one
+    pub fn end(cfg: &MyConfig, id: u32) -> Result<bool, SigoError> {
+        let mut templates = Self::read_tasks(cfg)?;
+        let len = templates.len();
+        templates.retain(|t| t.id != id);
+        let ended = templates.len() < len;
+        Self::write_tasks(cfg, templates)?;
+        Ok(ended)
+    }
+
+    /// Issue the instance deleted again the next time the template is expanded
+    pub fn keep_period(cfg: &MyConfig, id: u32) -> Result<(), SigoError> {
+        let mut templates = Self::read_tasks(cfg)?;
+        for template in templates.iter_mut().filter(|t| t.id == id) {
+            template.reissue = true;
+            template.modified_at = Local::now();
+        }
+        Self::write_tasks(cfg, templates)
+    }
+
+    /// Generate the next instance of every template whose previous instance is no longer open
+    pub fn expand(cfg: &MyConfig) -> Result<Vec<ReadyTask>, SigoError> {
+        let mut open_templates = HashSet::new();
+        for task in ReadyTask::read_tasks(cfg)? {
+            open_templates.extend(task.template);
+        }
+        for task in WaitingTask::read_tasks(cfg)? {
+            open_templates.extend(task.template);
+        }
+
+        let mut created = Vec::new();
+        let mut templates = Self::read_tasks(cfg)?;
+        for template in templates.iter_mut() {
+            if open_templates.contains(&template.id) {
+                continue;
+            }
+            if !template.reissue {
+                template.due = template
+                    .recur
+                    .next(template.due)
+                    .ok_or(SigoError::RecurPastLastDate(template.id))?;
+            }
+            template.reissue = false;
+            let task = ReadyTask {
+                due: Some(template.due),
+                tags: template.tags.clone(),
+                project: template.project.clone(),
+                template: Some(template.id),
+                ..ReadyTask::new(cfg, &template.description, template.priority)?
+            };
+            created.push(ReadyTask::add_task(cfg, task)?);
+        }
+        Self::write_tasks(cfg, templates)?;
+        Ok(created)
+    }
 }
+
 impl CompletedTask {
-    const FILE_NAME: &'static str = "completed_tasks";
+    pub const FILE_NAME: &'static str = "completed_tasks";
+    /// Yearly archive files are named by this prefix and the year the sigos were done in
+    const ARCHIVE_PREFIX: &'static str = "completed_";
+
+    /// When the sigo was done, or last modified if unknown
+    pub fn done_at(&self) -> DateTime<Local> {
+        self.completed_at.unwrap_or(self.modified_at)
+    }
+
+    /// Split into sigos done before `before` except the `keep_last` most recent ones, and the rest
+    pub fn partition_old(
+        tasks: Vec<Self>,
+        before: Option<NaiveDate>,
+        keep_last: Option<usize>,
+    ) -> (Vec<Self>, Vec<Self>) {
+        let mut recent = tasks
+            .iter()
+            .map(|t| (t.done_at(), t.uuid))
+            .collect::<Vec<_>>();
+        recent.sort_by(|a, b| b.cmp(a));
+        let kept = recent
+            .into_iter()
+            .take(keep_last.unwrap_or(0))
+            .map(|(_, uuid)| uuid)
+            .collect::<HashSet<Uuid>>();
+        tasks.into_iter().partition(|t| {
+            !kept.contains(&t.uuid) && before.is_none_or(|b| t.done_at().date_naive() < b)
+        })
+    }
+
+    /// Collection of the sigos completed in the year once archived
+    pub fn archive_name(year: i32) -> String {
+        format!("{}{}", Self::ARCHIVE_PREFIX, year)
+    }
+
+    /// Names of the yearly archives, oldest first
+    pub fn archive_file_names(cfg: &MyConfig) -> Result<Vec<String>, SigoError> {
+        let mut names = storage::open(cfg).names()?;
+        names.retain(|name| {
+            name.strip_prefix(Self::ARCHIVE_PREFIX)
+                .is_some_and(|year| !year.is_empty() && year.chars().all(|c| c.is_ascii_digit()))
+        });
+        Ok(names)
+    }
+
+    /// Completed sigos, with those in the yearly archive files if `archived`
+    pub fn read_tasks_with_archive(cfg: &MyConfig, archived: bool) -> Result<Vec<Self>, SigoError> {
+        Self::read_matching(cfg, archived, |_| true)
+    }
+
+    /// Completed sigos kept by `keep`, with those in the yearly archive files if `archived`,
+    /// each checked as it is read so those not kept are never held together
+    pub fn read_matching(
+        cfg: &MyConfig,
+        archived: bool,
+        keep: impl Fn(&Self) -> bool,
+    ) -> Result<Vec<Self>, SigoError> {
+        let storage = storage::open(cfg);
+        let mut names = vec![Self::FILE_NAME.to_owned()];
+        if archived {
+            names.extend(Self::archive_file_names(cfg)?);
+        }
+        let mut tasks = Vec::new();
+        for name in &names {
+            storage.for_each(name, &mut |value| {
+                let task = serde_json::from_value::<Self>(value)
+                    .map_err(|e| storage.parse_error(name, e))?;
+                if keep(&task) {
+                    tasks.push(task);
+                }
+                Ok(())
+            })?;
+        }
+        Ok(tasks)
+    }
+
+    /// Append to the archive file of the year each sigo was done in
+    pub fn archive(cfg: &MyConfig, tasks: Vec<Self>) -> Result<(), SigoError> {
+        let mut by_year = BTreeMap::<i32, Vec<Self>>::new();
+        for task in tasks {
+            by_year.entry(task.done_at().year()).or_default().push(task);
+        }
+        for (year, tasks) in by_year {
+            let name = Self::archive_name(year);
+            let mut archived = Self::read_tasks_from(cfg, &name)?;
+            archived.extend(tasks);
+            Self::write_tasks_to(cfg, &name, archived)?;
+        }
+        Ok(())
+    }
+
+    /// Move the sigo back to ready, reissuing its id if another sigo took it
+    pub fn restore(&self, cfg: &MyConfig) -> Result<ReadyTask, SigoError> {
+        let id = match self.id {
+            Some(id) if Task::is_id_free(cfg, id)? => id,
+            _ => Task::issue_task_id(cfg)?,
+        };
+        let task = ReadyTask {
+            uuid: self.uuid,
+            tags: self.tags.clone(),
+            project: self.project.clone(),
+            assignee: self.assignee.clone(),
+            estimate: self.estimate,
+            points: self.points,
+            intervals: self.intervals.clone(),
+            attachments: self.attachments.clone(),
+            annotations: self.annotations.clone(),
+            created_at: self.created_at,
+            udas: self.udas.clone(),
+            ..ReadyTask::with_id(id, &self.description, self.priority)
+        };
+        transition(cfg, self, task, None)
+    }
+
+    /// Sigo done just now without ever being ready
+    pub fn new(description: &str, priority: Priority) -> Self {
+        Self {
+            uuid: Uuid::new_v4(),
+            id: None,
+            priority,
+            description: description.to_owned(),
+            tags: Vec::new(),
+            project: None,
+            assignee: None,
+            estimate: None,
+            points: None,
+            intervals: Vec::new(),
+            attachments: Vec::new(),
+            annotations: Vec::new(),
+            created_at: Local::now(),
+            modified_at: Local::now(),
+            completed_at: Some(Local::now()),
+            udas: BTreeMap::new(),
+        }
+    }
+
+    /// Sigo done just now, its tracked time stopped
+    fn completing(task: ReadyTask) -> Self {
+        Self {
+            uuid: task.uuid,
+            id: Some(task.id),
+            priority: task.priority,
+            description: task.get_main_description(),
+            tags: task.tags,
+            project: task.project,
+            assignee: task.assignee,
+            estimate: task.estimate,
+            points: task.points,
+            intervals: utils::stop_intervals(task.intervals),
+            attachments: task.attachments,
+            annotations: task.annotations,
+            created_at: task.created_at,
+            modified_at: Local::now(),
+            completed_at: Some(Local::now()),
+            udas: task.udas,
+        }
+    }
+
+    /// Names and values shown by `sigo info`
+    pub fn info_rows(&self) -> Vec<(String, String)> {
+        let mut rows = vec![
+            ("UUID".to_owned(), self.uuid.to_string()),
+            ("Description".to_owned(), self.description.clone()),
+            ("Priority".to_owned(), self.priority.to_string()),
+            ("Tags".to_owned(), utils::display_tags(&self.tags)),
+            (
+                "Project".to_owned(),
+                utils::display_option_string(&self.project),
+            ),
+            (
+                "Assignee".to_owned(),
+                utils::display_option_string(&self.assignee),
+            ),
+            (
+                "Estimate".to_owned(),
+                utils::display_option_minutes(&self.estimate),
+            ),
+            ("Points".to_owned(), utils::display_option_u32(&self.points)),
+            (
+                "Tracked".to_owned(),
+                utils::display_intervals(&self.intervals),
+            ),
+            ("Attachments".to_owned(), self.attachments.join("\n")),
+            (
+                "Annotations".to_owned(),
+                utils::display_annotations(&self.annotations),
+            ),
+            (
+                "Created".to_owned(),
+                utils::display_datetime(&self.created_at),
+            ),
+            (
+                "Modified".to_owned(),
+                utils::display_datetime(&self.modified_at),
+            ),
+            (
+                "Completed".to_owned(),
+                utils::display_option_datetime(&self.completed_at),
+            ),
+        ];
+        rows.extend(utils::uda_rows(&self.udas));
+        rows
+    }
+
+    pub fn modify(&self, cfg: &MyConfig, modification: &Modification) -> Result<Self, SigoError> {
+        let mut task = self.clone();
+        if let Some(description) = &modification.description {
+            task.description = description.to_owned();
+        }
+        task.priority = modification.priority.unwrap_or(task.priority);
+        modification.apply_tags(&mut task.tags);
+        if let Some(project) = &modification.project {
+            task.project = project.clone();
+        }
+        if let Some(assignee) = &modification.assignee {
+            task.assignee = assignee.clone();
+        }
+        modification.apply_udas(&mut task.udas);
+        task.estimate = modification.estimate.unwrap_or(task.estimate);
+        task.points = modification.points.unwrap_or(task.points);
+        if let Some(annotations) = &modification.annotations {
+            task.annotations = annotations.clone();
+        }
+        task.modified_at = Local::now();
+
+        let mut tasks = Self::read_tasks(cfg)?;
+        for t in tasks.iter_mut().filter(|t| t.uuid == task.uuid) {
+            *t = task.clone();
+        }
+        Self::write_tasks(cfg, tasks)?;
+        Ok(task)
+    }
+}
+
+impl DeletedTask {
+    pub const FILE_NAME: &'static str = "deleted_tasks";
+
+    /// Sigo deleted just now, or expired when its until date has passed
+    fn discarding(task: ReadyTask, expired: bool) -> Self {
+        Self {
+            uuid: task.uuid,
+            priority: task.priority,
+            description: task.get_main_description(),
+            tags: task.tags,
+            project: task.project,
+            assignee: task.assignee,
+            estimate: task.estimate,
+            points: task.points,
+            intervals: utils::stop_intervals(task.intervals),
+            attachments: task.attachments,
+            annotations: task.annotations,
+            created_at: task.created_at,
+            modified_at: Local::now(),
+            udas: task.udas,
+            until: task.until,
+            expired,
+        }
+    }
+
+    pub fn set_description(&self, cfg: &MyConfig, description: &str) -> Result<Self, SigoError> {
+        let mut task = self.clone();
+        task.description = description.to_owned();
+        task.modified_at = Local::now();
+
+        let mut tasks = Self::read_tasks(cfg)?;
+        for t in tasks.iter_mut().filter(|t| t.uuid == task.uuid) {
+            *t = task.clone();
+        }
+        Self::write_tasks(cfg, tasks)?;
+        Ok(task)
+    }
+
+    /// Names and values shown by `sigo info`
+    pub fn info_rows(&self) -> Vec<(String, String)> {
+        let mut rows = vec![
+            ("UUID".to_owned(), self.uuid.to_string()),
+            ("Description".to_owned(), self.description.clone()),
+            ("Priority".to_owned(), self.priority.to_string()),
+            ("Tags".to_owned(), utils::display_tags(&self.tags)),
+            (
+                "Project".to_owned(),
+                utils::display_option_string(&self.project),
+            ),
+            (
+                "Assignee".to_owned(),
+                utils::display_option_string(&self.assignee),
+            ),
+            ("Until".to_owned(), utils::display_option_date(&self.until)),
+            (
+                "Estimate".to_owned(),
+                utils::display_option_minutes(&self.estimate),
+            ),
+            ("Points".to_owned(), utils::display_option_u32(&self.points)),
+            (
+                "Tracked".to_owned(),
+                utils::display_intervals(&self.intervals),
+            ),
+            ("Attachments".to_owned(), self.attachments.join("\n")),
+            (
+                "Annotations".to_owned(),
+                utils::display_annotations(&self.annotations),
+            ),
+            (
+                "Created".to_owned(),
+                utils::display_datetime(&self.created_at),
+            ),
+            (
+                "Modified".to_owned(),
+                utils::display_datetime(&self.modified_at),
+            ),
+        ];
+        rows.extend(utils::uda_rows(&self.udas));
+        rows
+    }
 }
@@ -0,0 +1,113 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::MyConfig,
+    crypt::Crypt,
+    error::SigoError,
+    storage::{self, ensure_on_disk, Backend},
+};
+
+pub const DIR_NAME: &str = "trash";
+/// Older snapshots are dropped, so the trash does not grow forever
+const MAX_SNAPSHOTS: usize = 20;
+
+/// Collections as they were before a destructive command changed them
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken: DateTime<Local>,
+    pub command: String,
+    collections: BTreeMap<String, String>,
+}
+
+/// Directory of the snapshots, each a file named by when it was taken
+fn dir(cfg: &MyConfig) -> PathBuf {
+    PathBuf::from(&cfg.data).join(DIR_NAME)
+}
+
+/// Files of the snapshots, oldest first
+fn list(cfg: &MyConfig) -> Result<Vec<PathBuf>, SigoError> {
+    let dir = dir(cfg);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(&dir).map_err(|e| SigoError::FileReadErr(dir.clone(), e))?;
+    let mut paths = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        // Leaving out those of writes which did not finish
+        .filter(|path| path.is_file() && path.extension().is_none())
+        .collect::<Vec<PathBuf>>();
+    paths.sort();
+    Ok(paths)
+}
+
+fn read(path: &Path, crypt: &Crypt) -> Result<Snapshot, SigoError> {
+    let content =
+        fs::read_to_string(path).map_err(|e| SigoError::FileReadErr(path.to_path_buf(), e))?;
+    serde_json::from_str(&crypt.open(path, content)?)
+        .map_err(|e| SigoError::ParseStrToTasksErr(path.to_path_buf(), e))
+}
+
+/// Put the collections as they are into the trash before a destructive command changes them,
+/// unless they are kept in memory
+pub fn snapshot(cfg: &MyConfig, names: &[String]) -> Result<(), SigoError> {
+    if cfg.storage == Backend::Memory || !storage::may_write()? {
+        return Ok(());
+    }
+    let storage = storage::open(cfg);
+    let mut collections = BTreeMap::new();
+    for name in names {
+        collections.insert(name.clone(), storage.read(name)?);
+    }
+    let taken = Local::now();
+    let snapshot = Snapshot {
+        taken,
+        command: std::env::args().skip(1).collect::<Vec<_>>().join(" "),
+        collections,
+    };
+    let dir = dir(cfg);
+    fs::create_dir_all(&dir).map_err(|e| SigoError::FileCreateErr(dir.clone(), e))?;
+    let content = Crypt::new(cfg).seal(serde_json::to_string(&snapshot)?)?;
+    let path = dir.join(taken.format("%Y%m%d-%H%M%S-%6f").to_string());
+    storage::write_atomically(&path, content.as_bytes(), cfg.durability)?;
+
+    let paths = list(cfg)?;
+    let overflow = paths.len().saturating_sub(MAX_SNAPSHOTS);
+    for path in &paths[..overflow] {
+        fs::remove_file(path).map_err(|e| SigoError::FileWriteErr(path.clone(), e))?;
+    }
+    Ok(())
+}
+
+/// Put back the collections of the last snapshot and drop it
+pub fn restore_last(cfg: &MyConfig) -> Result<Snapshot, SigoError> {
+    ensure_on_disk(cfg, "undo --last-destructive")?;
+    let path = list(cfg)?.pop().ok_or(SigoError::NothingInTrash)?;
+    let snapshot = read(&path, &Crypt::new(cfg))?;
+    let collections = snapshot
+        .collections
+        .iter()
+        .map(|(name, content)| (name.as_str(), content.clone()))
+        .collect::<Vec<(&str, String)>>();
+    storage::open(cfg).write_all(&collections)?;
+    if !storage::may_write()? {
+        return Ok(snapshot);
+    }
+    fs::remove_file(&path).map_err(|e| SigoError::FileWriteErr(path.clone(), e))?;
+    Ok(snapshot)
+}
+
+/// Write the snapshots again, read and written with the keys given
+pub fn rekey(cfg: &MyConfig, crypt: &Crypt) -> Result<(), SigoError> {
+    for path in list(cfg)? {
+        let content = serde_json::to_string(&read(&path, crypt)?)?;
+        storage::write_atomically(&path, crypt.seal(content)?.as_bytes(), cfg.durability)?;
+    }
+    Ok(())
+}
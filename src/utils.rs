@@ -1,43 +1,586 @@
-use std::{collections::HashMap, fs, io::Write, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::{IsTerminal, Write},
+    path::PathBuf,
+};
 
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Weekday};
 use tabled::{
+    builder::Builder,
     grid::config::HorizontalLine,
-    settings::{object::Rows, Padding, Theme},
+    settings::{object::Rows, Color, Padding, Theme},
     Table, Tabled,
 };
 
-use crate::error::SigoError;
+use crate::{
+    error::SigoError,
+    storage::{self, Mode},
+    task::{Annotation, Interval},
+};
 
 pub fn tasks_to_string<I, T>(tasks: I) -> String
 where
     I: IntoIterator<Item = T>,
     T: Tabled,
 {
+    tasks_to_string_with(tasks, &Columns::all(Vec::new()), |_| Vec::new())
+}
+
+/// Columns of a table: the derived ones, extra ones after them and which of them are shown
+pub struct Columns {
+    extra: Vec<String>,
+    shown: Option<Vec<usize>>,
+}
+
+impl Columns {
+    pub fn all(extra: Vec<String>) -> Self {
+        Self { extra, shown: None }
+    }
+
+    /// Only the named columns in that order, or all of them without names
+    pub fn select<T: Tabled>(extra: Vec<String>, names: &[String]) -> Result<Self, SigoError> {
+        if names.is_empty() {
+            return Ok(Self::all(extra));
+        }
+        let headers = T::headers()
+            .into_iter()
+            .map(Cow::into_owned)
+            .chain(extra.iter().cloned())
+            .collect::<Vec<String>>();
+        let shown = names
+            .iter()
+            .map(|name| {
+                let header = column_header(name);
+                headers
+                    .iter()
+                    .position(|h| h.eq_ignore_ascii_case(header))
+                    .ok_or_else(|| SigoError::UnknownColumn(name.to_owned(), headers.join(", ")))
+            })
+            .collect::<Result<Vec<usize>, SigoError>>()?;
+        Ok(Self {
+            extra,
+            shown: Some(shown),
+        })
+    }
+
+    fn pick(&self, record: Vec<String>) -> Vec<String> {
+        match &self.shown {
+            Some(shown) => shown.iter().map(|i| record[*i].clone()).collect(),
+            None => record,
+        }
+    }
+}
+
+/// Header of the column also known by its full name
+fn column_header(name: &str) -> &str {
+    match name.to_lowercase().as_str() {
+        "priority" => "P",
+        "scheduled" => "sched",
+        "depends" => "deps",
+        "estimate" => "est",
+        "points" => "pts",
+        "urgency" => "urg",
+        _ => name,
+    }
+}
+
+/// Sigos on the page, counted from 1, and which of them they are when there are other pages
+pub fn paginate<T>(tasks: Vec<T>, limit: usize, page: usize) -> (Vec<T>, Option<String>) {
+    let total = tasks.len();
+    if total <= limit && page == 1 {
+        return (tasks, None);
+    }
+    let pages = total.div_ceil(limit);
+    let skip = limit.saturating_mul(page - 1);
+    let tasks = tasks.into_iter().skip(skip).take(limit).collect::<Vec<T>>();
+    let footer = if tasks.is_empty() {
+        format!("no sigos on page {} of {}", page, pages)
+    } else {
+        format!(
+            "sigos {}-{} of {} (page {} of {})",
+            skip + 1,
+            skip + tasks.len(),
+            total,
+            page,
+            pages
+        )
+    };
+    (tasks, Some(footer))
+}
+
+/// Render tasks with extra columns after the derived ones, like user defined attributes
+pub fn tasks_to_string_with<I, T, F>(tasks: I, columns: &Columns, fields: F) -> String
+where
+    I: IntoIterator<Item = T>,
+    T: Tabled,
+    F: Fn(&T) -> Vec<String>,
+{
+    build_table(tasks, columns, fields).to_string()
+}
+
+/// Render tasks with the rows colored when printed to a terminal
+pub fn tasks_to_colored_string<T, F>(tasks: Vec<T>, color: F) -> String
+where
+    T: Tabled,
+    F: Fn(&T) -> Option<Color>,
+{
+    let colors = tasks.iter().map(color).collect::<Vec<_>>();
+    let mut table = build_table(tasks, &Columns::all(Vec::new()), |_| Vec::new());
+    if std::io::stdout().is_terminal() {
+        for (row, color) in colors.into_iter().enumerate() {
+            if let Some(color) = color {
+                // The header is the first row
+                table.modify(Rows::single(row + 1), color);
+            }
+        }
+    }
+    table.to_string()
+}
+
+fn build_table<I, T, F>(tasks: I, columns: &Columns, fields: F) -> Table
+where
+    I: IntoIterator<Item = T>,
+    T: Tabled,
+    F: Fn(&T) -> Vec<String>,
+{
+    let mut builder = Builder::default();
+    builder.push_record(
+        columns.pick(
+            T::headers()
+                .into_iter()
+                .map(Cow::into_owned)
+                .chain(columns.extra.iter().cloned())
+                .collect(),
+        ),
+    );
+    for task in tasks {
+        builder.push_record(
+            columns.pick(
+                task.fields()
+                    .into_iter()
+                    .map(Cow::into_owned)
+                    .chain(fields(&task))
+                    .collect(),
+            ),
+        );
+    }
+
     let mut style = Theme::default();
     style.set_lines_horizontal(HashMap::from_iter([(
         1,
         HorizontalLine::full('-', ' ', ' ', ' '),
     )]));
     style.set_border_intersection_top(' ');
-    Table::new(tasks)
+    let mut table = builder.build();
+    table
         .modify(Rows::new(..), Padding::new(0, 0, 0, 0))
-        .with(style)
-        .to_string()
+        .with(style);
+    table
 }
 
-pub fn create_file_if_not_exist(path: &PathBuf) -> Result<(), SigoError> {
-    if !path.is_file() {
-        let mut f =
-            fs::File::create(path).map_err(|e| SigoError::FileCreateErr(path.to_path_buf(), e))?;
-        f.write_all(b"[]")
-            .map_err(|e| SigoError::FileWriteErr(path.to_path_buf(), e))?;
+/// Ask a yes/no question on stdin, defaulting to no
+pub fn confirm(question: &str) -> Result<bool, SigoError> {
+    let answer = prompt(&format!("{} (y/N)", question))?.unwrap_or_default();
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Ask for a line on stdin, `None` at the end of the input
+pub fn prompt(question: &str) -> Result<Option<String>, SigoError> {
+    print!("{} ", question);
+    std::io::stdout().flush().map_err(SigoError::StdinReadErr)?;
+    let mut answer = String::new();
+    let read = std::io::stdin()
+        .read_line(&mut answer)
+        .map_err(SigoError::StdinReadErr)?;
+    Ok(Some(answer.trim().to_owned()).filter(|_| read > 0))
+}
+
+/// Keep URLs as they are and make file paths absolute so they can be opened from anywhere
+pub fn normalize_attachment(attachment: &str) -> Result<String, SigoError> {
+    if attachment.contains("://") {
+        return Ok(attachment.to_owned());
     }
+    let path = fs::canonicalize(attachment)
+        .map_err(|e| SigoError::AttachmentNotFound(PathBuf::from(attachment), e))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Open a file or URL with the platform's default application
+pub fn open_with_platform(target: &str) -> Result<(), SigoError> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command
+        .arg(target)
+        .spawn()
+        .map_err(|e| SigoError::OpenErr(target.to_owned(), e))?;
     Ok(())
 }
 
+/// Create the file as an empty list if it is missing, unless nothing may be written, returning
+/// whether it is there
+pub fn create_file_if_not_exist(path: &PathBuf) -> Result<bool, SigoError> {
+    if path.is_file() {
+        return Ok(true);
+    }
+    if storage::mode() != Mode::Write {
+        return Ok(false);
+    }
+    let mut f =
+        fs::File::create(path).map_err(|e| SigoError::FileCreateErr(path.to_path_buf(), e))?;
+    f.write_all(b"[]")
+        .map_err(|e| SigoError::FileWriteErr(path.to_path_buf(), e))?;
+    Ok(true)
+}
+
+/// Parse a date given as `YYYY-MM-DD`, `today`, `tomorrow` or a weekday name (the next one after today)
+pub fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    let today = Local::now().date_naive();
+    match s.to_lowercase().as_str() {
+        "today" => Ok(today),
+        "tomorrow" => Ok(today + Days::new(1)),
+        word => match word.parse::<Weekday>() {
+            Ok(weekday) => {
+                let days = (weekday.num_days_from_monday() + 6
+                    - today.weekday().num_days_from_monday())
+                    % 7
+                    + 1;
+                Ok(today + Days::new(days.into()))
+            }
+            Err(_) => NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| e.to_string()),
+        },
+    }
+}
+
+/// Parse when a snoozed sigo comes back, either after a duration like `3d` or on a date like
+/// `monday`. Wait dates have no time, so it is tomorrow at the earliest.
+pub fn parse_snooze(s: &str) -> Result<NaiveDate, String> {
+    let date = if s.starts_with(|c: char| c.is_ascii_digit()) {
+        (Local::now() + parse_duration(s)?).date_naive()
+    } else {
+        parse_date(s)?
+    };
+    Ok(date.max(Local::now().date_naive() + Days::new(1)))
+}
+
+/// Parse a month given as `YYYY-MM` into its first day
+pub fn parse_month(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d").map_err(|e| e.to_string())
+}
+
 pub fn display_option_vec_string(o: &Option<Vec<String>>) -> String {
     match o {
         Some(v) => v.join("\n* "),
         None => "No description".to_owned(),
     }
 }
+
+pub fn display_option_string(o: &Option<String>) -> String {
+    match o {
+        Some(s) => s.to_owned(),
+        None => "".to_owned(),
+    }
+}
+
+pub fn display_option_date(o: &Option<NaiveDate>) -> String {
+    match o {
+        Some(d) => d.to_string(),
+        None => "".to_owned(),
+    }
+}
+
+pub fn display_option_datetime(o: &Option<DateTime<Local>>) -> String {
+    match o {
+        Some(d) => display_datetime(d),
+        None => "".to_owned(),
+    }
+}
+
+/// Parse a duration like `30min`, `2h`, `7d`, `1w`, `3mo` or `1y`
+pub fn parse_duration(s: &str) -> Result<chrono::Duration, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number = number
+        .parse::<i64>()
+        .map_err(|_| format!("'{}' does not start with a number", s))?;
+    match unit {
+        "min" => Ok(chrono::Duration::minutes(number)),
+        "h" => Ok(chrono::Duration::hours(number)),
+        "d" => Ok(chrono::Duration::days(number)),
+        "w" => Ok(chrono::Duration::weeks(number)),
+        "mo" => Ok(chrono::Duration::days(number * 30)),
+        "y" => Ok(chrono::Duration::days(number * 365)),
+        _ => Err(format!("unknown unit '{}' (min, h, d, w, mo or y)", unit)),
+    }
+}
+
+pub fn display_datetime(d: &DateTime<Local>) -> String {
+    d.format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// Annotations oldest first, one per line
+pub fn display_annotations(annotations: &[Annotation]) -> String {
+    let mut annotations = annotations.iter().collect::<Vec<_>>();
+    annotations.sort_by_key(|a| a.entry);
+    annotations
+        .iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// User defined attributes as names and values
+pub fn uda_rows(udas: &BTreeMap<String, serde_json::Value>) -> Vec<(String, String)> {
+    udas.iter()
+        .map(|(name, value)| match value {
+            serde_json::Value::String(value) => (name.to_owned(), value.to_owned()),
+            value => (name.to_owned(), value.to_string()),
+        })
+        .collect()
+}
+
+pub fn display_option_u32(o: &Option<u32>) -> String {
+    match o {
+        Some(n) => n.to_string(),
+        None => "".to_owned(),
+    }
+}
+
+pub fn display_option_minutes(o: &Option<i64>) -> String {
+    match o {
+        Some(m) => display_minutes(m),
+        None => "".to_owned(),
+    }
+}
+
+/// Format minutes precisely as hours and minutes (e.g. `1h30min`)
+pub fn display_minutes(minutes: &i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}min", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h{}min", h, m),
+    }
+}
+
+/// Total tracked time, marked while a span is still open
+pub fn display_intervals(intervals: &[Interval]) -> String {
+    if intervals.is_empty() {
+        return "".to_owned();
+    }
+    let total = display_minutes(&intervals.iter().map(|i| i.minutes()).sum());
+    if intervals.iter().any(|i| i.end.is_none()) {
+        format!("{} (active)", total)
+    } else {
+        total
+    }
+}
+
+/// Close the open span, if any
+pub fn stop_intervals(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    for interval in intervals.iter_mut().filter(|i| i.end.is_none()) {
+        interval.end = Some(Local::now());
+    }
+    intervals
+}
+
+pub fn display_age(created_at: &DateTime<Local>) -> String {
+    format_duration(Local::now() - *created_at)
+}
+
+pub fn display_option_age(o: &Option<DateTime<Local>>) -> String {
+    match o {
+        Some(d) => display_age(d),
+        None => "".to_owned(),
+    }
+}
+
+/// Format a duration in its largest unit, like taskwarrior's age column (e.g. `3d`, `2w`)
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let minutes = duration.num_minutes();
+    match minutes {
+        m if m < 60 => format!("{}min", m.max(0)),
+        m if m < 60 * 24 => format!("{}h", m / 60),
+        m if m < 60 * 24 * 14 => format!("{}d", m / (60 * 24)),
+        m if m < 60 * 24 * 90 => format!("{}w", m / (60 * 24 * 7)),
+        m if m < 60 * 24 * 365 => format!("{}mo", m / (60 * 24 * 30)),
+        m => format!("{}y", m / (60 * 24 * 365)),
+    }
+}
+
+/// Date a waiting sigo becomes ready with the time left until then
+pub fn display_wait_until(o: &Option<NaiveDate>) -> String {
+    let Some(date) = o else {
+        return "".to_owned();
+    };
+    match (*date - Local::now().date_naive()).num_days() {
+        days if days <= 0 => format!("{} (today)", date),
+        days => format!(
+            "{} (in {})",
+            date,
+            format_duration(chrono::Duration::days(days))
+        ),
+    }
+}
+
+pub fn display_ids(ids: &[u32]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+pub fn display_tags(tags: &[String]) -> String {
+    tags.join(" ")
+}
+
+/// Words given on the command line, split into their meanings
+#[derive(Debug, Default)]
+pub struct ParsedWords {
+    pub description: String,
+    pub tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    /// Empty when given as `project:`, meaning no project
+    pub project: Option<String>,
+    /// Empty when given as `assignee:`, meaning nobody
+    pub assignee: Option<String>,
+    pub udas: BTreeMap<String, String>,
+}
+
+/// Split command-line words into the description, `+tag`s, `-tag`s, `project:`, `assignee:` and user defined attributes
+pub fn parse_words(words: &[String], udas: &[String]) -> ParsedWords {
+    let mut description = Vec::new();
+    let mut parsed = ParsedWords::default();
+    for word in words {
+        if let Some(tag) = word.strip_prefix('+').filter(|t| !t.is_empty()) {
+            parsed.tags.push(tag.to_owned());
+        } else if let Some(tag) = word.strip_prefix('-').filter(|t| !t.is_empty()) {
+            parsed.remove_tags.push(tag.to_owned());
+        } else if let Some(project) = word.strip_prefix("project:") {
+            parsed.project = Some(project.to_owned());
+        } else if let Some(assignee) = word.strip_prefix("assignee:") {
+            parsed.assignee = Some(assignee.to_owned());
+        } else if let Some((key, value)) = word
+            .split_once(':')
+            .filter(|(key, _)| udas.iter().any(|uda| uda == key))
+        {
+            parsed.udas.insert(key.to_owned(), value.to_owned());
+        } else {
+            description.push(word.as_str());
+        }
+    }
+    parsed.description = description.join(" ");
+    parsed
+}
+
+/// Whether `project` is `filter` itself or one of its sub projects (e.g. `home.garden` for `home`).
+/// An empty filter matches only no project.
+pub fn is_in_project(project: &Option<String>, filter: &str) -> bool {
+    if filter.is_empty() {
+        return project.is_none();
+    }
+    match project {
+        Some(project) => {
+            project == filter
+                || project
+                    .strip_prefix(filter)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        }
+        None => false,
+    }
+}
+
+pub fn group_by_project<T, F>(tasks: Vec<T>, project: F) -> BTreeMap<String, Vec<T>>
+where
+    F: Fn(&T) -> &Option<String>,
+{
+    let mut groups: BTreeMap<String, Vec<T>> = BTreeMap::new();
+    for task in tasks {
+        let name = project(&task)
+            .clone()
+            .unwrap_or_else(|| "(none)".to_owned());
+        groups.entry(name).or_default().push(task);
+    }
+    groups
+}
+
+/// Group tasks under each of their tags, so a task with several tags is in several groups
+pub fn group_by_tags<T, F>(tasks: Vec<T>, tags: F) -> BTreeMap<String, Vec<T>>
+where
+    T: Clone,
+    F: Fn(&T) -> &[String],
+{
+    let mut groups: BTreeMap<String, Vec<T>> = BTreeMap::new();
+    for task in tasks {
+        if tags(&task).is_empty() {
+            groups.entry("(none)".to_owned()).or_default().push(task);
+            continue;
+        }
+        for tag in tags(&task) {
+            groups.entry(tag.to_owned()).or_default().push(task.clone());
+        }
+    }
+    groups
+}
+
+/// Order tasks depth first under their parents, paired with their depth.
+/// Tasks whose parent is not among `tasks` become roots.
+pub fn build_tree<T, I, P>(tasks: Vec<T>, id: I, parent: P) -> Vec<(usize, T)>
+where
+    I: Fn(&T) -> u32,
+    P: Fn(&T) -> Option<u32>,
+{
+    let ids = tasks.iter().map(&id).collect::<HashSet<u32>>();
+    let mut children: HashMap<Option<u32>, Vec<usize>> = HashMap::new();
+    for (i, task) in tasks.iter().enumerate() {
+        let parent = parent(task).filter(|p| ids.contains(p));
+        children.entry(parent).or_default().push(i);
+    }
+
+    let mut slots = tasks.into_iter().map(Some).collect::<Vec<Option<T>>>();
+    let mut ordered = Vec::new();
+    let mut stack: Vec<(usize, usize)> = children
+        .get(&None)
+        .map(|roots| roots.iter().rev().map(|i| (0, *i)).collect())
+        .unwrap_or_default();
+    while let Some((depth, i)) = stack.pop() {
+        let Some(task) = slots[i].take() else {
+            continue;
+        };
+        if let Some(kids) = children.get(&Some(id(&task))) {
+            stack.extend(kids.iter().rev().map(|k| (depth + 1, *k)));
+        }
+        ordered.push((depth, task));
+    }
+    ordered
+}
+
+pub fn grouped_tasks_to_string<T, F>(
+    groups: BTreeMap<String, Vec<T>>,
+    columns: &Columns,
+    fields: F,
+) -> String
+where
+    T: Tabled,
+    F: Fn(&T) -> Vec<String>,
+{
+    groups
+        .into_iter()
+        .map(|(name, tasks)| {
+            format!(
+                "{} ({})\n{}",
+                name,
+                tasks.len(),
+                tasks_to_string_with(tasks, columns, &fields)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n")
+}
@@ -0,0 +1,110 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{Command, Output},
+};
+
+/// Config and data directories of a test, removed when it ends
+struct Sandbox {
+    dir: PathBuf,
+}
+
+impl Sandbox {
+    fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("sigo-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self { dir }
+    }
+
+    fn data(&self) -> PathBuf {
+        self.dir.join("data")
+    }
+
+    fn sigo(&self, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_sigo"))
+            .env("XDG_CONFIG_HOME", self.dir.join("config"))
+            .env("SIGO_HOME", self.data())
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    /// Run the command, failing the test if it fails, returning what it printed
+    fn ok(&self, args: &[&str]) -> String {
+        let output = self.sigo(args);
+        assert!(
+            output.status.success(),
+            "sigo {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    fn tasks_file(&self) -> Vec<u8> {
+        fs::read(self.data().join("tasks")).unwrap()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// A ready sigo 1 and sigo 2 waiting until a date which has passed
+fn with_sigo_to_wake(name: &str) -> Sandbox {
+    let sandbox = Sandbox::new(name);
+    sandbox.ok(&["add", "first"]);
+    sandbox.ok(&["add", "second"]);
+    sandbox.ok(&["wait", "2", "--until", "2020-01-01"]);
+    sandbox
+}
+
+#[test]
+fn read_only_lists_without_waking() {
+    let sandbox = with_sigo_to_wake("read-only");
+    let before = sandbox.tasks_file();
+    let listed = sandbox.ok(&["--read-only", "list"]);
+    assert!(listed.contains("first"));
+    assert!(!listed.contains("second"));
+    assert_eq!(sandbox.tasks_file(), before);
+}
+
+#[test]
+fn read_only_fails_commands_which_write() {
+    let sandbox = with_sigo_to_wake("read-only-write");
+    let before = sandbox.tasks_file();
+    let output = sandbox.sigo(&["--read-only", "add", "third"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(sandbox.tasks_file(), before);
+}
+
+#[test]
+fn dry_run_lists_sigos_it_would_wake() {
+    let sandbox = with_sigo_to_wake("dry-run");
+    let before = sandbox.tasks_file();
+    let output = sandbox.ok(&["--dry-run", "list"]);
+    let (listed, summary) = output.split_once("Dry run").unwrap();
+    assert!(listed.contains("second"));
+    assert!(summary.contains("add sigo 2 \"second\" to ready_tasks"));
+    assert_eq!(sandbox.tasks_file(), before);
+}
+
+#[test]
+fn dry_run_purge_lists_sigos_it_would_remove() {
+    let sandbox = Sandbox::new("dry-run-purge");
+    sandbox.ok(&["add", "finished"]);
+    sandbox.ok(&["done", "1"]);
+    let before = sandbox.tasks_file();
+    let output = sandbox.ok(&["--dry-run", "purge", "--keep-last", "0", "--archive"]);
+    assert!(output.contains("remove sigo 1 \"finished\" from completed_tasks"));
+    assert!(!output.contains("no sigos would change"));
+    assert_eq!(sandbox.tasks_file(), before);
+    assert!(fs::read_dir(sandbox.data()).unwrap().all(|entry| !entry
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .starts_with("completed_")));
+}
@@ -0,0 +1,16 @@
+use clap::Parser;
+use sigo::{command, config::MyConfig, error::SigoError, storage::Backend, AppArg};
+
+/// Config keeping the sigos in memory, apart from those of tests on other threads
+pub fn in_memory() -> MyConfig {
+    MyConfig {
+        storage: Backend::Memory,
+        ..MyConfig::default()
+    }
+}
+
+/// Run the command given after `sigo` on the command line
+pub fn sigo(cfg: &MyConfig, args: &[&str]) -> Result<String, SigoError> {
+    let args = AppArg::try_parse_from(["sigo"].iter().chain(args)).unwrap();
+    command::run(cfg, args)
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use common::{in_memory, sigo};
+use sigo::{
+    error::SigoError,
+    storage,
+    task::{CompletedTask, ReadyTask, RecurringTask},
+    Priority,
+};
+
+fn descriptions(tasks: &[ReadyTask]) -> Vec<String> {
+    tasks.iter().map(|t| t.get_main_description()).collect()
+}
+
+#[test]
+fn add_complete_and_undo() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "first"]).unwrap();
+    sigo(&cfg, &["add", "second"]).unwrap();
+    assert_eq!(
+        descriptions(&ReadyTask::read_tasks(&cfg).unwrap()),
+        ["first", "second"]
+    );
+
+    sigo(&cfg, &["done", "1"]).unwrap();
+    assert_eq!(
+        descriptions(&ReadyTask::read_tasks(&cfg).unwrap()),
+        ["second"]
+    );
+    let completed = CompletedTask::read_tasks(&cfg).unwrap();
+    assert_eq!(completed.len(), 1);
+    assert_eq!(completed[0].description, "first");
+
+    sigo(&cfg, &["undo"]).unwrap();
+    assert_eq!(ReadyTask::read_tasks(&cfg).unwrap().len(), 2);
+    assert!(CompletedTask::read_tasks(&cfg).unwrap().is_empty());
+}
+
+#[test]
+fn completing_twice_fails() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "once"]).unwrap();
+    sigo(&cfg, &["done", "1"]).unwrap();
+    assert!(matches!(
+        sigo(&cfg, &["done", "1"]),
+        Err(SigoError::TaskNotFound(1))
+    ));
+    assert_eq!(CompletedTask::read_tasks(&cfg).unwrap().len(), 1);
+}
+
+#[test]
+fn undo_without_changes_fails() {
+    let cfg = in_memory();
+    assert!(matches!(
+        sigo(&cfg, &["undo"]),
+        Err(SigoError::NothingToUndo)
+    ));
+}
+
+#[test]
+fn transaction_rolls_back_on_error() {
+    let cfg = in_memory();
+    let output = storage::transaction(&cfg, || {
+        ReadyTask::add_task(&cfg, ReadyTask::new(&cfg, "rolled back", Priority::M)?)?;
+        Err::<(), _>(SigoError::NothingToUndo)
+    });
+    assert!(matches!(output, Err(SigoError::NothingToUndo)));
+    assert!(ReadyTask::read_tasks(&cfg).unwrap().is_empty());
+}
+
+#[test]
+fn transaction_keeps_changes_of_partial_failures() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "done"]).unwrap();
+    sigo(&cfg, &["add", "open"]).unwrap();
+    assert!(matches!(
+        sigo(&cfg, &["done", "1", "9"]),
+        Err(SigoError::Failures(..))
+    ));
+    assert_eq!(
+        descriptions(&ReadyTask::read_tasks(&cfg).unwrap()),
+        ["open"]
+    );
+    assert_eq!(CompletedTask::read_tasks(&cfg).unwrap().len(), 1);
+}
+
+#[test]
+fn deleting_a_recurring_sigo_ends_its_recurrence() {
+    let cfg = in_memory();
+    sigo(
+        &cfg,
+        &["add", "water", "--recur", "weekly", "--due", "2026-10-18"],
+    )
+    .unwrap();
+    assert_eq!(
+        sigo(&cfg, &["delete", "1"]).unwrap(),
+        "Deleted sigo 1 and ended its recurrence"
+    );
+    assert!(RecurringTask::read_tasks(&cfg).unwrap().is_empty());
+    sigo(&cfg, &["recur"]).unwrap();
+    assert!(ReadyTask::read_tasks(&cfg).unwrap().is_empty());
+}
+
+#[test]
+fn deleting_keeping_the_series_issues_the_same_date_again() {
+    let cfg = in_memory();
+    sigo(
+        &cfg,
+        &["add", "water", "--recur", "weekly", "--due", "2026-10-18"],
+    )
+    .unwrap();
+    sigo(&cfg, &["delete", "1", "--keep-series"]).unwrap();
+    sigo(&cfg, &["recur"]).unwrap();
+    let tasks = ReadyTask::read_tasks(&cfg).unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].due, "2026-10-18".parse().ok());
+
+    sigo(&cfg, &["done", &tasks[0].id.to_string()]).unwrap();
+    let tasks = ReadyTask::read_tasks(&cfg).unwrap();
+    assert_eq!(tasks[0].due, "2026-10-25".parse().ok());
+}
+
+#[test]
+fn invalid_transitions_fail() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "paused"]).unwrap();
+    sigo(&cfg, &["add", "open"]).unwrap();
+    sigo(&cfg, &["wait", "1"]).unwrap();
+    assert!(matches!(
+        sigo(&cfg, &["wait", "1"]),
+        Err(SigoError::AlreadyWaiting(_))
+    ));
+    assert!(matches!(
+        sigo(&cfg, &["start", "1"]),
+        Err(SigoError::NotReady(_))
+    ));
+    assert!(matches!(
+        sigo(&cfg, &["wait", "1,2"]),
+        Err(SigoError::Failures(waited, failed)) if waited.len() == 1 && failed.len() == 1
+    ));
+}
@@ -0,0 +1,84 @@
+use sigo::{
+    config::MyConfig,
+    error::SigoError,
+    filter::Filter,
+    task::{TaskRef, TaskRefs},
+};
+
+fn refs(s: &str) -> Result<Vec<String>, String> {
+    Ok(s.parse::<TaskRefs>()?
+        .0
+        .iter()
+        .map(TaskRef::to_string)
+        .collect())
+}
+
+fn filter(s: &str) -> Result<Filter, SigoError> {
+    let words = s.split_whitespace().map(str::to_owned).collect::<Vec<_>>();
+    Filter::parse(&MyConfig::default(), &words)
+}
+
+#[test]
+fn task_refs_expand_ranges() {
+    assert_eq!(refs("3,5,7-9").unwrap(), ["3", "5", "7", "8", "9"]);
+    assert_eq!(refs("4-4").unwrap(), ["4"]);
+    assert!(refs("9-7").is_err());
+}
+
+#[test]
+fn task_refs_take_uuid_prefixes() {
+    assert_eq!(refs("5ac23550-e1d7").unwrap(), ["5ac23550-e1d7"]);
+    assert_eq!(refs("5AC23550").unwrap(), ["5ac23550"]);
+    assert_eq!(refs("uuid:5ac2").unwrap(), ["5ac2"]);
+    assert!(matches!(
+        "uuid:5ac2".parse::<TaskRef>(),
+        Ok(TaskRef::Uuid(prefix)) if prefix == "5ac2"
+    ));
+}
+
+#[test]
+fn task_refs_reject_words() {
+    assert!(refs("cafe").is_err());
+    assert!(refs("add").is_err());
+    assert!(refs("uuid:").is_err());
+    assert!(refs("3,next").is_err());
+}
+
+#[test]
+fn filter_binds_not_before_and_before_or() {
+    let parsed = filter("project:home +a or not +b").unwrap();
+    let Filter::Or(left, right) = parsed else {
+        panic!("not an or: {:?}", parsed);
+    };
+    assert!(matches!(
+        *left,
+        Filter::And(ref project, ref tag)
+            if matches!(**project, Filter::Project(ref p) if p == "home")
+                && matches!(**tag, Filter::Tag(ref t) if t == "a")
+    ));
+    assert!(
+        matches!(*right, Filter::Not(ref tag) if matches!(**tag, Filter::Tag(ref t) if t == "b"))
+    );
+}
+
+#[test]
+fn filter_groups_with_parentheses() {
+    let parsed = filter("( +a or +b ) status:ready").unwrap();
+    assert!(matches!(
+        parsed,
+        Filter::And(ref group, ref status)
+            if matches!(**group, Filter::Or(..))
+                && matches!(**status, Filter::Status(ref s) if s == "ready")
+    ));
+}
+
+#[test]
+fn filter_of_nothing_matches_all() {
+    assert!(matches!(filter("").unwrap(), Filter::All));
+}
+
+#[test]
+fn filter_rejects_unbalanced_parentheses() {
+    assert!(matches!(filter("( +a"), Err(SigoError::InvalidFilter(_))));
+    assert!(matches!(filter("+a )"), Err(SigoError::InvalidFilter(_))));
+}
@@ -0,0 +1,162 @@
+use std::{env, fs, path::PathBuf};
+
+use serde_json::json;
+use sigo::{
+    config::MyConfig,
+    error::SigoError,
+    migrations,
+    storage::{self, Backend},
+    task::{CompletedTask, OpenState, ReadyTask, Task},
+    Priority,
+};
+
+/// Data directory of a test, removed when it ends
+struct DataDir(PathBuf);
+
+impl DataDir {
+    fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("sigo-storage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn config(&self) -> MyConfig {
+        MyConfig {
+            data: self.0.to_string_lossy().into_owned(),
+            storage: Backend::Json,
+            ..MyConfig::default()
+        }
+    }
+
+    fn tasks_file(&self) -> PathBuf {
+        self.0.join("tasks")
+    }
+}
+
+impl Drop for DataDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn add(cfg: &MyConfig, description: &str) {
+    let task = ReadyTask::new(cfg, description, Priority::M).unwrap();
+    ReadyTask::add_task(cfg, task).unwrap();
+}
+
+#[test]
+fn migrations_upgrade_a_bare_array() {
+    let file = json!([
+        { "status": "ready", "id": 1, "description": ["old"], "priority": "M" },
+        { "status": "recurring", "id": 1, "description": ["template"] },
+    ]);
+    assert_eq!(migrations::version_of(&file), 0);
+    let upgraded = migrations::upgrade(&PathBuf::from("tasks"), file).unwrap();
+    assert_eq!(migrations::version_of(&upgraded), migrations::VERSION);
+    assert_eq!(upgraded["generation"], 0);
+    assert!(upgraded["tasks"][0]["uuid"].is_string());
+    assert!(upgraded["tasks"][0]["created_at"].is_string());
+    // Templates of recurring sigos have no uuid
+    assert!(upgraded["tasks"][1].get("uuid").is_none());
+}
+
+#[test]
+fn migrations_reject_newer_versions() {
+    let file = json!({ "version": migrations::VERSION + 1, "generation": 0, "tasks": [] });
+    assert!(matches!(
+        migrations::upgrade(&PathBuf::from("tasks"), file),
+        Err(SigoError::NewerFormat(_, version)) if version == migrations::VERSION + 1
+    ));
+}
+
+#[test]
+fn checksum_is_written_and_verified() {
+    let dir = DataDir::new("checksum");
+    let cfg = dir.config();
+    add(&cfg, "kept");
+    let content = fs::read_to_string(dir.tasks_file()).unwrap();
+    assert!(content.contains(r#","checksum":""#));
+    assert_eq!(ReadyTask::read_tasks(&cfg).unwrap().len(), 1);
+
+    fs::write(dir.tasks_file(), content.replace("kept", "kept!")).unwrap();
+    assert!(matches!(
+        ReadyTask::read_tasks(&cfg),
+        Err(SigoError::Corrupted(..))
+    ));
+}
+
+#[test]
+fn checksum_removed_by_hand_is_accepted() {
+    let dir = DataDir::new("no-checksum");
+    let cfg = dir.config();
+    add(&cfg, "edited");
+    let content = fs::read_to_string(dir.tasks_file()).unwrap();
+    let (body, _) = content.split_once(r#","checksum":""#).unwrap();
+    fs::write(
+        dir.tasks_file(),
+        format!("{}}}", body.replace("edited", "by hand")),
+    )
+    .unwrap();
+    let tasks = ReadyTask::read_tasks(&cfg).unwrap();
+    assert_eq!(tasks[0].get_main_description(), "by hand");
+}
+
+#[test]
+fn corrupted_completed_sigos_fail_while_streamed() {
+    let dir = DataDir::new("streamed");
+    let cfg = dir.config();
+    add(&cfg, "finished");
+    let Task::Ready(task) = Task::get_by_id(&cfg, 1).unwrap() else {
+        panic!("sigo 1 is not ready");
+    };
+    task.complete(&cfg).unwrap();
+    let content = fs::read_to_string(dir.tasks_file()).unwrap();
+    fs::write(dir.tasks_file(), content.replace("finished", "finishes")).unwrap();
+    assert!(matches!(
+        CompletedTask::read_matching(&cfg, false, |_| true),
+        Err(SigoError::Corrupted(..))
+    ));
+}
+
+#[test]
+fn lookups_by_id_report_unreadable_sigos() {
+    let dir = DataDir::new("unreadable");
+    let cfg = dir.config();
+    add(&cfg, "broken");
+    let content = fs::read_to_string(dir.tasks_file()).unwrap();
+    let (body, _) = content.split_once(r#","checksum":""#).unwrap();
+    fs::write(
+        dir.tasks_file(),
+        format!(
+            "{}}}",
+            body.replace(r#""priority":"M""#, r#""priority":"Z""#)
+        ),
+    )
+    .unwrap();
+    assert!(matches!(
+        Task::get_by_id(&cfg, 1),
+        Err(SigoError::ParseStrToTasksErr(..))
+    ));
+    assert!(Task::is_id_free(&cfg, 1).is_err());
+}
+
+#[test]
+fn migration_copies_every_collection() {
+    let dir = DataDir::new("migrate");
+    let cfg = dir.config();
+    add(&cfg, "moved");
+    add(&cfg, "finished");
+    let Task::Ready(task) = Task::get_by_id(&cfg, 2).unwrap() else {
+        panic!("sigo 2 is not ready");
+    };
+    task.complete(&cfg).unwrap();
+    storage::migrate(&cfg, Backend::Sqlite).unwrap();
+
+    let cfg = MyConfig {
+        storage: Backend::Sqlite,
+        ..cfg
+    };
+    assert_eq!(ReadyTask::read_tasks(&cfg).unwrap().len(), 1);
+    assert_eq!(CompletedTask::read_tasks(&cfg).unwrap().len(), 1);
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::{in_memory, sigo};
+use sigo::{
+    config::MyConfig,
+    error::SigoError,
+    task::{CompletedTask, DeletedTask, OpenState, ReadyTask, Task, WaitingTask},
+};
+
+fn ready(cfg: &MyConfig, id: u32) -> ReadyTask {
+    match Task::get_by_id(cfg, id).unwrap() {
+        Task::Ready(task) => task,
+        _ => panic!("sigo {} is not ready", id),
+    }
+}
+
+fn waiting(cfg: &MyConfig, id: u32) -> WaitingTask {
+    match Task::get_by_id(cfg, id).unwrap() {
+        Task::Waiting(task) => task,
+        _ => panic!("sigo {} is not waiting", id),
+    }
+}
+
+#[test]
+fn completing_unblocks_ready_and_waiting_dependents() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "first"]).unwrap();
+    sigo(&cfg, &["add", "ready after", "--depends", "1"]).unwrap();
+    sigo(&cfg, &["add", "waiting after", "--depends", "1"]).unwrap();
+    sigo(&cfg, &["wait", "3"]).unwrap();
+
+    ready(&cfg, 1).complete(&cfg).unwrap();
+    assert!(ready(&cfg, 2).depends.is_empty());
+    assert!(waiting(&cfg, 3).depends.is_empty());
+}
+
+#[test]
+fn completing_a_waiting_sigo_keeps_its_annotations() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "postponed"]).unwrap();
+    sigo(&cfg, &["wait", "1", "--reason", "rain"]).unwrap();
+    sigo(&cfg, &["annotate", "1", "note"]).unwrap();
+
+    let completed = waiting(&cfg, 1).complete(&cfg).unwrap();
+    let annotations = completed
+        .annotations
+        .iter()
+        .map(|a| a.description.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(annotations, ["note"]);
+    assert!(WaitingTask::read_tasks(&cfg).unwrap().is_empty());
+    assert_eq!(CompletedTask::read_tasks(&cfg).unwrap().len(), 1);
+}
+
+#[test]
+fn completing_a_stale_sigo_fails() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "once"]).unwrap();
+    let task = ready(&cfg, 1);
+    task.complete(&cfg).unwrap();
+    assert!(matches!(
+        task.complete(&cfg),
+        Err(SigoError::AlreadyCompleted(_))
+    ));
+    assert_eq!(CompletedTask::read_tasks(&cfg).unwrap().len(), 1);
+}
+
+#[test]
+fn moving_back_a_stale_sigo_fails() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "paused"]).unwrap();
+    sigo(&cfg, &["wait", "1"]).unwrap();
+    let task = waiting(&cfg, 1);
+    task.clone().back(&cfg, &None).unwrap();
+    assert!(matches!(
+        task.back(&cfg, &None),
+        Err(SigoError::TaskNotFound(1))
+    ));
+    assert_eq!(ReadyTask::read_tasks(&cfg).unwrap().len(), 1);
+}
+
+#[test]
+fn deleting_and_expiring_move_to_deleted() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "unwanted"]).unwrap();
+    sigo(&cfg, &["add", "outdated"]).unwrap();
+
+    assert!(!ready(&cfg, 1).delete(&cfg).unwrap().expired);
+    assert!(ready(&cfg, 2).expire(&cfg).unwrap().expired);
+    assert!(ReadyTask::read_tasks(&cfg).unwrap().is_empty());
+    assert_eq!(DeletedTask::read_tasks(&cfg).unwrap().len(), 2);
+}
+
+#[test]
+fn restoring_keeps_the_uuid() {
+    let cfg = in_memory();
+    sigo(&cfg, &["add", "again"]).unwrap();
+    let completed = ready(&cfg, 1).complete(&cfg).unwrap();
+
+    let restored = completed.restore(&cfg).unwrap();
+    assert_eq!(restored.uuid, completed.uuid);
+    assert!(CompletedTask::read_tasks(&cfg).unwrap().is_empty());
+    assert!(matches!(
+        completed.restore(&cfg),
+        Err(SigoError::UuidNotFound(_))
+    ));
+}
+
+#[test]
+fn restoring_with_monotonic_ids_issues_no_id_when_keeping_its_own() {
+    let cfg = MyConfig {
+        monotonic_ids: true,
+        ..in_memory()
+    };
+    sigo(&cfg, &["add", "back again"]).unwrap();
+    let completed = ready(&cfg, 1).complete(&cfg).unwrap();
+    assert_eq!(completed.restore(&cfg).unwrap().id, 1);
+    sigo(&cfg, &["add", "next"]).unwrap();
+    assert_eq!(ready(&cfg, 2).get_main_description(), "next");
+}